@@ -7,11 +7,19 @@ pub enum Modifier {
     None,
 }
 
+/// The struct or enum that a derive macro is applied to
+#[derive(Debug)]
+pub enum Item {
+    Struct(Struct),
+    Enum(Enum),
+}
+
 #[derive(Debug)]
 pub struct Struct {
     pub ident: Ident,
     pub modifier: Modifier,
-    pub fields: Vec<Field>,
+    pub generics: Generics,
+    pub fields: Fields,
     pub attrs: Vec<Attribute>,
 }
 
@@ -36,6 +44,144 @@ impl Struct {
     }
 }
 
+#[derive(Debug)]
+pub struct Enum {
+    pub ident: Ident,
+    pub modifier: Modifier,
+    pub generics: Generics,
+    pub variants: Vec<Variant>,
+    pub attrs: Vec<Attribute>,
+}
+
+impl Enum {
+    pub fn attr(&self, ident: &str) -> Option<&Attribute> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.ident.to_string() == ident)
+    }
+
+    pub fn all_attrs(&self, ident: &str) -> Option<Vec<&Attribute>> {
+        let attrs: Vec<&Attribute> = self
+            .attrs
+            .iter()
+            .filter(|attr| attr.ident.to_string() == ident)
+            .collect();
+        if attrs.is_empty() {
+            None
+        } else {
+            Some(attrs)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Variant {
+    pub ident: Ident,
+    pub fields: Fields,
+    pub attrs: Vec<Attribute>,
+}
+
+impl Variant {
+    pub fn attr(&self, ident: &str) -> Option<&Attribute> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.ident.to_string() == ident)
+    }
+
+    pub fn all_attrs(&self, ident: &str) -> Option<Vec<&Attribute>> {
+        let attrs: Vec<&Attribute> = self
+            .attrs
+            .iter()
+            .filter(|attr| attr.ident.to_string() == ident)
+            .collect();
+        if attrs.is_empty() {
+            None
+        } else {
+            Some(attrs)
+        }
+    }
+}
+
+/// The fields of a struct or an enum variant
+#[derive(Debug)]
+pub enum Fields {
+    /// `Name { a: A, b: B }`
+    Named(Vec<Field>),
+    /// `Name(A, B)`, with the attributes of each field
+    Unnamed(Vec<(Type, Vec<Attribute>)>),
+    /// `Name`
+    Unit,
+}
+
+/// The generic parameters and where clause of a struct or enum
+#[derive(Debug, Default)]
+pub struct Generics {
+    pub params: Vec<GenericParam>,
+    /// The predicates of the where clause, e.g. `T: Clone` in `where T: Clone`
+    pub where_predicates: Vec<String>,
+}
+
+impl Generics {
+    /// The parameters to declare on an impl, e.g. `<'a, T: Addr, const N: usize>`
+    pub fn impl_params(&self) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| match p {
+                GenericParam::Lifetime { name, bounds } | GenericParam::Type { name, bounds } => {
+                    if bounds.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}: {}", name, bounds)
+                    }
+                }
+                GenericParam::Const { name, typ } => format!("const {}: {}", name, typ),
+            })
+            .collect();
+        format!("<{}>", params.join(", "))
+    }
+
+    /// The arguments of the type in an impl, e.g. `<'a, T, N>`
+    pub fn type_args(&self) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let args: Vec<&str> = self.params.iter().map(GenericParam::name).collect();
+        format!("<{}>", args.join(", "))
+    }
+
+    /// The names of the type parameters, e.g. `T` of `<'a, T, const N: usize>`
+    pub fn type_params(&self) -> impl Iterator<Item = &str> {
+        self.params.iter().filter_map(|p| match p {
+            GenericParam::Type { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum GenericParam {
+    /// `'a: 'b`
+    Lifetime { name: String, bounds: String },
+    /// `T: Addr`, leaving out any default type
+    Type { name: String, bounds: String },
+    /// `const N: usize`, leaving out any default value
+    Const { name: String, typ: String },
+}
+
+impl GenericParam {
+    pub fn name(&self) -> &str {
+        match self {
+            GenericParam::Lifetime { name, .. }
+            | GenericParam::Type { name, .. }
+            | GenericParam::Const { name, .. } => name,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Field {
     pub ident: Ident,
@@ -92,7 +238,7 @@ impl Attribute {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AttributeOption {
     KeyValue(Ident, AttributeValue),
     Value(AttributeValue),
@@ -109,6 +255,8 @@ pub enum AttributeValue {
     Bool(bool),
     /// Any ident that is not true/false
     Ident(Ident),
+    /// Options nested in parentheses, e.g. `serialize = "..."` in `bound(serialize = "...")`
+    List(Vec<AttributeOption>),
 }
 
 impl AttributeValue {
@@ -121,6 +269,9 @@ impl AttributeValue {
             AttributeValue::Ident(_) => {
                 panic!("called `AttributeValue::unwrap_literal()` on an `Ident` value")
             }
+            AttributeValue::List(_) => {
+                panic!("called `AttributeValue::unwrap_literal()` on a `List` value")
+            }
         }
     }
 
@@ -133,6 +284,9 @@ impl AttributeValue {
             AttributeValue::Ident(_) => {
                 panic!("called `AttributeValue::unwrap_bool()` on an `Ident` value")
             }
+            AttributeValue::List(_) => {
+                panic!("called `AttributeValue::unwrap_bool()` on a `List` value")
+            }
         }
     }
 
@@ -145,6 +299,9 @@ impl AttributeValue {
             AttributeValue::Bool(_) => {
                 panic!("called `AttributeValue::unwrap_ident()` on a `Bool` value")
             }
+            AttributeValue::List(_) => {
+                panic!("called `AttributeValue::unwrap_ident()` on a `List` value")
+            }
         }
     }
 }
@@ -160,6 +317,8 @@ pub struct Type {
     pub ident: Ident,
     /// Full _given_ path, not necessarily fully qualified path
     pub full_path: Vec<PathPart>,
+    /// Lifetime arguments, e.g. `'a` in `Cow<'a, str>`
+    pub lifetimes: Vec<String>,
     pub generics: Vec<Type>,
 }
 
@@ -197,15 +356,21 @@ impl Display for Type {
             }
         }
 
-        if !self.generics.is_empty() {
+        if !self.lifetimes.is_empty() || !self.generics.is_empty() {
             if !last_was_double_colon {
                 write!(f, "::")?;
             }
             write!(f, "<")?;
-            for (idx, g) in self.generics.iter().enumerate() {
+            for (idx, l) in self.lifetimes.iter().enumerate() {
                 if idx > 0 {
                     write!(f, ",")?;
                 }
+                write!(f, "{}", l)?;
+            }
+            for (idx, g) in self.generics.iter().enumerate() {
+                if idx > 0 || !self.lifetimes.is_empty() {
+                    write!(f, ",")?;
+                }
                 g.fmt(f)?;
             }
             write!(f, ">")?;
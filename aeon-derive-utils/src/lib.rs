@@ -4,4 +4,5 @@ mod items;
 mod parser;
 
 pub use items::*;
+pub use parser::parse_item;
 pub use parser::parse_struct as parse_token_stream;
@@ -71,7 +71,7 @@ fn or_unexpected_end(
     match it {
         None => {
             derive_error!(
-                "Unexpected end: Expected a full struct with braces as delimiters",
+                "Unexpected end: Expected a full struct or enum",
                 span.unwrap_or_else(proc_macro::Span::call_site)
             );
         }
@@ -80,12 +80,21 @@ fn or_unexpected_end(
 }
 
 pub fn parse_struct(ts: TokenStream) -> ParseResult<Struct> {
+    match parse_item(ts)? {
+        Item::Struct(s) => Ok(s),
+        Item::Enum(e) => {
+            derive_error!("Expected a struct, found an enum", e.ident.span());
+        }
+    }
+}
+
+pub fn parse_item(ts: TokenStream) -> ParseResult<Item> {
     let mut iter = ts.into_iter().peekable();
 
     let attrs = get_attrs(&mut iter)?;
     if attrs.is_none() {
         derive_error!(
-            "Expected a struct or attribute(s) on struct",
+            "Expected a struct, an enum or attribute(s) on them",
             proc_macro::Span::call_site()
         );
     }
@@ -96,42 +105,211 @@ pub fn parse_struct(ts: TokenStream) -> ParseResult<Struct> {
 
     // if tt was consumed (matched "pub"), get next, otherwise use tt
     let tt = if maybe_pub {
-        or_unexpected_end(iter.next(), Some(tt.span()))?
+        let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
+        // skip the restriction of pub(crate), pub(super) and pub(in path)
+        if matches!(&tt, TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis) {
+            or_unexpected_end(iter.next(), Some(tt.span()))?
+        } else {
+            tt
+        }
     } else {
         tt
     };
-    require_token!(tt, TokenTree::Ident(ref v) if v.to_string() == "struct", tt.span(), "Expected 'struct'");
+    let is_enum = matches!(&tt, TokenTree::Ident(v) if v.to_string() == "enum");
+    require_token!(tt, TokenTree::Ident(ref v) if is_enum || v.to_string() == "struct", tt.span(), "Expected 'struct' or 'enum'");
 
     let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
     let span = tt.span();
-    let struct_ident = require_token!(
+    let ident = require_token!(
         take tt,
         TokenTree::Ident[v],
         tt.span(),
-        "Expected struct identifier (name)"
+        "Expected struct or enum identifier (name)"
     );
+    let modifier = if maybe_pub {
+        Modifier::Pub
+    } else {
+        Modifier::None
+    };
+    let mut generics = get_generics(&mut iter, span)?;
 
     let tt = or_unexpected_end(iter.next(), Some(span))?;
-    let struct_group = require_token!(
-        take tt,
-        TokenTree::Group[v] if matches!(v.delimiter(), Delimiter::Brace),
-        tt.span(),
-        "Expected braces surrounding struct fields (cannot be used on generic structs)");
+    if is_enum {
+        let enum_group = require_token!(
+            take tt,
+            TokenTree::Group[v] if matches!(v.delimiter(), Delimiter::Brace),
+            tt.span(),
+            "Expected braces surrounding enum variants");
 
-    let fields = get_fields(struct_group)?;
+        let variants = get_variants(enum_group)?;
 
-    Ok(Struct {
-        ident: struct_ident,
-        modifier: if maybe_pub {
-            Modifier::Pub
-        } else {
-            Modifier::None
-        },
+        return Ok(Item::Enum(Enum {
+            ident,
+            modifier,
+            generics,
+            variants,
+            attrs,
+        }));
+    }
+
+    let fields = match tt {
+        TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => Fields::Named(get_fields(g)?),
+        TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => {
+            let types = get_tuple_fields(g)?;
+            // the where clause of a tuple struct comes after its fields
+            let where_clause = get_generics(&mut iter, span)?;
+            generics
+                .where_predicates
+                .extend(where_clause.where_predicates);
+            let tt = or_unexpected_end(iter.next(), Some(span))?;
+            require_token!(tt, TokenTree::Punct(p) if p.as_char() == ';', tt.span(), "Expected ';' after tuple struct fields");
+            Fields::Unnamed(types)
+        }
+        TokenTree::Punct(p) if p.as_char() == ';' => Fields::Unit,
+        tt => {
+            derive_error!(
+                "Expected braces or parentheses surrounding struct fields, or ';'",
+                tt.span()
+            );
+        }
+    };
+
+    Ok(Item::Struct(Struct {
+        ident,
+        modifier,
+        generics,
         fields,
         attrs,
+    }))
+}
+
+/// Parses the generic parameters after the name of a struct or enum,
+/// and the where clause before its body, if there are any
+fn get_generics(iter: &mut Peekable<IntoIter>, span: proc_macro::Span) -> ParseResult<Generics> {
+    let mut generics = Generics::default();
+    if matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '<') {
+        let _ = iter.next(); // skip peeked
+        for param in split_generics(iter, span, true)? {
+            generics.params.push(get_generic_param(&param)?);
+        }
+    }
+    if matches!(iter.peek(), Some(TokenTree::Ident(i)) if i.to_string() == "where") {
+        let _ = iter.next(); // skip peeked
+        generics.where_predicates = split_generics(iter, span, false)?
+            .iter()
+            .map(|predicate| tokens_to_string(predicate))
+            .collect();
+    }
+    Ok(generics)
+}
+
+/// Splits the tokens at the commas outside of angle brackets, until the '>' closing the generic
+/// parameters if `in_angle_brackets`, or else until the braces or ';' after a where clause
+fn split_generics(
+    iter: &mut Peekable<IntoIter>,
+    span: proc_macro::Span,
+    in_angle_brackets: bool,
+) -> ParseResult<Vec<Vec<TokenTree>>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut last_was_dash = false;
+    loop {
+        let tt = match iter.peek() {
+            None if in_angle_brackets => {
+                derive_error!(
+                    "Unexpected end: Expected '>' closing the generic parameters",
+                    span
+                );
+            }
+            None => break,
+            Some(tt) => tt.clone(),
+        };
+        let at_body = match &tt {
+            TokenTree::Group(g) => g.delimiter() == Delimiter::Brace,
+            TokenTree::Punct(p) => p.as_char() == ';',
+            _ => false,
+        };
+        if !in_angle_brackets && depth == 0 && at_body {
+            break;
+        }
+        let _ = iter.next(); // skip peeked
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+            // '>' of '->' in e.g. `F: Fn() -> T` doesn't close anything
+            TokenTree::Punct(p) if p.as_char() == '>' && !last_was_dash => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            TokenTree::Punct(p) if p.as_char() == ',' && depth == 0 => {
+                parts.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        last_was_dash = matches!(&tt, TokenTree::Punct(p) if p.as_char() == '-');
+        parts.last_mut().unwrap().push(tt);
+    }
+    parts.retain(|p| !p.is_empty());
+    Ok(parts)
+}
+
+/// Parses `'a: 'b`, `T: Bound = Default` or `const N: usize = 1`
+fn get_generic_param(tokens: &[TokenTree]) -> ParseResult<GenericParam> {
+    // the tokens after the ':' of the parameter, leaving out any default
+    let after_colon = |start: usize| {
+        let mut depth = 0usize;
+        let end = tokens
+            .iter()
+            .position(|tt| match tt {
+                TokenTree::Punct(p) if p.as_char() == '<' => {
+                    depth += 1;
+                    false
+                }
+                TokenTree::Punct(p) if p.as_char() == '>' => {
+                    depth = depth.saturating_sub(1);
+                    false
+                }
+                TokenTree::Punct(p) => p.as_char() == '=' && depth == 0,
+                _ => false,
+            })
+            .unwrap_or(tokens.len());
+        tokens
+            .get(start + 1..end)
+            .map(tokens_to_string)
+            .unwrap_or_default()
+    };
+    Ok(match (tokens.first(), tokens.get(1)) {
+        (Some(TokenTree::Punct(p)), Some(TokenTree::Ident(name))) if p.as_char() == '\'' => {
+            GenericParam::Lifetime {
+                name: format!("'{}", name),
+                bounds: after_colon(2),
+            }
+        }
+        (Some(TokenTree::Ident(c)), Some(TokenTree::Ident(name))) if c.to_string() == "const" => {
+            GenericParam::Const {
+                name: name.to_string(),
+                typ: after_colon(2),
+            }
+        }
+        (Some(TokenTree::Ident(name)), _) => GenericParam::Type {
+            name: name.to_string(),
+            bounds: after_colon(1),
+        },
+        _ => {
+            derive_error!(
+                "Expected a lifetime, type or const generic parameter",
+                tokens[0].span()
+            );
+        }
     })
 }
 
+fn tokens_to_string(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
+
 fn get_attrs(iter: &mut Peekable<IntoIter>) -> ParseResult<Option<Vec<Attribute>>> {
     // overarching parser of attributes
     // handles '#' and then delegates to other functions
@@ -318,7 +496,7 @@ fn get_attr_options(group: Group) -> ParseResult<Vec<AttributeOption>> {
             }
         }
 
-        // value_or_key is now known to be a key and an equal sign is expected
+        // value_or_key is now known to be a key and an equal sign or parentheses are expected
         let attr_option_ident = require_token!(
             take value_or_key,
             TokenTree::Ident[v],
@@ -326,13 +504,20 @@ fn get_attr_options(group: Group) -> ParseResult<Vec<AttributeOption>> {
             "Expected attribute option key"
         );
 
-        // require equal sign
-        //let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
-        require_token!(tt, TokenTree::Punct(ref p) if p.as_char() == '=', tt.span(), "Expected equals and attribute option value");
-        // option =
+        let attr_option_value = match tt {
+            // option(nested = value)
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => {
+                AttributeValue::List(get_attr_options(g)?)
+            }
+            tt => {
+                // require equal sign
+                require_token!(tt, TokenTree::Punct(ref p) if p.as_char() == '=', tt.span(), "Expected equals and attribute option value");
+                // option =
 
-        let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
-        let attr_option_value = tt_to_attr_value(tt)?;
+                let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
+                tt_to_attr_value(tt)?
+            }
+        };
         // option = value
         attr_options.push(AttributeOption::KeyValue(
             attr_option_ident,
@@ -393,7 +578,13 @@ fn get_fields(group: Group) -> ParseResult<Vec<Field>> {
 
         // if tt was consumed (matched "pub"), get next, otherwise use tt
         let tt = if maybe_pub {
-            or_unexpected_end(iter.next(), Some(tt.span()))?
+            let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
+            // skip the restriction of pub(crate), pub(super) and pub(in path)
+            if matches!(&tt, TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis) {
+                or_unexpected_end(iter.next(), Some(tt.span()))?
+            } else {
+                tt
+            }
         } else {
             tt
         };
@@ -432,8 +623,104 @@ fn get_fields(group: Group) -> ParseResult<Vec<Field>> {
     Ok(fields)
 }
 
+fn get_variants(group: Group) -> ParseResult<Vec<Variant>> {
+    let mut variants = Vec::new();
+    let ts = group.stream();
+    let mut iter = ts.into_iter().peekable();
+
+    loop {
+        let attrs = get_attrs(&mut iter)?;
+        if attrs.is_none() {
+            break;
+        }
+        let attrs = attrs.unwrap();
+
+        let tt = or_unexpected_end(iter.next(), None)?;
+        let variant_ident = require_token!(
+            take tt,
+            TokenTree::Ident[v],
+            tt.span(),
+            "Expected variant identifier (name)"
+        );
+
+        let fields = match iter.peek() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => {
+                let g = g.clone();
+                let _ = iter.next(); // skip peeked
+                Fields::Named(get_fields(g)?)
+            }
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                let g = g.clone();
+                let _ = iter.next(); // skip peeked
+                Fields::Unnamed(get_tuple_fields(g)?)
+            }
+            _ => Fields::Unit,
+        };
+        variants.push(Variant {
+            ident: variant_ident,
+            fields,
+            attrs,
+        });
+
+        // skip explicit discriminants, Variant = 1
+        if matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '=') {
+            while iter
+                .peek()
+                .is_some_and(|tt| !matches!(tt, TokenTree::Punct(p) if p.as_char() == ','))
+            {
+                let _ = iter.next();
+            }
+        }
+
+        // comma or end
+        let tt = iter.next();
+        if tt.is_none() {
+            break;
+        }
+        let tt = tt.unwrap();
+        require_token!(tt, TokenTree::Punct(p) if p.as_char() == ',', tt.span(), "Expected ',' or end of enum");
+    }
+
+    Ok(variants)
+}
+
+/// The types of the fields of a tuple, e.g. `(pub A, B)`
+fn get_tuple_fields(group: Group) -> ParseResult<Vec<(Type, Vec<Attribute>)>> {
+    let mut types = Vec::new();
+    let ts = group.stream();
+    let mut iter = ts.into_iter().peekable();
+
+    loop {
+        let attrs = match get_attrs(&mut iter)? {
+            Some(attrs) => attrs,
+            None => break,
+        };
+
+        if matches!(iter.peek(), Some(TokenTree::Ident(s)) if s.to_string() == "pub") {
+            let _ = iter.next(); // skip peeked
+                                 // skip the restriction of pub(crate), pub(super) and pub(in path)
+            if matches!(iter.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+            {
+                let _ = iter.next(); // skip peeked
+            }
+        }
+        types.push((recursive_get_field_type(&mut iter)?, attrs));
+
+        // comma or end
+        let tt = iter.next();
+        if tt.is_none() {
+            break;
+        }
+        let tt = tt.unwrap();
+        require_token!(tt, TokenTree::Punct(p) if p.as_char() == ',', tt.span(), "Expected ',' or end of tuple");
+    }
+
+    Ok(types)
+}
+
 fn recursive_get_field_type(iter: &mut Peekable<IntoIter>) -> ParseResult<Type> {
     let mut full_path = Vec::new();
+    let mut lifetimes = Vec::new();
     let mut generics = Vec::new();
 
     let tt = or_unexpected_end(iter.peek().cloned(), None)?;
@@ -458,10 +745,10 @@ fn recursive_get_field_type(iter: &mut Peekable<IntoIter>) -> ParseResult<Type>
         full_path.push(PathPart::Ident(current_ident.clone()));
 
         match iter.peek() {
-            Some(s) if matches!(s, TokenTree::Punct(p) if p.as_char() == ',' || p.as_char() == '>') =>
-            {
+            Some(TokenTree::Punct(p)) if p.as_char() == ',' || p.as_char() == '>' => {
                 return Ok(Type {
                     ident: current_ident,
+                    lifetimes,
                     generics,
                     full_path,
                 });
@@ -469,6 +756,7 @@ fn recursive_get_field_type(iter: &mut Peekable<IntoIter>) -> ParseResult<Type>
             None => {
                 return Ok(Type {
                     ident: current_ident,
+                    lifetimes,
                     generics,
                     full_path,
                 });
@@ -506,8 +794,20 @@ fn recursive_get_field_type(iter: &mut Peekable<IntoIter>) -> ParseResult<Type>
                 span = tt.span();
 
                 loop {
-                    let generic_type = recursive_get_field_type(iter)?;
-                    generics.push(generic_type);
+                    if matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '\'') {
+                        let tt = iter.next().unwrap(); // skip peeked
+                        let tt = or_unexpected_end(iter.next(), Some(tt.span()))?;
+                        let lifetime = require_token!(
+                            take tt,
+                            TokenTree::Ident[v],
+                            tt.span(),
+                            "Expected lifetime name"
+                        );
+                        lifetimes.push(format!("'{}", lifetime));
+                    } else {
+                        let generic_type = recursive_get_field_type(iter)?;
+                        generics.push(generic_type);
+                    }
 
                     let tt = or_unexpected_end(iter.next(), Some(span))?;
                     span = tt.span();
@@ -516,6 +816,7 @@ fn recursive_get_field_type(iter: &mut Peekable<IntoIter>) -> ParseResult<Type>
                             // generic finished
                             return Ok(Type {
                                 ident: current_ident,
+                                lifetimes,
                                 generics,
                                 full_path,
                             });
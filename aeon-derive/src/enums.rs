@@ -0,0 +1,546 @@
+use crate::utils::{self, Derive, RenameRule, Target};
+use crate::Member;
+use aeon_derive_utils::{Enum, Fields, Variant};
+use proc_macro::TokenStream;
+use std::str::FromStr;
+
+/// How the variants of an enum are told apart when written in Aeon
+enum Representation {
+    /// `Variant(...)` with a macro per variant, or `"Variant"` for unit variants
+    External,
+    /// `{ tag: "Variant", field: ... }`, set with `#[aeon(tag = "tag")]`
+    Internal { tag: String },
+    /// `{ tag: "Variant", content: ... }`, set with `#[aeon(tag = "tag", content = "content")]`
+    Adjacent { tag: String, content: String },
+    /// Only the fields, set with `#[aeon(untagged)]`, deserialized as the first variant that fits
+    Untagged,
+}
+
+impl Representation {
+    fn of(data: &Enum) -> Result<Representation, String> {
+        let string_option = |name: &str| utils::string_option(&data.attrs, name, "kind");
+        utils::check_options(&data.attrs, utils::ENUM_OPTIONS, "enums")?;
+        let tag = string_option("tag")?;
+        let content = string_option("content")?;
+        let untagged = utils::aeon_option(&data.attrs, "untagged").is_some();
+        let representation = match (tag, content) {
+            _ if untagged && utils::aeon_option(&data.attrs, "tag").is_some() => {
+                return Err("`untagged` cannot be combined with `tag`".to_string())
+            }
+            _ if untagged => Representation::Untagged,
+            (Some(tag), Some(content)) => Representation::Adjacent { tag, content },
+            (Some(tag), None) => Representation::Internal { tag },
+            (None, Some(_)) => return Err("`content` needs a `tag` as well".to_string()),
+            (None, None) => Representation::External,
+        };
+        if let Representation::Internal { tag } = &representation {
+            let tuple = data
+                .variants
+                .iter()
+                .find(|v| matches!(v.fields, Fields::Unnamed(_)));
+            if let Some(v) = tuple {
+                return Err(format!(
+                    "Variant `{}` has unnamed fields, which need `content` as well as `tag = \"{}\"`",
+                    v.ident, tag
+                ));
+            }
+        }
+        Ok(representation)
+    }
+}
+
+fn variant_names(variants: &Variants) -> String {
+    let names: Vec<String> = variants
+        .iter()
+        .map(|(_, name, _)| format!("\"{}\"", name))
+        .collect();
+    format!("&[{}]", names.join(", "))
+}
+
+/// The variants of an enum with their names in Aeon, set with `rename`,
+/// and their fields renamed by `rename` and the `rename_all` of the enum
+type Variants<'a> = Vec<(&'a Variant, String, Vec<Member<'a>>)>;
+
+/// The name of the variant in Aeon, which is also the name of its macro
+fn variant_name(v: &Variant) -> Result<String, String> {
+    match utils::string_option(&v.attrs, "rename", "TcpCheck")? {
+        Some(name) if !utils::is_valid_identifier(&name) => Err(format!(
+            "Cannot rename `{}` to `{}`, which is not an identifier in Aeon",
+            v.ident, name
+        )),
+        Some(name) => Ok(name),
+        None => Ok(v.ident.to_string()),
+    }
+}
+
+/// The representation and impl target of the enum, or the compile error explaining why it has none
+fn prepare<'a>(
+    data: &'a Enum,
+    derive: Derive,
+) -> Result<(Representation, Target, Variants<'a>), TokenStream> {
+    let prepared = || -> Result<(Representation, Target, Variants<'a>), String> {
+        let representation = Representation::of(data)?;
+        let target = Target::new(&data.ident, &data.generics, &data.attrs, derive)?;
+        let rename_all = RenameRule::of(&data.attrs)?;
+        let variants = data
+            .variants
+            .iter()
+            .map(|v| {
+                utils::check_options(&v.attrs, utils::VARIANT_OPTIONS, "variants")?;
+                // the fields of a variant follow its own `rename_all`, or else that of the enum
+                let rename_all = RenameRule::of(&v.attrs)?.or(rename_all);
+                Ok((v, variant_name(v)?, Member::all(&v.fields, rename_all)?))
+            })
+            .collect::<Result<Variants, String>>()?;
+        for (idx, (v, name, _)) in variants.iter().enumerate() {
+            if let Some((other, ..)) = variants[..idx].iter().find(|(_, n, _)| n == name) {
+                return Err(format!(
+                    "Variants `{}` and `{}` are both named `{}`",
+                    other.ident, v.ident, name
+                ));
+            }
+        }
+        Ok((representation, target, variants))
+    };
+    prepared().map_err(|err| utils::compile_error(&err))
+}
+
+pub(crate) fn derive_deserialize(data: &Enum) -> TokenStream {
+    let (representation, target, variants) = match prepare(data, Derive::Deserialize) {
+        Ok(ok) => ok,
+        Err(err) => return err,
+    };
+    let name = &target.name;
+    let unknown_variant = |value: &str| {
+        format!(
+            "Err(aeon::convert::unknown_variant(\"{}\", {}, &{}))",
+            name,
+            variant_names(&variants),
+            value
+        )
+    };
+
+    let body = match &representation {
+        Representation::External | Representation::Internal { .. } => {
+            let (read_tag, tag) = match &representation {
+                Representation::Internal { tag } => {
+                    (format!("let tag = {};", required_property(tag)), "tag")
+                }
+                _ => (String::new(), "field"),
+            };
+            let arms: Vec<String> = variants
+                .iter()
+                .map(|(v, name, members)| {
+                    format!("Some(\"{}\") => Ok({}),", name, from_map(v, members))
+                })
+                .collect();
+            format!(
+                r#"
+{}
+match aeon::convert::variant_name(&{}) {{
+    {}
+    _ => {},
+}}
+"#,
+                read_tag,
+                tag,
+                arms.join("\r\n"),
+                unknown_variant(tag)
+            )
+        }
+        Representation::Adjacent { tag, content } => {
+            let arms: Vec<String> = variants
+                .iter()
+                .map(|(v, variant, members)| {
+                    let constructor = match &v.fields {
+                        Fields::Unit => format!("Self::{}", v.ident),
+                        Fields::Named(_) => {
+                            format!("{{ let field = content; {} }}", from_map(v, members))
+                        }
+                        Fields::Unnamed(_) => from_list(name, v, members, "content"),
+                    };
+                    let read_content = match &v.fields {
+                        Fields::Unit => String::new(),
+                        _ => format!("let content = {};", required_property(content)),
+                    };
+                    format!(
+                        "Some(\"{}\") => {{ {} Ok({}) }},",
+                        variant, read_content, constructor
+                    )
+                })
+                .collect();
+            format!(
+                r#"
+let tag = {};
+match aeon::convert::variant_name(&tag) {{
+    {}
+    _ => {},
+}}
+"#,
+                required_property(tag),
+                arms.join("\r\n"),
+                unknown_variant("tag")
+            )
+        }
+        Representation::Untagged => {
+            let attempts: Vec<String> = variants
+                .iter()
+                .map(|(v, variant, members)| match &v.fields {
+                    Fields::Unit => format!(
+                        "if aeon::convert::variant_name(&field) == Some(\"{}\") {{ return Ok(Self::{}); }}",
+                        variant, v.ident
+                    ),
+                    Fields::Named(_) => format!(
+                        r#"
+if matches!(field, aeon::value::AeonValue::Object(_) | aeon::value::AeonValue::Record(..)) {{
+    if let Ok(value) = (|| -> aeon::DeserializeResult<Self> {{ Ok({}) }})() {{
+        return Ok(value);
+    }}
+}}
+"#,
+                        from_map(v, members)
+                    ),
+                    Fields::Unnamed(_) => format!(
+                        r#"
+if let Ok(value) = (|| -> aeon::DeserializeResult<Self> {{ Ok({}) }})() {{
+    return Ok(value);
+}}
+"#,
+                        from_list(name, v, members, "field.clone()")
+                    ),
+                })
+                .collect();
+            format!("{}\r\n{}", attempts.join("\r\n"), unknown_variant("field"))
+        }
+    };
+
+    let expanded = crate::generate_deserialize_impl(&target, &body);
+
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in Deserialize of aeon-derive")
+}
+
+/// The value of the property `name` of `field`, or an error if it's missing
+fn required_property(name: &str) -> String {
+    format!(
+        r#"field
+    .get("{}")
+    .ok_or_else(|| aeon::error::AeonDeserializeError::conversion(
+        "Missing property `{}`".to_string()
+    ))?"#,
+        name, name
+    )
+}
+
+/// Creates the variant from the object or macro call `field`
+fn from_map(v: &Variant, members: &[Member]) -> String {
+    match &v.fields {
+        Fields::Unit => format!("Self::{}", v.ident),
+        _ => format!(
+            "Self::{} {{ {} }}",
+            v.ident,
+            crate::generate_property_assignments(members)
+        ),
+    }
+}
+
+/// Creates the variant with the unnamed fields `members` from the value `source`,
+/// which is a list unless only one field is read
+fn from_list(name: &str, v: &Variant, members: &[Member], source: &str) -> String {
+    let (required, len, items) = crate::generate_item_assignments(members);
+    if len == 1 {
+        let items: Vec<String> = members
+            .iter()
+            .map(|m| {
+                if m.skip_deserializing {
+                    crate::skipped_value(m)
+                } else {
+                    format!("aeon::AeonDeserializeProperty::from_property({})?", source)
+                }
+            })
+            .collect();
+        return format!("Self::{}({})", v.ident, items.join(", "));
+    }
+    format!(
+        r#"{{
+    let mut items = aeon::convert::tuple_items({}, {}, {}, "{}::{}")?;
+    Self::{}({})
+}}"#,
+        source,
+        required,
+        len,
+        name,
+        v.ident,
+        v.ident,
+        items.join(", ")
+    )
+}
+
+pub(crate) fn derive_serialize(data: &Enum) -> TokenStream {
+    let (representation, target, variants) = match prepare(data, Derive::Serialize) {
+        Ok(ok) => ok,
+        Err(err) => return err,
+    };
+    if let Representation::External = representation {
+        // a call missing an argument can't be written with the macro of its variant
+        let conditional = variants.iter().find_map(|(v, _, members)| {
+            let m = members.iter().find(|m| m.skip_serializing_if.is_some())?;
+            Some((v, m))
+        });
+        if let Some((v, m)) = conditional {
+            return utils::compile_error(&format!(
+                "`{}::{}` is written as a macro call, which can't leave out `{}` with `skip_serializing_if`, use #[aeon(tag = \"kind\")] on the enum instead",
+                data.ident, v.ident, m.access
+            ));
+        }
+    }
+
+    let arms: Vec<String> = variants
+        .iter()
+        .map(|(v, name, members)| {
+            format!(
+                "{} => {},",
+                pattern(v, members),
+                serialize_variant(&representation, v, name, members)
+            )
+        })
+        .collect();
+
+    let insert_self_macros: Vec<String> = match representation {
+        Representation::External => variants
+            .iter()
+            .filter_map(|(v, name, members)| {
+                if let Fields::Unit = v.fields {
+                    return None;
+                }
+                Some(crate::generate_insert_self_macro(
+                    name,
+                    &crate::macro_args(members),
+                ))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let create_property_macros = crate::generate_create_macros_fns(
+        &insert_self_macros.join("\r\n"),
+        variants
+            .iter()
+            .flat_map(|(_, _, members)| crate::serialized_types(members)),
+    );
+
+    let expanded = crate::generate_serialize_impl(&target)
+        + format!(
+            r#"       {} {{
+            fn serialize_property(&self) -> aeon::SerializeResult<aeon::value::AeonValue> {{
+                use aeon::value::AeonValue;
+                Ok(match self {{
+                    {}
+                }})
+            }}
+            {}
+        }}
+"#,
+            target.impl_header("aeon::AeonSerializeProperty"),
+            arms.join("\r\n"),
+            create_property_macros
+        )
+        .as_str();
+
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in Serialize of aeon-derive")
+}
+
+/// Binding prefix of the fields of a variant, so they can't shadow the generated variables
+const BINDING: &str = "__";
+
+/// Matches the variant and binds its fields to their names prefixed with [`BINDING`],
+/// e.g. `__0` for the first unnamed field
+fn pattern(v: &Variant, members: &[Member]) -> String {
+    match &v.fields {
+        Fields::Unit => format!("Self::{}", v.ident),
+        _ => {
+            let bindings: Vec<String> = members
+                .iter()
+                .map(|m| format!("{}: {}{}", m.access, BINDING, m.access))
+                .collect();
+            format!("Self::{} {{ {} }}", v.ident, bindings.join(", "))
+        }
+    }
+}
+
+/// Inserts the fields of the variant, bound by [`pattern`], into `obj`
+fn field_insertions(members: &[Member]) -> String {
+    crate::generate_property_hashmap_insertions_for_serialization(members, BINDING)
+}
+
+/// The unnamed fields of the variant that aren't skipped, bound by [`pattern`], as one value,
+/// which is a list unless only one field is written
+fn fields_as_list(members: &[Member]) -> String {
+    let written: Vec<&Member> = members.iter().filter(|m| !m.skip_serializing).collect();
+    if let [m] = written[..] {
+        return format!("{}{}.serialize_property()?", BINDING, m.access);
+    }
+    let items: Vec<String> = written
+        .iter()
+        .map(|m| format!("{}{}.serialize_property()?,", BINDING, m.access))
+        .collect();
+    format!("AeonValue::List(vec![{}])", items.join(" "))
+}
+
+fn serialize_variant(
+    representation: &Representation,
+    v: &Variant,
+    name: &str,
+    members: &[Member],
+) -> String {
+    let new_obj = "let mut obj = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();";
+    let insert_tag = |tag: &str| {
+        format!(
+            "obj.insert(\"{}\".into(), AeonValue::String(\"{}\".into()));",
+            tag, name
+        )
+    };
+    match (representation, &v.fields) {
+        (Representation::External | Representation::Untagged, Fields::Unit) => {
+            format!("AeonValue::String(\"{}\".into())", name)
+        }
+        (Representation::External, _) => format!(
+            "{{ {} {} AeonValue::Record(\"{}\".into(), obj) }}",
+            new_obj,
+            field_insertions(members),
+            name
+        ),
+        (Representation::Internal { tag }, _) => format!(
+            "{{ {} {} {} AeonValue::Object(obj) }}",
+            new_obj,
+            insert_tag(tag),
+            field_insertions(members)
+        ),
+        (Representation::Adjacent { tag, .. }, Fields::Unit) => format!(
+            "{{ {} {} AeonValue::Object(obj) }}",
+            new_obj,
+            insert_tag(tag)
+        ),
+        (Representation::Adjacent { tag, content }, Fields::Named(_)) => format!(
+            "{{ {} {} obj.insert(\"{}\".into(), {{ {} {} AeonValue::Object(obj) }}); AeonValue::Object(obj) }}",
+            new_obj,
+            insert_tag(tag),
+            content,
+            new_obj,
+            field_insertions(members)
+        ),
+        (Representation::Adjacent { tag, content }, Fields::Unnamed(_)) => format!(
+            "{{ {} {} obj.insert(\"{}\".into(), {}); AeonValue::Object(obj) }}",
+            new_obj,
+            insert_tag(tag),
+            content,
+            fields_as_list(members)
+        ),
+        (Representation::Untagged, Fields::Named(_)) => format!(
+            "{{ {} {} AeonValue::Object(obj) }}",
+            new_obj,
+            field_insertions(members)
+        ),
+        (Representation::Untagged, Fields::Unnamed(_)) => fields_as_list(members),
+    }
+}
+
+pub(crate) fn derive_schema(data: &Enum) -> TokenStream {
+    let (representation, target, variants) = match prepare(data, Derive::Schema) {
+        Ok(ok) => ok,
+        Err(err) => return err,
+    };
+    let strings = |names: Vec<String>| {
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|n| format!("AeonValue::String(\"{}\".into())", n))
+            .collect();
+        format!("AeonValue::List(vec![{}])", names.join(", "))
+    };
+    let all: Vec<String> = variants.iter().map(|(_, name, _)| name.clone()).collect();
+    let names = |unit: bool| -> Vec<String> {
+        variants
+            .iter()
+            .filter(|(v, ..)| matches!(v.fields, Fields::Unit) == unit)
+            .map(|(_, name, _)| name.clone())
+            .collect()
+    };
+    let (units, others) = (names(true), names(false));
+
+    let insertions = match &representation {
+        Representation::External => {
+            let mut types = Vec::new();
+            let mut insertions = Vec::new();
+            if !units.is_empty() {
+                types.push("string".to_string());
+            }
+            if !others.is_empty() {
+                types.push("object".to_string());
+                insertions.push(format!(
+                    "node.insert(\"macros\".into(), {});",
+                    strings(others.clone())
+                ));
+            }
+            if !units.is_empty() {
+                insertions.push(format!("node.insert(\"enum\".into(), {});", strings(units)));
+            }
+            insertions.insert(
+                0,
+                format!("node.insert(\"type\".into(), {});", strings(types)),
+            );
+            insertions.join("\r\n")
+        }
+        Representation::Internal { tag } | Representation::Adjacent { tag, .. } => {
+            let content = match &representation {
+                Representation::Adjacent { content, .. } => format!(
+                    r#"
+let mut content = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+content.insert("required".into(), AeonValue::Bool(false));
+properties.insert("{}".into(), AeonValue::Object(content));
+"#,
+                    content
+                ),
+                _ => String::new(),
+            };
+            format!(
+                r#"
+let mut tag = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+tag.insert("type".into(), AeonValue::String("string".into()));
+tag.insert("enum".into(), {});
+let mut properties = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+properties.insert("{}".into(), AeonValue::Object(tag));
+{}
+node.insert("type".into(), AeonValue::String("object".into()));
+node.insert("properties".into(), AeonValue::Object(properties));
+node.insert("additional".into(), AeonValue::Bool(true));
+"#,
+                strings(all),
+                tag,
+                content
+            )
+        }
+        Representation::Untagged => String::new(),
+    };
+    let description = crate::generate_description(&data.attrs);
+
+    let expanded = format!(
+        r#"
+{} {{
+    fn property_schema() -> aeon::value::AeonValue {{
+        use aeon::value::AeonValue;
+        #[allow(unused_mut)]
+        let mut node = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+        {}
+        {}
+        AeonValue::Object(node)
+    }}
+}}
+"#,
+        target.impl_header("aeon::AeonSchema"),
+        insertions,
+        description
+    );
+
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in AeonSchema of aeon-derive")
+}
@@ -1,45 +1,282 @@
+mod enums;
 mod utils;
 
 extern crate proc_macro;
 
-use aeon_derive_utils::{Field, Struct, Type};
-use proc_macro::{Ident, TokenStream};
-use std::collections::HashSet;
+use aeon_derive_utils::{Attribute, AttributeValue, Fields, Item, Struct, Type};
+use proc_macro::TokenStream;
 use std::str::FromStr;
+use utils::{Derive, RenameRule, Target};
+
+/// A field of a struct or variant, e.g. the unnamed field `0` written as the property `f0`
+pub(crate) struct Member<'a> {
+    /// Name of the field in Rust, `0` for the first unnamed field
+    pub access: String,
+    /// Name of the property in Aeon
+    pub prop: String,
+    pub typ: &'a Type,
+    pub attrs: &'a [Attribute],
+    /// The value of the field when its property is missing, instead of an error
+    pub default: Option<String>,
+    /// Set by `skip` or `skip_serializing`, the field is never written
+    pub skip_serializing: bool,
+    /// Set by `skip` or `skip_deserializing`, the field always gets its default value
+    pub skip_deserializing: bool,
+    /// Path to a function taking a reference to the field, which is not written if it returns true
+    pub skip_serializing_if: Option<String>,
+}
+
+impl<'a> Member<'a> {
+    /// The fields, with their property names changed by `rename` or `rename_all`
+    pub(crate) fn all(
+        fields: &'a Fields,
+        rename_all: Option<RenameRule>,
+    ) -> Result<Vec<Member<'a>>, String> {
+        Ok(match fields {
+            Fields::Named(fields) => fields
+                .iter()
+                .map(|f| {
+                    utils::check_options(&f.attrs, utils::FIELD_OPTIONS, "fields")?;
+                    let option = |name: &str| utils::aeon_option(&f.attrs, name).is_some();
+                    Ok(Member {
+                        access: f.ident.to_string(),
+                        prop: utils::property_name(&f.ident, &f.attrs, rename_all)?,
+                        typ: &f.typ,
+                        attrs: &f.attrs,
+                        default: utils::default_value(&f.attrs)?,
+                        skip_serializing: option("skip") || option("skip_serializing"),
+                        skip_deserializing: option("skip") || option("skip_deserializing"),
+                        skip_serializing_if: utils::string_option(
+                            &f.attrs,
+                            "skip_serializing_if",
+                            "Option::is_none",
+                        )?,
+                    })
+                })
+                .collect::<Result<Vec<Member>, String>>()?,
+            Fields::Unnamed(types) => types
+                .iter()
+                .enumerate()
+                .map(|(idx, (typ, attrs))| {
+                    utils::check_options(attrs, utils::TUPLE_FIELD_OPTIONS, "unnamed fields")?;
+                    let option = |name: &str| utils::aeon_option(attrs, name).is_some();
+                    Ok(Member {
+                        access: idx.to_string(),
+                        prop: utils::positional_name(idx),
+                        typ,
+                        attrs,
+                        default: utils::default_value(attrs)?,
+                        skip_serializing: option("skip") || option("skip_serializing"),
+                        skip_deserializing: option("skip") || option("skip_deserializing"),
+                        skip_serializing_if: None,
+                    })
+                })
+                .collect::<Result<Vec<Member>, String>>()?,
+            Fields::Unit => Vec::new(),
+        })
+    }
+}
+
+/// How a struct is written in Aeon
+enum Layout<'a> {
+    /// A macro call or an object of the fields
+    Named(Vec<Member<'a>>),
+    /// A macro call with the positional names of the fields as arguments, or a list
+    Tuple(Vec<Member<'a>>),
+    /// Only the value of the single field, for newtypes and `#[aeon(transparent)]`
+    Transparent(Member<'a>),
+    /// nil
+    Unit,
+}
+
+impl<'a> Layout<'a> {
+    fn of(data: &'a Struct) -> Result<Layout<'a>, String> {
+        utils::check_options(&data.attrs, utils::STRUCT_OPTIONS, "structs")?;
+        let mut members = Member::all(&data.fields, RenameRule::of(&data.attrs)?)?;
+        let transparent = utils::aeon_option(&data.attrs, "transparent").is_some();
+        if transparent && members.len() != 1 {
+            return Err("`transparent` needs a struct with exactly one field".to_string());
+        }
+        let newtype = matches!(data.fields, Fields::Unnamed(_)) && members.len() == 1;
+        if (transparent || newtype)
+            && (members[0].skip_serializing || members[0].skip_deserializing)
+        {
+            return Err(format!(
+                "`{}` is written as its only field, which cannot be skipped",
+                data.ident
+            ));
+        }
+        // missing fields are taken from the default of the struct, see `CONTAINER_DEFAULT`
+        if utils::default_value(&data.attrs)?.is_some() {
+            for m in members.iter_mut().filter(|m| m.default.is_none()) {
+                m.default = Some(format!("{}.{}", CONTAINER_DEFAULT, m.access));
+            }
+        }
+        Ok(match &data.fields {
+            _ if transparent => Layout::Transparent(members.remove(0)),
+            _ if newtype => Layout::Transparent(members.remove(0)),
+            Fields::Named(_) => Layout::Named(members),
+            Fields::Unnamed(_) => Layout::Tuple(members),
+            Fields::Unit => Layout::Unit,
+        })
+    }
+}
+
+/// The default of a struct marked `#[aeon(default)]`, which its missing fields are moved out of
+const CONTAINER_DEFAULT: &str = "__default";
+
+/// The layout and impl target of a struct, or the compile error explaining why it has none
+fn layout_and_target<'a>(
+    data: &'a Struct,
+    derive: Derive,
+) -> Result<(Layout<'a>, Target), TokenStream> {
+    Layout::of(data)
+        .and_then(|layout| {
+            let target = Target::new(&data.ident, &data.generics, &data.attrs, derive)?;
+            Ok((layout, target))
+        })
+        .map_err(|err| utils::compile_error(&err))
+}
 
 #[proc_macro_derive(Deserialize, attributes(aeon))]
 pub fn aeon_deserialize(input: TokenStream) -> TokenStream {
-    let parsed = match aeon_derive_utils::parse_token_stream(input) {
+    let parsed = match aeon_derive_utils::parse_item(input) {
         Err(err) => return err,
+        Ok(Item::Struct(ok)) => ok,
+        Ok(Item::Enum(e)) => return enums::derive_deserialize(&e),
+    };
+
+    let (layout, target) = match layout_and_target(&parsed, Derive::Deserialize) {
         Ok(ok) => ok,
+        Err(err) => return err,
+    };
+
+    let container_default = match utils::default_value(&parsed.attrs) {
+        Ok(Some(value)) => format!("let {}: Self = {};", CONTAINER_DEFAULT, value),
+        _ => String::new(),
+    };
+    let body = match &layout {
+        Layout::Named(members) => format!(
+            r#"
+        {}
+        Ok(Self {{
+            {}
+        }})
+"#,
+            container_default,
+            generate_property_assignments(members)
+        ),
+        Layout::Tuple(members) => {
+            let (required, len, items) = generate_item_assignments(members);
+            format!(
+                r#"
+        {}
+        if let aeon::value::AeonValue::List(_) = field {{
+            let mut items = aeon::convert::tuple_items(field, {}, {}, "{}")?;
+            return Ok(Self({}));
+        }}
+        Ok(Self {{
+            {}
+        }})
+"#,
+                container_default,
+                required,
+                len,
+                target.name,
+                items.join(", "),
+                generate_property_assignments(members)
+            )
+        }
+        Layout::Transparent(member) => format!(
+            "Ok(Self {{ {}: aeon::AeonDeserializeProperty::from_property(field)? }})",
+            member.access
+        ),
+        Layout::Unit => format!(
+            r#"
+        match field {{
+            aeon::value::AeonValue::Nil => Ok(Self),
+            v => Err(aeon::error::AeonDeserializeError::conversion(format!(
+                "Expected nil for `{}`, found {{}}",
+                aeon::value::AeonValue::tag_to_str(v.tag())
+            ))),
+        }}
+"#,
+            target.name
+        ),
     };
 
-    let name = parsed.ident.clone();
+    let expanded = generate_deserialize_impl(&target, &body);
 
-    let property_assignments = generate_property_assignments(&parsed);
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in Deserialize of aeon-derive")
+}
 
-    let expanded = format!(
+/// The `AeonDeserialize` and `AeonDeserializeProperty` impls of `target`,
+/// where `from_property` converts the value `field` with `body`
+fn generate_deserialize_impl(target: &Target, body: &str) -> String {
+    format!(
         r#"
-impl aeon::AeonDeserialize for {} {{
+{} {{
     fn from_aeon(s: String) -> aeon::DeserializeResult<Self> {{
         use aeon::AeonDeserializeProperty;
         aeon::deserialize(s).and_then(|doc| Self::from_property(doc.into()))
     }}
 }}
-impl aeon::AeonDeserializeProperty for {} {{
+{} {{
     fn from_property(field: aeon::value::AeonValue) -> aeon::DeserializeResult<Self> {{
+        #[allow(unused_imports)]
         use aeon::convert::AeonConvert;
-        Ok(Self {{
-            {}
-        }})
+        {}
     }}
 }}
 "#,
-        name, name, property_assignments
-    );
+        target.impl_header("aeon::AeonDeserialize"),
+        target.impl_header("aeon::AeonDeserializeProperty"),
+        body
+    )
+}
 
-    TokenStream::from_str(expanded.as_str())
-        .expect("Internal proc_macro error in Deserialize of aeon-derive")
+/// The fields of a tuple converted from the list items in `items`, and the least and most number
+/// of items, since skipped fields aren't in the list and the last fields may be left out if they
+/// have defaults
+pub(crate) fn generate_item_assignments(members: &[Member]) -> (usize, usize, Vec<String>) {
+    let read: Vec<&Member> = members.iter().filter(|m| !m.skip_deserializing).collect();
+    let required = read
+        .iter()
+        .rposition(|m| m.default.is_none())
+        .map_or(0, |idx| idx + 1);
+    let mut idx = 0;
+    let items = members
+        .iter()
+        .map(|m| {
+            if m.skip_deserializing {
+                return skipped_value(m);
+            }
+            idx += 1;
+            match &m.default {
+                Some(default) if idx > required => format!(
+                    r#"
+match items.next() {{
+    Some(item) => aeon::AeonDeserializeProperty::from_property(item)?,
+    None => {},
+}}
+"#,
+                    default
+                ),
+                _ => "aeon::AeonDeserializeProperty::from_property(items.next().unwrap())?"
+                    .to_string(),
+            }
+        })
+        .collect();
+    (required, read.len(), items)
+}
+
+/// The value of a field that is never read, which is its default
+pub(crate) fn skipped_value(m: &Member) -> String {
+    // skipped fields are never read, so their types don't need to be deserializable
+    m.default
+        .clone()
+        .unwrap_or_else(|| "::std::default::Default::default()".to_string())
 }
 
 /* printing out all attributes in generate_property_assignments:
@@ -65,30 +302,30 @@ impl aeon::AeonDeserializeProperty for {} {{
    }
 */
 
-fn generate_property_assignments(data: &Struct) -> String {
-    let recurse: Vec<String> = data
-        .fields
+fn generate_property_assignments(members: &[Member]) -> String {
+    let recurse: Vec<String> = members
         .iter()
-        .map(|f: &Field| {
-            let name = f.ident.to_string();
-            let prop_assignment = generate_property_assignment_for_type(&f.ident, &f.typ);
-            format!("{}: {},", name, prop_assignment)
+        .map(|m: &Member| {
+            let prop_assignment = match &m.default {
+                _ if m.skip_deserializing => skipped_value(m),
+                Some(default) => generate_property_assignment_with_default(&m.prop, default),
+                None => generate_property_assignment_for_type(&m.prop, m.typ),
+            };
+            format!("{}: {},", m.access, prop_assignment)
         })
         .collect();
     recurse.join("\r\n")
 }
 
-fn generate_property_assignment_for_type(name: &Ident, typ: &Type) -> String {
-    let prop = format!("{:#}", name);
+fn generate_property_assignment_for_type(name: &str, typ: &Type) -> String {
+    let prop = name;
 
     match typ.ident.to_string().as_str() {
+        // nil is None as well, like a missing property
         "Option" => {
             format!(
                 r#"
-field
-    .get("{}")
-    .map(aeon::AeonDeserializeProperty::from_property)
-    .transpose()?
+aeon::convert::maybe(field.get("{}"))?
 "#,
                 prop
             )
@@ -100,7 +337,9 @@ field
     .get("{}")
     .map(aeon::AeonDeserializeProperty::from_property)
     .transpose()?
-    .expect("Failed to deserialize property `{}`")
+    .ok_or_else(|| aeon::error::AeonDeserializeError::conversion(
+        "Missing property `{}`".to_string()
+    ))?
 "#,
                 prop, prop
             )
@@ -108,26 +347,110 @@ field
     }
 }
 
+/// Converts the property `name` of `field`, or evaluates `default` if it's missing
+fn generate_property_assignment_with_default(name: &str, default: &str) -> String {
+    format!(
+        r#"
+match field.get("{}") {{
+    Some(value) => aeon::AeonDeserializeProperty::from_property(value)?,
+    None => {},
+}}
+"#,
+        name, default
+    )
+}
+
 #[proc_macro_derive(Serialize, attributes(aeon))]
 pub fn aeon_serialize(input: TokenStream) -> TokenStream {
-    let parsed = match aeon_derive_utils::parse_token_stream(input) {
+    let parsed = match aeon_derive_utils::parse_item(input) {
         Err(err) => return err,
+        Ok(Item::Struct(ok)) => ok,
+        Ok(Item::Enum(e)) => return enums::derive_serialize(&e),
+    };
+
+    let (layout, target) = match layout_and_target(&parsed, Derive::Serialize) {
         Ok(ok) => ok,
+        Err(err) => return err,
     };
+    let name = &target.macro_name;
 
-    let name = parsed.ident.clone();
+    let (serialize_property, create_property_macros) = match &layout {
+        Layout::Named(members) | Layout::Tuple(members) => {
+            let property_hashmap_insertions =
+                generate_property_hashmap_insertions_for_serialization(members, "self.");
+            let insert_self_macro = generate_insert_self_macro(name, &macro_args(members));
+            (
+                format!(
+                    r#"
+                let mut obj = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+                {}
+                Ok(AeonValue::Record("{}".into(), obj))
+"#,
+                    property_hashmap_insertions, name
+                ),
+                generate_create_macros_fns(&insert_self_macro, serialized_types(members)),
+            )
+        }
+        // the field is written in place of the struct, and so are its macros
+        Layout::Transparent(member) => (
+            format!("self.{}.serialize_property()", member.access),
+            format!(
+                r#"
+            fn create_property_macros(insert_self: bool) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {{
+                <{} as aeon::AeonSerializeProperty>::create_property_macros(insert_self)
+            }}
 
-    let property_hashmap_insertions =
-        generate_property_hashmap_insertions_for_serialization(&parsed);
-    let insert_self_macro = generate_insert_self_macro(&name, &parsed);
-    let create_macros_calls = generate_create_macros_calls(&parsed);
+            fn try_create_property_macros(insert_self: bool) -> aeon::SerializeResult<aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro>> {{
+                <{} as aeon::AeonSerializeProperty>::try_create_property_macros(insert_self)
+            }}
+"#,
+                member.typ, member.typ
+            ),
+        ),
+        Layout::Unit => (
+            "Ok(AeonValue::Nil)".to_string(),
+            r#"
+            fn create_property_macros(_insert_self: bool) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {
+                aeon::ordered_map::OrderedMap::new()
+            }
+"#
+            .to_string(),
+        ),
+    };
 
-    let expanded = format!(
-r#"        impl aeon::AeonSerialize for {} {{
+    let expanded = generate_serialize_impl(&target)
+        + format!(
+            r#"       {} {{
+            fn serialize_property(&self) -> aeon::SerializeResult<aeon::value::AeonValue> {{
+                use aeon::value::AeonValue;
+                {}
+            }}
+            {}
+        }}
+"#,
+            target.impl_header("aeon::AeonSerializeProperty"),
+            serialize_property,
+            create_property_macros
+        )
+        .as_str();
+
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in Serialize of aeon-derive")
+}
+
+/// The `AeonSerialize` impl of `target`, which is the same for every struct and enum
+fn generate_serialize_impl(target: &Target) -> String {
+    format!(
+        r#"        {} {{
             fn to_aeon(&self) -> aeon::SerializeResult<String> {{
                 use aeon::document::AeonDocument;
-                let mut doc = AeonDocument::try_from_object(self.to_aeon_value()?).unwrap();
-                doc.set_macros(Self::create_macros(false));
+                let mut doc = AeonDocument::try_from_object(self.to_aeon_value()?).ok_or_else(|| {{
+                    aeon::error::AeonSerializeError {{
+                        code: aeon::error::AeonSerializeErrorCode::ConversionFailed,
+                        message: "`{}` is not written as an object, so it cannot be a document".to_string(),
+                    }}
+                }})?;
+                doc.set_macros(<Self as aeon::AeonSerializeProperty>::try_create_property_macros(false)?);
                 aeon::serialize(&doc)
             }}
 
@@ -136,66 +459,71 @@ r#"        impl aeon::AeonSerialize for {} {{
                 self.serialize_property()
             }}
 
-            fn create_macros(insert_self: bool) -> std::collections::HashMap<String, aeon::document::AeonMacro> {{
+            fn create_macros(insert_self: bool) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {{
                 use aeon::AeonSerializeProperty;
                 Self::create_property_macros(insert_self)
             }}
         }}
-  "#, name) +
-        format!(
-r#"       impl aeon::AeonSerializeProperty for {} {{
-            fn serialize_property(&self) -> aeon::SerializeResult<aeon::value::AeonValue> {{
-                use aeon::value::AeonValue;
-                let mut obj = std::collections::HashMap::<String, AeonValue>::new();
-                {}
-                Ok(AeonValue::Object(obj))
-            }}
-
-            fn create_property_macros(insert_self: bool) -> std::collections::HashMap<String, aeon::document::AeonMacro> {{
-                use aeon::document::AeonMacro;
-                let mut macros = std::collections::HashMap::<String, AeonMacro>::new();
-                if insert_self {{
-                    {}
-                }}
-                {}
-                macros
-            }}
-        }}
-"#, name, property_hashmap_insertions, insert_self_macro, create_macros_calls).as_str();
-
-    TokenStream::from_str(expanded.as_str())
-        .expect("Internal proc_macro error in Serialize of aeon-derive")
+  "#,
+        target.impl_header("aeon::AeonSerialize"),
+        target.name
+    )
 }
 
-fn generate_property_hashmap_insertions_for_serialization(data: &Struct) -> String {
-    let recurse: Vec<String> = data
-        .fields
+/// Inserts the fields that aren't skipped into `obj`, reading them from `{access}{field name}`
+fn generate_property_hashmap_insertions_for_serialization(
+    members: &[Member],
+    access: &str,
+) -> String {
+    let recurse: Vec<String> = members
         .iter()
-        .map(|f: &Field| {
-            let name = f.ident.to_string();
-            let prop = format!("\"{}\"", name);
-            format!(
+        .filter(|m| !m.skip_serializing)
+        .map(|m: &Member| {
+            let prop = format!("\"{}\"", m.prop);
+            let insertion = format!(
                 r#"
 obj.insert(
     {}.into(),
-    self.{}.serialize_property()?,
+    {}{}.serialize_property()?,
 );
 "#,
-                prop, name
-            )
+                prop, access, m.access
+            );
+            match &m.skip_serializing_if {
+                Some(predicate) => format!(
+                    "if !{}(&{}{}) {{ {} }}",
+                    predicate, access, m.access, insertion
+                ),
+                None => insertion,
+            }
         })
         .collect();
 
     recurse.join("\r\n")
 }
 
-fn generate_insert_self_macro(name: &Ident, data: &Struct) -> String {
-    let recurse: Vec<String> = data
-        .fields
+/// The argument names of the macro of a struct or variant, leaving out skipped fields
+fn macro_args(members: &[Member]) -> Vec<String> {
+    members
         .iter()
-        .map(|f: &Field| {
-            let name = f.ident.to_string();
-            let prop = format!("\"{}\"", name);
+        .filter(|m| !m.skip_serializing)
+        .map(|m| m.prop.clone())
+        .collect()
+}
+
+/// The types of the fields that are written, which create their own macros
+fn serialized_types<'a>(members: &'a [Member]) -> impl Iterator<Item = &'a Type> {
+    members
+        .iter()
+        .filter(|m| !m.skip_serializing)
+        .map(|m| m.typ)
+}
+
+fn generate_insert_self_macro(name: &str, args: &[String]) -> String {
+    let recurse: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            let prop = format!("\"{}\"", arg);
             format!("{},", prop)
         })
         .collect();
@@ -206,7 +534,7 @@ fn generate_insert_self_macro(name: &Ident, data: &Struct) -> String {
         r#"
 macros.insert(
     {}.into(),
-    AeonMacro::new_cloned(
+    aeon::document::AeonMacro::new_cloned(
         {},
         vec![
             {}
@@ -218,22 +546,196 @@ macros.insert(
     )
 }
 
-fn generate_create_macros_calls(data: &Struct) -> String {
-    let recurse: Vec<String> = data
-        .fields
+/// `create_property_macros`, and `try_create_property_macros` which fails on macros of the same
+/// name with different arguments, inserting `insert_self` and then the macros of `field_types`
+fn generate_create_macros_fns<'a>(
+    insert_self: &str,
+    field_types: impl Iterator<Item = &'a Type>,
+) -> String {
+    let mut types: Vec<String> = Vec::new();
+    for t in field_types.flat_map(utils::get_macro_types_from_type) {
+        // keep declaration order so the macros are always written in the same order
+        if !types.contains(&t) {
+            types.push(t);
+        }
+    }
+    let calls = |call: &dyn Fn(&String) -> String| {
+        types.iter().map(call).collect::<Vec<String>>().join("\r\n")
+    };
+    let extend = calls(&|t| {
+        format!(
+            "macros.extend(<{} as aeon::AeonSerializeProperty>::create_property_macros(true));",
+            t
+        )
+    });
+    let merge = calls(&|t| {
+        format!(
+            "aeon::document::merge_macros(&mut macros, <{} as aeon::AeonSerializeProperty>::try_create_property_macros(true)?)?;",
+            t
+        )
+    });
+    format!(
+        r#"
+            fn create_property_macros(insert_self: bool) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {{
+                #[allow(unused_mut)]
+                let mut macros = aeon::ordered_map::OrderedMap::<String, aeon::document::AeonMacro>::new();
+                if insert_self {{
+                    {}
+                }}
+                {}
+                macros
+            }}
+
+            fn try_create_property_macros(insert_self: bool) -> aeon::SerializeResult<aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro>> {{
+                #[allow(unused_mut)]
+                let mut macros = aeon::ordered_map::OrderedMap::<String, aeon::document::AeonMacro>::new();
+                if insert_self {{
+                    {}
+                }}
+                {}
+                Ok(macros)
+            }}
+"#,
+        insert_self, extend, insert_self, merge
+    )
+}
+
+#[proc_macro_derive(AeonSchema, attributes(aeon))]
+pub fn aeon_schema(input: TokenStream) -> TokenStream {
+    let parsed = match aeon_derive_utils::parse_item(input) {
+        Err(err) => return err,
+        Ok(Item::Struct(ok)) => ok,
+        Ok(Item::Enum(e)) => return enums::derive_schema(&e),
+    };
+
+    let (layout, target) = match layout_and_target(&parsed, Derive::Schema) {
+        Ok(ok) => ok,
+        Err(err) => return err,
+    };
+
+    let description = generate_description(&parsed.attrs);
+    let node = match &layout {
+        Layout::Named(members) | Layout::Tuple(members) => {
+            let types = match &layout {
+                Layout::Tuple(_) => format!(
+                    r#"
+        node.insert("type".into(), AeonValue::List(vec![AeonValue::String("object".into()), AeonValue::String("list".into())]));
+        node.insert("min_length".into(), AeonValue::Integer({}));
+        node.insert("max_length".into(), AeonValue::Integer({}));
+"#,
+                    members.len(),
+                    members.len()
+                ),
+                _ => {
+                    r#"node.insert("type".into(), AeonValue::String("object".into()));"#.to_string()
+                }
+            };
+            format!(
+                r#"
+        let mut properties = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+        {}
+        let mut node = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+        {}
+        node.insert("macros".into(), AeonValue::List(vec![AeonValue::String("{}".into())]));
+        node.insert("properties".into(), AeonValue::Object(properties));
+        {}
+        AeonValue::Object(node)
+"#,
+                generate_property_schema_insertions(members),
+                types,
+                target.macro_name,
+                description
+            )
+        }
+        Layout::Transparent(member) => format!(
+            r#"
+        #[allow(unused_mut)]
+        let mut property = <{} as aeon::AeonSchema>::property_schema();
+        if let AeonValue::Object(node) = &mut property {{
+            {}
+        }}
+        property
+"#,
+            member.typ, description
+        ),
+        Layout::Unit => format!(
+            r#"
+        let mut node = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
+        node.insert("type".into(), AeonValue::String("nil".into()));
+        {}
+        AeonValue::Object(node)
+"#,
+            description
+        ),
+    };
+
+    let expanded = format!(
+        r#"
+{} {{
+    fn property_schema() -> aeon::value::AeonValue {{
+        use aeon::value::AeonValue;
+        {}
+    }}
+}}
+"#,
+        target.impl_header("aeon::AeonSchema"),
+        node
+    );
+
+    TokenStream::from_str(expanded.as_str())
+        .expect("Internal proc_macro error in AeonSchema of aeon-derive")
+}
+
+fn generate_property_schema_insertions(members: &[Member]) -> String {
+    let recurse: Vec<String> = members
         .iter()
-        .flat_map(|f: &Field| utils::get_macro_types_from_type(&f.typ))
-        .collect::<HashSet<_>>()
-        .into_iter()
-        .map(|t| {
+        // the schema describes what is read, which skipped fields never are
+        .filter(|m| !m.skip_deserializing)
+        .map(|m: &Member| {
+            let mut node_insertions = generate_description(m.attrs);
+            if m.default.is_some() {
+                node_insertions
+                    .push_str(r#"node.insert("required".into(), AeonValue::Bool(false));"#);
+            }
             format!(
                 r#"
-macros.extend({}::create_property_macros(true));
+{{
+    let mut property = <{} as aeon::AeonSchema>::property_schema();
+    if let AeonValue::Object(node) = &mut property {{
+        {}
+    }}
+    properties.insert("{}".into(), property);
+}}
 "#,
-                t
+                m.typ, node_insertions, m.prop
             )
         })
         .collect();
 
     recurse.join("\r\n")
 }
+
+/// Inserts the doc comments in `attrs` as the description of the schema node `node`,
+/// one line per comment
+fn generate_description(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.ident.to_string() == "doc")
+        .filter_map(|a| match a.first_value() {
+            Some(AttributeValue::Literal(lit)) => Some(format!("{},", lit)),
+            _ => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"
+node.insert(
+    "description".into(),
+    AeonValue::String([{}].iter().map(|l: &&str| l.trim()).collect::<Vec<&str>>().join("\n")),
+);
+"#,
+        lines.join(" ")
+    )
+}
@@ -1,4 +1,6 @@
-use aeon_derive_utils::Type;
+use aeon_derive_utils::{Attribute, AttributeOption, AttributeValue, Generics, Type};
+use proc_macro::{Ident, TokenStream};
+use std::str::FromStr;
 
 fn is_type_builtin(typ: &Type) -> bool {
     matches!(
@@ -17,26 +19,401 @@ fn is_type_builtin(typ: &Type) -> bool {
             | "f32"
     )
 }
+/// Whether the macros of `typ` are only the macros of its generic arguments
+fn is_type_container(typ: &Type) -> bool {
+    matches!(
+        typ.ident.to_string().as_str(),
+        "Option" | "Vec" | "HashMap" | "OrderedMap"
+    )
+}
+
 pub(crate) fn get_macro_types_from_type(typ: &Type) -> Vec<String> {
-    if typ.generics.is_empty() {
-        let name = typ.to_full_path();
-        if !is_type_builtin(typ) {
-            return vec![name];
+    if !is_type_container(typ) {
+        if typ.generics.is_empty() && typ.lifetimes.is_empty() {
+            if !is_type_builtin(typ) {
+                return vec![typ.to_full_path()];
+            }
+            return Vec::new();
         }
-        return Vec::new();
+        // other generic types create their own macros, e.g. `Pool::<Server>`
+        return vec![typ.to_string()];
     }
 
-    let mut types = Vec::new();
-    for g in &typ.generics {
-        if g.generics.is_empty() {
-            if !is_type_builtin(g) {
-                types.push(g.to_full_path());
+    typ.generics
+        .iter()
+        .flat_map(get_macro_types_from_type)
+        .collect()
+}
+
+/// The value of the option `name` of the `#[aeon(...)]` attributes in `attrs`,
+/// e.g. `"kind"` for `#[aeon(tag = "kind")]` or `untagged` for `#[aeon(untagged)]`
+pub(crate) fn aeon_option<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a AttributeValue> {
+    attrs
+        .iter()
+        .filter(|a| a.ident.to_string() == "aeon")
+        .find_map(|a| a.opt_value(name))
+}
+
+/// Options of `#[aeon(...)]` on structs
+pub(crate) const STRUCT_OPTIONS: &[&str] =
+    &["rename", "rename_all", "transparent", "default", "bound"];
+/// Options of `#[aeon(...)]` on enums
+pub(crate) const ENUM_OPTIONS: &[&str] = &["tag", "content", "untagged", "rename_all", "bound"];
+/// Options of `#[aeon(...)]` on enum variants
+pub(crate) const VARIANT_OPTIONS: &[&str] = &["rename", "rename_all"];
+/// Options of `#[aeon(...)]` on fields
+pub(crate) const FIELD_OPTIONS: &[&str] = &[
+    "rename",
+    "default",
+    "skip",
+    "skip_serializing",
+    "skip_deserializing",
+    "skip_serializing_if",
+];
+/// Options of `#[aeon(...)]` on unnamed fields, which have no name to change, and can't be left out
+/// of a list with `skip_serializing_if` without moving the fields after them
+pub(crate) const TUPLE_FIELD_OPTIONS: &[&str] =
+    &["default", "skip", "skip_serializing", "skip_deserializing"];
+
+/// Checks that the `#[aeon(...)]` attributes in `attrs` only have the options in `allowed`,
+/// which is one of the lists above, on the kind of item named by `place`, e.g. "variants"
+pub(crate) fn check_options(
+    attrs: &[Attribute],
+    allowed: &[&str],
+    place: &str,
+) -> Result<(), String> {
+    let opts = attrs
+        .iter()
+        .filter(|a| a.ident.to_string() == "aeon")
+        .flat_map(|a| a.opts.iter());
+    for opt in opts {
+        let name = match opt {
+            AttributeOption::KeyValue(id, _)
+            | AttributeOption::Value(AttributeValue::Ident(id)) => id.to_string(),
+            AttributeOption::Value(_) => {
+                return Err(
+                    "Expected the name of an option, e.g. #[aeon(rename = \"...\")]".to_string(),
+                )
             }
+        };
+        if allowed.contains(&name.as_str()) {
+            continue;
+        }
+        let known = [STRUCT_OPTIONS, ENUM_OPTIONS, VARIANT_OPTIONS, FIELD_OPTIONS]
+            .iter()
+            .any(|options| options.contains(&name.as_str()));
+        return Err(if known {
+            format!("`{}` cannot be used on {}", name, place)
         } else {
-            let additional_types = get_macro_types_from_type(g);
-            types.extend(additional_types);
+            format!(
+                "Unknown option `{}`, expected one of {}",
+                name,
+                allowed.join(", ")
+            )
+        });
+    }
+    Ok(())
+}
+
+/// The contents of a string literal, e.g. `kind` for `"kind"`
+pub(crate) fn literal_string(value: &AttributeValue) -> Option<String> {
+    match value {
+        AttributeValue::Literal(lit) => {
+            let lit = lit.to_string();
+            let contents = lit.strip_prefix('"')?.strip_suffix('"')?;
+            Some(contents.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The value of the option `name` if it's a string, or an error showing `example` if it's not
+pub(crate) fn string_option(
+    attrs: &[Attribute],
+    name: &str,
+    example: &str,
+) -> Result<Option<String>, String> {
+    match aeon_option(attrs, name) {
+        None => Ok(None),
+        Some(value) => literal_string(value).map(Some).ok_or_else(|| {
+            format!(
+                "`{}` must be a string, e.g. #[aeon({} = \"{}\")]",
+                name, name, example
+            )
+        }),
+    }
+}
+
+/// Whether `name` can be written as a property and a macro argument,
+/// i.e. it starts with an ASCII letter followed by letters, digits, '_' and '-',
+/// where every '-' is followed by a letter, digit or '_'
+pub(crate) fn is_valid_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && !name.ends_with('-')
+        && !name.contains("--")
+        && !matches!(name, "nil" | "true" | "false")
+}
+
+/// The property name of a field, from `#[aeon(rename = "...")]` or the field name itself
+pub(crate) fn property_name(
+    ident: &Ident,
+    attrs: &[Attribute],
+    rename_all: Option<RenameRule>,
+) -> Result<String, String> {
+    if let Some(name) = string_option(attrs, "rename", "max-conns")? {
+        if !is_valid_identifier(&name) {
+            return Err(format!(
+                "Cannot rename `{}` to `{}`, which is not an identifier in Aeon",
+                ident, name
+            ));
+        }
+        return Ok(name);
+    }
+    let name = ident.to_string();
+    let name = name.strip_prefix("r#").unwrap_or(&name);
+    match rename_all {
+        Some(rule) => {
+            let renamed = rule.apply(name);
+            if renamed != name && !is_valid_identifier(&renamed) {
+                return Err(format!(
+                    "`rename_all` turns `{}` into `{}`, which is not an identifier in Aeon",
+                    name, renamed
+                ));
+            }
+            Ok(renamed)
+        }
+        None => Ok(name.to_string()),
+    }
+}
+
+/// The expression filling in a missing value, from `#[aeon(default)]`
+/// or `#[aeon(default = "path::to::fn")]`
+pub(crate) fn default_value(attrs: &[Attribute]) -> Result<Option<String>, String> {
+    match aeon_option(attrs, "default") {
+        None => Ok(None),
+        Some(value @ AttributeValue::Literal(_)) => match literal_string(value) {
+            Some(path) if !path.trim().is_empty() => Ok(Some(format!("{}()", path.trim()))),
+            _ => Err(
+                "`default` must be a path to a function, e.g. #[aeon(default = \"default_port\")]"
+                    .to_string(),
+            ),
+        },
+        Some(_) => Ok(Some("::std::default::Default::default()".to_string())),
+    }
+}
+
+/// The case of the property names of the fields, set with `#[aeon(rename_all = "...")]`
+#[derive(Clone, Copy)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+const RENAME_RULES: &[(&str, RenameRule)] = &[
+    ("lowercase", RenameRule::Lower),
+    ("UPPERCASE", RenameRule::Upper),
+    ("PascalCase", RenameRule::Pascal),
+    ("camelCase", RenameRule::Camel),
+    ("snake_case", RenameRule::Snake),
+    ("SCREAMING_SNAKE_CASE", RenameRule::ScreamingSnake),
+    ("kebab-case", RenameRule::Kebab),
+    ("SCREAMING-KEBAB-CASE", RenameRule::ScreamingKebab),
+];
+
+impl RenameRule {
+    pub(crate) fn of(attrs: &[Attribute]) -> Result<Option<RenameRule>, String> {
+        let rule = match string_option(attrs, "rename_all", "kebab-case")? {
+            None => return Ok(None),
+            Some(rule) => rule,
+        };
+        match RENAME_RULES.iter().find(|(name, _)| *name == rule) {
+            Some((_, rule)) => Ok(Some(*rule)),
+            None => {
+                let names: Vec<&str> = RENAME_RULES.iter().map(|(name, _)| *name).collect();
+                Err(format!(
+                    "Unknown `rename_all` case \"{}\", expected one of {}",
+                    rule,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Converts the snake_case name of a field
+    pub(crate) fn apply(self, field: &str) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        };
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field.split('_').map(capitalize).collect(),
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Name of the property of the unnamed field `idx`, which has to be an identifier
+/// since it's also the name of a macro argument
+pub(crate) fn positional_name(idx: usize) -> String {
+    format!("f{}", idx)
+}
+
+pub(crate) fn compile_error(message: &str) -> TokenStream {
+    TokenStream::from_str(format!("compile_error!({:?});", message).as_str())
+        .expect("Internal proc_macro error in compile_error of aeon-derive")
+}
+
+/// The struct or enum that impls are generated for
+pub(crate) struct Target {
+    /// Name of the struct or enum, without generics
+    pub name: String,
+    /// Name of the macro of a struct, set with `#[aeon(rename = "...")]`
+    pub macro_name: String,
+    params: String,
+    ty: String,
+    where_clause: String,
+}
+
+/// The trait being derived, which bounds the type parameters of its impls
+#[derive(Clone, Copy)]
+pub(crate) enum Derive {
+    Serialize,
+    Deserialize,
+    Schema,
+}
+
+impl Derive {
+    /// The bound of every type parameter, unless replaced with `#[aeon(bound = "...")]`
+    fn bound(self) -> &'static str {
+        match self {
+            Derive::Serialize => "aeon::AeonSerializeProperty",
+            Derive::Deserialize => "aeon::AeonDeserializeProperty",
+            Derive::Schema => "aeon::AeonSchema",
+        }
+    }
+
+    /// The key of the bounds of this derive in `#[aeon(bound(serialize = "..."))]`
+    fn key(self) -> &'static str {
+        match self {
+            Derive::Serialize => "serialize",
+            Derive::Deserialize => "deserialize",
+            Derive::Schema => "schema",
+        }
+    }
+}
+
+/// The bounds replacing those of `derive`, from `#[aeon(bound = "...")]` for every derive
+/// or `#[aeon(bound(serialize = "...", deserialize = "...", schema = "..."))]` for each one
+fn bound_option(attrs: &[Attribute], derive: Derive) -> Result<Option<String>, String> {
+    let opts = match aeon_option(attrs, "bound") {
+        Some(AttributeValue::List(opts)) => opts,
+        _ => return string_option(attrs, "bound", "T: Clone"),
+    };
+    let mut bounds = None;
+    for opt in opts {
+        let (key, value) = match opt {
+            AttributeOption::KeyValue(key, value) => (key.to_string(), value),
+            AttributeOption::Value(_) => {
+                return Err(
+                    "Expected bounds per derive, e.g. #[aeon(bound(serialize = \"T: Clone\"))]"
+                        .to_string(),
+                )
+            }
+        };
+        if !matches!(key.as_str(), "serialize" | "deserialize" | "schema") {
+            return Err(format!(
+                "Unknown bound `{}`, expected serialize, deserialize or schema",
+                key
+            ));
+        }
+        let value = literal_string(value).ok_or_else(|| {
+            format!(
+                "`{}` must be a string, e.g. #[aeon(bound({} = \"T: Clone\"))]",
+                key, key
+            )
+        })?;
+        if key == derive.key() {
+            bounds = Some(value);
         }
     }
+    Ok(bounds)
+}
 
-    types
+impl Target {
+    /// Bounds every type parameter by the trait being derived, e.g. `T: aeon::AeonSerializeProperty`,
+    /// unless `#[aeon(bound = "...")]` replaces the bounds
+    pub(crate) fn new(
+        ident: &Ident,
+        generics: &Generics,
+        attrs: &[Attribute],
+        derive: Derive,
+    ) -> Result<Target, String> {
+        let mut predicates = generics.where_predicates.clone();
+        match bound_option(attrs, derive)? {
+            Some(bounds) => {
+                let bounds = bounds.trim().trim_end_matches(',');
+                if !bounds.is_empty() {
+                    predicates.push(bounds.to_string());
+                }
+            }
+            None => predicates.extend(
+                generics
+                    .type_params()
+                    .map(|t| format!("{}: {}", t, derive.bound())),
+            ),
+        }
+        let macro_name = match string_option(attrs, "rename", "TcpCheck")? {
+            Some(name) if !is_valid_identifier(&name) => {
+                return Err(format!(
+                    "Cannot rename `{}` to `{}`, which is not an identifier in Aeon",
+                    ident, name
+                ))
+            }
+            Some(name) => name,
+            None => ident.to_string(),
+        };
+        let where_clause = if predicates.is_empty() {
+            String::new()
+        } else {
+            format!("where {}", predicates.join(", "))
+        };
+        Ok(Target {
+            name: ident.to_string(),
+            macro_name,
+            params: generics.impl_params(),
+            ty: format!("{}{}", ident, generics.type_args()),
+            where_clause,
+        })
+    }
+
+    /// The start of an impl of `trait_path` for the target, up to its opening brace
+    pub(crate) fn impl_header(&self, trait_path: &str) -> String {
+        format!(
+            "impl{} {} for {} {}",
+            self.params, trait_path, self.ty, self.where_clause
+        )
+    }
 }
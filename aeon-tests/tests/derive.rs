@@ -3,8 +3,11 @@ extern crate aeon_derive;
 
 #[cfg(test)]
 mod tests {
+    use aeon::convert::{AeonConvert, AeonObjectConvert};
+    use aeon::schema::Schema;
+    use aeon::value::AeonValue;
     use aeon::{AeonDeserialize, AeonSerialize};
-    use aeon_derive::{Deserialize, Serialize};
+    use aeon_derive::{AeonSchema, Deserialize, Serialize};
     use std::collections::HashMap;
 
     // TODO: #[aeon] for custom deserializing/serializing function for specific property?
@@ -23,6 +26,7 @@ mod tests {
     );
 );
 
+    #[allow(dead_code)]
     #[derive(Deserialize, Serialize)]
     /// it's fine to have a comment here
     /// multiple even
@@ -96,6 +100,7 @@ mod tests {
         thingy: HashMap<String, HashMap<String, OtherDerive>>,
         maybe: Option<bool>,
     }
+
     #[test]
     pub fn test_serialize2() {
         let test = TestDerive {
@@ -135,4 +140,1007 @@ mod tests {
                 .unwrap()
         )
     }
+
+    #[test]
+    pub fn test_deserialize_missing_property_is_error() {
+        let aeon = "veco: [\"hi\"]".to_string();
+        let err = OtherDerive::from_aeon(aeon).err().expect("expected error");
+        assert_eq!("Missing property `do_it`", err.message);
+    }
+
+    #[test]
+    pub fn test_deserialize_out_of_range_is_error() {
+        let aeon = "bytes: [256] some_ip: \"\" thingy: {} maybe: nil".to_string();
+        assert!(TestDerive::from_aeon(aeon).is_err());
+    }
+
+    #[test]
+    pub fn test_serialize_fields_in_declaration_order() {
+        let test = TestDerive {
+            bytes: vec![],
+            some_ip: String::from("1.2.3.4"),
+            thingy: HashMap::new(),
+            maybe: Some(true),
+        };
+        let serialized: String = test.to_aeon().unwrap();
+        let positions: Vec<usize> = ["bytes:", "some_ip:", "thingy:", "maybe:"]
+            .iter()
+            .map(|p| serialized.find(p).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", serialized);
+    }
+
+    #[test]
+    pub fn test_serialize_remembers_macro_name() {
+        let other = OtherDerive {
+            veco: vec![],
+            do_it: true,
+        };
+        let value = other.to_aeon_value().unwrap();
+        assert_eq!(Some("OtherDerive"), value.macro_name());
+    }
+
+    #[allow(dead_code)]
+    #[derive(AeonSchema, Deserialize)]
+    /// A server to connect to
+    pub struct SchemaServer {
+        /// Name or address of the server
+        pub host: String,
+        pub port: u16,
+    }
+
+    #[allow(dead_code)]
+    #[derive(AeonSchema, Deserialize)]
+    pub struct SchemaConfig {
+        pub name: String,
+        /// Seconds to wait,
+        /// or nil to wait forever
+        pub timeout: Option<f64>,
+        pub servers: Vec<SchemaServer>,
+        pub tags: HashMap<String, Option<i8>>,
+    }
+
+    #[test]
+    pub fn test_schema_describes_fields() {
+        let doc = Schema::document_of::<SchemaConfig>();
+        assert_eq!(
+            vec!["name", "timeout", "servers", "tags"],
+            doc.properties.keys().collect::<Vec<_>>()
+        );
+        let string = |s: &str| Some(AeonValue::String(s.into()));
+        assert_eq!(string("string"), doc.get_path("name/type"));
+        assert_eq!(string("double?"), doc.get_path("timeout/type"));
+        assert_eq!(
+            Some(AeonValue::Bool(false)),
+            doc.get_path("timeout/required")
+        );
+        assert_eq!(
+            string("Seconds to wait,\nor nil to wait forever"),
+            doc.get_path("timeout/description")
+        );
+        assert_eq!(
+            string("SchemaServer"),
+            doc.get_path("servers/items/macros/0")
+        );
+        assert_eq!(
+            string("A server to connect to"),
+            doc.get_path("servers/items/description")
+        );
+        assert_eq!(
+            string("Name or address of the server"),
+            doc.get_path("servers/items/properties/host/description")
+        );
+        assert_eq!(
+            Some(AeonValue::Integer(65535)),
+            doc.get_path("servers/items/properties/port/max")
+        );
+        assert_eq!(string("int?"), doc.get_path("tags/values/type"));
+        assert!(aeon::serialize(&doc).is_ok());
+    }
+
+    #[test]
+    pub fn test_schema_validates_before_deserializing() {
+        let schema = Schema::of::<SchemaConfig>().unwrap();
+        let valid = r#"
+        @SchemaServer(host, port)
+        name: "app"
+        servers: [SchemaServer("a", 80), { host: "b", port: 81 }]
+        tags: { fast: 1, slow: nil }
+        "#;
+        assert_eq!(0, schema.validate_source(valid).unwrap().len());
+        assert!(SchemaConfig::from_aeon(valid.to_string()).is_ok());
+
+        let invalid = r#"
+        @other(host, port)
+        name: 1
+        timeout: 5
+        servers: [other("a", 80), { host: "b", port: 65536 }]
+        tags: { fast: 128 }
+        "#;
+        let violations: Vec<String> = schema
+            .validate_source(invalid)
+            .unwrap()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "Expected string, found int at 'name'",
+                "Expected double?, found int at 'timeout'",
+                "Macro 'other' is not allowed here, expected one of SchemaServer at 'servers/0'",
+                "Must be at most 65535, found 65536 at 'servers/1/port'",
+                "Must be at most 127, found 128 at 'tags/fast'",
+            ],
+            violations
+        );
+        assert!(SchemaConfig::from_aeon(invalid.to_string()).is_err());
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Shape {
+        Empty,
+        Circle { radius: f64 },
+        Rect(i64, i64),
+        Named(String),
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "kind")]
+    pub enum Source {
+        Stdin,
+        File { path: String, follow: Option<bool> },
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "t", content = "c")]
+    pub enum Size {
+        Auto,
+        Fixed(i64),
+        Range(i64, i64),
+        Scaled { factor: f64 },
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(untagged)]
+    pub enum Port {
+        Any,
+        Number(u16),
+        Range(u16, u16),
+        Named { service: String },
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Drawing {
+        shapes: Vec<Shape>,
+        source: Source,
+        sizes: Vec<Size>,
+        ports: Vec<Port>,
+    }
+
+    fn drawing() -> Drawing {
+        Drawing {
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle { radius: 1.5 },
+                Shape::Rect(2, 3),
+                Shape::Named("star".into()),
+            ],
+            source: Source::File {
+                path: "a.svg".into(),
+                follow: None,
+            },
+            sizes: vec![
+                Size::Auto,
+                Size::Fixed(10),
+                Size::Range(1, 5),
+                Size::Scaled { factor: 0.5 },
+            ],
+            ports: vec![
+                Port::Any,
+                Port::Number(80),
+                Port::Range(8000, 8080),
+                Port::Named {
+                    service: "http".into(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    pub fn test_enums_round_trip() {
+        let serialized = drawing().to_aeon().unwrap();
+        assert!(serialized.contains("@Circle(radius)"), "{}", serialized);
+        assert!(serialized.contains("@Rect(f0, f1)"), "{}", serialized);
+        assert!(!serialized.contains("@File"), "{}", serialized);
+        let deserialized = Drawing::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(drawing(), deserialized, "{}", serialized);
+    }
+
+    #[test]
+    pub fn test_enum_schemas() {
+        let schema = Schema::of::<Drawing>().unwrap();
+        let serialized = drawing().to_aeon().unwrap();
+        assert_eq!(0, schema.validate_source(&serialized).unwrap().len());
+        let doc = Schema::document_of::<Drawing>();
+        let string = |s: &str| Some(AeonValue::String(s.into()));
+        assert_eq!(string("string"), doc.get_path("shapes/items/type/0"));
+        assert_eq!(string("Rect"), doc.get_path("shapes/items/macros/1"));
+        assert_eq!(
+            string("File"),
+            doc.get_path("source/properties/kind/enum/1")
+        );
+
+        let invalid = r#"
+        @Square(side)
+        shapes: [Square(1), "Square"]
+        source: { kind: "Pipe" }
+        sizes: []
+        ports: []
+        "#;
+        let violations: Vec<String> = schema
+            .validate_source(invalid)
+            .unwrap()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "Macro 'Square' is not allowed here, expected one of Circle, Rect, Named at 'shapes/0'",
+                "Expected one of \"Empty\", found \"Square\" at 'shapes/1'",
+                "Expected one of \"Stdin\", \"File\", found \"Pipe\" at 'source/kind'",
+            ],
+            violations
+        );
+    }
+
+    #[test]
+    pub fn test_enum_representations() {
+        let aeon = r#"
+        @Circle(radius)
+        @Rect(f0, f1)
+        shapes: ["Empty", Circle(1.5), Rect(2, 3), { Named: "star" }]
+        source: { kind: "File", path: "a.svg" }
+        sizes: [{ t: "Auto" }, { t: "Fixed", c: 10 }, { t: "Range", c: [1, 5] }, { t: "Scaled", c: { factor: 0.5 } }]
+        ports: ["Any", 80, [8000, 8080], { service: "http" }]
+        "#;
+        let err = Drawing::from_aeon(aeon.to_string()).unwrap_err();
+        assert_eq!("Expected a variant of `Shape`, found object", err.message);
+
+        let aeon = aeon.replace("{ Named: \"star\" }", "Named(\"star\")");
+        let aeon = format!("@Named(f0)\n{}", aeon);
+        assert_eq!(drawing(), Drawing::from_aeon(aeon).unwrap());
+    }
+
+    #[test]
+    pub fn test_unknown_variant_is_error() {
+        let err = Source::from_aeon("kind: \"Pipe\"".to_string()).unwrap_err();
+        assert_eq!("Unknown variant `Pipe` of `Source`", err.message);
+        assert_eq!(
+            Some("the variants are `Stdin`, `File`"),
+            err.help.as_deref()
+        );
+        let err = Source::from_aeon("path: \"a\"".to_string()).unwrap_err();
+        assert_eq!("Missing property `kind`", err.message);
+        let aeon = "shapes: [1] source: { kind: \"Stdin\" } sizes: [] ports: []";
+        let err = Drawing::from_aeon(aeon.to_string()).unwrap_err();
+        assert_eq!("Expected a variant of `Shape`, found int", err.message);
+        let aeon = "shapes: [] source: { kind: \"Stdin\" } sizes: [] ports: [true]";
+        let err = Drawing::from_aeon(aeon.to_string()).unwrap_err();
+        assert_eq!("Expected a variant of `Port`, found bool", err.message);
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Pool<T> {
+        items: Vec<T>,
+        spare: Option<T>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Endpoint<A: Clone>
+    where
+        A: std::fmt::Debug,
+    {
+        pool: Pool<A>,
+        primary: A,
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Label<'a> {
+        text: std::borrow::Cow<'a, str>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Reply<T, E> {
+        Done(T),
+        Failed { error: E },
+    }
+
+    // the bounds of the wrapped `Pool` are enough, so `T` needs no bounds of its own
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(bound = "Pool<T>: aeon::AeonSerializeProperty + aeon::AeonDeserializeProperty")]
+    pub struct Shared<T> {
+        pool: Pool<T>,
+    }
+
+    // only serializing uses the bounds of `Pool`, the other derives keep `T: Trait`
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(bound(serialize = "Pool<T>: aeon::AeonSerializeProperty"))]
+    pub struct Mirrored<T> {
+        pool: Pool<T>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    pub struct Backend {
+        host: String,
+    }
+
+    fn endpoint() -> Endpoint<Backend> {
+        let server = |host: &str| Backend { host: host.into() };
+        Endpoint {
+            pool: Pool {
+                items: vec![server("a"), server("b")],
+                spare: None,
+            },
+            primary: server("main"),
+        }
+    }
+
+    #[test]
+    pub fn test_generics_round_trip() {
+        let serialized = endpoint().to_aeon().unwrap();
+        assert!(serialized.contains("@Pool(items, spare)"), "{}", serialized);
+        assert!(serialized.contains("@Backend(host)"), "{}", serialized);
+        let deserialized = Endpoint::<Backend>::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(endpoint(), deserialized, "{}", serialized);
+
+        let label = Label { text: "tag".into() };
+        let deserialized = Label::from_aeon(label.to_aeon().unwrap()).unwrap();
+        assert_eq!(label, deserialized);
+
+        let replies: Vec<Reply<i64, String>> = vec![
+            Reply::Done(7),
+            Reply::Failed {
+                error: "timeout".into(),
+            },
+        ];
+        let shared = Shared {
+            pool: Pool {
+                items: replies,
+                spare: Some(Reply::Done(0)),
+            },
+        };
+        let serialized = shared.to_aeon().unwrap();
+        let deserialized = Shared::<Reply<i64, String>>::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(shared, deserialized, "{}", serialized);
+
+        let mirrored = Mirrored {
+            pool: Pool {
+                items: vec![1u8, 2],
+                spare: None,
+            },
+        };
+        let serialized = mirrored.to_aeon().unwrap();
+        let deserialized = Mirrored::<u8>::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(mirrored, deserialized, "{}", serialized);
+    }
+
+    #[test]
+    pub fn test_generic_schemas() {
+        let schema = Schema::document_of::<Pool<u8>>();
+        assert_eq!(Some(255), schema.get_path("items/items/max").int());
+        assert_eq!(Some("int?".into()), schema.get_path("spare/type").str());
+        let label = Schema::document_of::<Label>();
+        assert_eq!(Some("string".into()), label.get_path("text/type").str());
+        let mirrored = Schema::document_of::<Mirrored<u8>>();
+        assert_eq!(
+            Some(255),
+            mirrored.get_path("pool/properties/items/items/max").int()
+        );
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct ListenPort(u16);
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct ServerId(pub(crate) String);
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Point(i64, i64);
+
+    /// Shown to users
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(transparent)]
+    pub struct DisplayName {
+        value: String,
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Marker;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Tagged<T>(T, String)
+    where
+        T: Clone;
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Listener {
+        port: ListenPort,
+        ids: Vec<ServerId>,
+        origin: Point,
+        name: DisplayName,
+        marker: Marker,
+    }
+
+    fn listener() -> Listener {
+        Listener {
+            port: ListenPort(8080),
+            ids: vec![ServerId("a".into()), ServerId("b".into())],
+            origin: Point(1, -2),
+            name: DisplayName {
+                value: "main".into(),
+            },
+            marker: Marker,
+        }
+    }
+
+    #[test]
+    pub fn test_tuple_and_unit_structs_round_trip() {
+        let serialized = listener().to_aeon().unwrap();
+        assert!(serialized.contains("port: 8080"), "{}", serialized);
+        assert!(serialized.contains("@Point(f0, f1)"), "{}", serialized);
+        assert!(serialized.contains("name: \"main\""), "{}", serialized);
+        assert!(serialized.contains("marker: nil"), "{}", serialized);
+        let deserialized = Listener::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(listener(), deserialized, "{}", serialized);
+
+        let tagged = Tagged(7, "corner".into());
+        let serialized = tagged.to_aeon().unwrap();
+        assert!(serialized.contains("f0: 7"), "{}", serialized);
+        assert_eq!(tagged, Tagged::from_aeon(serialized).unwrap());
+    }
+
+    #[test]
+    pub fn test_tuple_structs_from_lists() {
+        let aeon = r#"
+        port: 80
+        ids: ["a"]
+        origin: [5, 6]
+        name: "x"
+        marker: nil
+        "#;
+        let listener = Listener::from_aeon(aeon.to_string()).unwrap();
+        assert_eq!(Point(5, 6), listener.origin);
+        assert_eq!(ListenPort(80), listener.port);
+
+        let err = Listener::from_aeon(aeon.replace("[5, 6]", "[5, 6, 7]")).unwrap_err();
+        assert_eq!("Expected 2 items for `Point`, found 3", err.message);
+        let err = Listener::from_aeon(aeon.replace("nil", "1")).unwrap_err();
+        assert_eq!("Expected nil for `Marker`, found int", err.message);
+        let err = Listener::from_aeon(aeon.replace("80", "80000")).unwrap_err();
+        assert!(err.message.contains("80000"), "{}", err.message);
+
+        let err = ListenPort(80).to_aeon().unwrap_err();
+        assert_eq!(
+            "`ListenPort` is not written as an object, so it cannot be a document",
+            err.message
+        );
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct PortRange(u16, #[aeon(default)] u16);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "t", content = "c")]
+    pub enum Retry {
+        Times(u8, #[aeon(default = "default_backoff")] f64),
+    }
+
+    fn default_backoff() -> f64 {
+        0.5
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Forward {
+        ports: PortRange,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct RetryPolicy {
+        retry: Retry,
+    }
+
+    #[test]
+    pub fn test_unnamed_fields_with_defaults_may_be_left_out() {
+        let forward = Forward::from_aeon("ports: [80]".to_string()).unwrap();
+        assert_eq!(PortRange(80, 0), forward.ports);
+        let forward = Forward::from_aeon("ports: [80, 90]".to_string()).unwrap();
+        assert_eq!(PortRange(80, 90), forward.ports);
+        let forward = Forward::from_aeon("ports: { f0: 80 }".to_string()).unwrap();
+        assert_eq!(PortRange(80, 0), forward.ports);
+        let err = Forward::from_aeon("ports: []".to_string()).unwrap_err();
+        assert_eq!(
+            "Expected 1 to 2 items for `PortRange`, found 0",
+            err.message
+        );
+
+        let policy = RetryPolicy::from_aeon(r#"retry: { t: "Times", c: [3] }"#.to_string());
+        assert_eq!(Retry::Times(3, 0.5), policy.unwrap().retry);
+
+        let schema = Schema::document_of::<Forward>();
+        assert_eq!(
+            Some(false),
+            schema.get_path("ports/properties/f1/required").bool()
+        );
+    }
+
+    #[test]
+    pub fn test_tuple_and_unit_struct_schemas() {
+        let schema = Schema::document_of::<Listener>();
+        assert_eq!(Some("int".into()), schema.get_path("port/type").str());
+        assert_eq!(Some(65535), schema.get_path("port/max").int());
+        assert_eq!(
+            Some("string".into()),
+            schema.get_path("ids/items/type").str()
+        );
+        assert_eq!(Some(2), schema.get_path("origin/min_length").int());
+        assert_eq!(
+            Some("int".into()),
+            schema.get_path("origin/properties/f1/type").str()
+        );
+        assert_eq!(
+            Some("Shown to users".into()),
+            schema.get_path("name/description").str()
+        );
+        assert_eq!(Some("nil".into()), schema.get_path("marker/type").str());
+
+        let schema = Schema::of::<Listener>().unwrap();
+        let aeon = "port: 80 ids: [] origin: [1, 2] name: \"x\" marker: nil";
+        let doc = aeon::deserialize(aeon.to_string()).unwrap();
+        assert!(schema.validate(&doc).is_empty());
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(rename_all = "kebab-case")]
+    pub struct PoolLimits {
+        #[aeon(rename = "max-conns")]
+        max_connections: u32,
+        idle_timeout: f64,
+        r#type: String,
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(rename_all = "camelCase")]
+    pub struct Cluster {
+        node_limits: Vec<PoolLimits>,
+        fallback_route: Route,
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
+    pub enum Route {
+        Direct { target_host: String },
+        Proxy { proxy_host: String, retry_count: u8 },
+    }
+
+    fn cluster() -> Cluster {
+        Cluster {
+            node_limits: vec![PoolLimits {
+                max_connections: 64,
+                idle_timeout: 2.5,
+                r#type: "tcp".into(),
+            }],
+            fallback_route: Route::Proxy {
+                proxy_host: "gateway".into(),
+                retry_count: 3,
+            },
+        }
+    }
+
+    #[test]
+    pub fn test_renamed_fields_round_trip() {
+        let serialized = cluster().to_aeon().unwrap();
+        assert!(
+            serialized.contains("@PoolLimits(max-conns, idle-timeout, type)"),
+            "{}",
+            serialized
+        );
+        assert!(serialized.contains("nodeLimits: ["), "{}", serialized);
+        assert!(
+            serialized.contains("PROXY_HOST: \"gateway\""),
+            "{}",
+            serialized
+        );
+        let deserialized = Cluster::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(cluster(), deserialized, "{}", serialized);
+
+        let aeon = r#"
+        nodeLimits: [{ max-conns: 64, idle-timeout: 2.5, type: "tcp" }]
+        fallbackRoute: { kind: "Proxy", PROXY_HOST: "gateway", RETRY_COUNT: 3 }
+        "#;
+        assert_eq!(cluster(), Cluster::from_aeon(aeon.to_string()).unwrap());
+        let err = Cluster::from_aeon(aeon.replace("max-conns", "max_connections")).unwrap_err();
+        assert_eq!("Missing property `max-conns`", err.message);
+    }
+
+    #[test]
+    pub fn test_renamed_field_schemas() {
+        let schema = Schema::document_of::<Cluster>();
+        assert_eq!(
+            Some("int".into()),
+            schema
+                .get_path("nodeLimits/items/properties/max-conns/type")
+                .str()
+        );
+        assert!(schema
+            .get_path("nodeLimits/items/properties/idle-timeout")
+            .is_some());
+        let schema = Schema::of::<Cluster>().unwrap();
+        assert!(schema
+            .validate(&aeon::deserialize(cluster().to_aeon().unwrap()).unwrap())
+            .is_empty());
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Mode {
+        #[aeon(rename = "fast-mode")]
+        Fast,
+        #[aeon(rename = "tuned", rename_all = "kebab-case")]
+        Tuned { worker_count: u8 },
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Modes {
+        m: Mode,
+        fallback: Mode,
+    }
+
+    #[test]
+    pub fn test_renamed_variants_round_trip() {
+        let modes = Modes {
+            m: Mode::Fast,
+            fallback: Mode::Tuned { worker_count: 4 },
+        };
+        let serialized = modes.to_aeon().unwrap();
+        assert!(
+            serialized.contains("@tuned(worker-count)"),
+            "{}",
+            serialized
+        );
+        assert!(serialized.contains("m: \"fast-mode\""), "{}", serialized);
+        let deserialized = Modes::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(modes, deserialized, "{}", serialized);
+
+        let err = Modes::from_aeon(serialized.replace("fast-mode", "Fast")).unwrap_err();
+        assert_eq!("Unknown variant `Fast` of `Mode`", err.message);
+        let schema = Schema::document_of::<Modes>();
+        let string = |s: &str| Some(AeonValue::String(s.into()));
+        assert_eq!(string("fast-mode"), schema.get_path("m/enum/0"));
+        assert_eq!(string("tuned"), schema.get_path("m/macros/0"));
+    }
+
+    fn default_port() -> u16 {
+        8080
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct HttpConfig {
+        host: String,
+        #[aeon(default = "default_port")]
+        port: u16,
+        #[aeon(default)]
+        aliases: Vec<String>,
+        #[aeon(default)]
+        timeout: Option<f64>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(default)]
+    pub struct Tuning {
+        workers: u8,
+        queue: u32,
+        #[aeon(default)]
+        verbose: bool,
+        label: Option<String>,
+    }
+
+    impl Default for Tuning {
+        fn default() -> Self {
+            Tuning {
+                workers: 4,
+                queue: 128,
+                verbose: true,
+                label: Some("default".into()),
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_missing_fields_use_defaults() {
+        let config = HttpConfig::from_aeon("host: \"localhost\"".to_string()).unwrap();
+        assert_eq!(
+            HttpConfig {
+                host: "localhost".into(),
+                port: 8080,
+                aliases: Vec::new(),
+                timeout: None,
+            },
+            config
+        );
+        let aeon = "host: \"a\" port: 80 aliases: [\"b\"] timeout: 1.5";
+        let config = HttpConfig::from_aeon(aeon.to_string()).unwrap();
+        assert_eq!(80, config.port);
+        assert_eq!(vec!["b".to_string()], config.aliases);
+        let err = HttpConfig::from_aeon("port: 80".to_string()).unwrap_err();
+        assert_eq!("Missing property `host`", err.message);
+
+        let tuning = Tuning::from_aeon("queue: 16".to_string()).unwrap();
+        assert_eq!(
+            Tuning {
+                workers: 4,
+                queue: 16,
+                verbose: false,
+                label: Some("default".into()),
+            },
+            tuning
+        );
+        let tuning = Tuning::from_aeon("label: nil".to_string()).unwrap();
+        assert_eq!(None, tuning.label);
+    }
+
+    #[test]
+    pub fn test_fields_with_defaults_are_optional_in_schemas() {
+        let schema = Schema::document_of::<HttpConfig>();
+        assert_eq!(None, schema.get_path("host/required"));
+        assert_eq!(Some(false), schema.get_path("port/required").bool());
+        assert_eq!(Some(false), schema.get_path("aliases/required").bool());
+        let violations = Schema::of::<HttpConfig>()
+            .unwrap()
+            .validate_source("host: \"localhost\"")
+            .unwrap();
+        assert!(violations.is_empty());
+    }
+
+    /// Runtime state without any aeon impls
+    #[derive(Default, Debug, PartialEq)]
+    pub struct ConnectionCache {
+        hits: u32,
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Upstream {
+        name: String,
+        #[aeon(skip)]
+        cache: ConnectionCache,
+        #[aeon(skip_serializing, default)]
+        password: String,
+        #[aeon(skip_deserializing)]
+        resolved: u16,
+        #[aeon(skip_serializing_if = "Option::is_none")]
+        weight: Option<u8>,
+        #[aeon(skip_serializing_if = "Vec::is_empty", default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "kind")]
+    pub enum Probe {
+        Http {
+            path: String,
+            #[aeon(skip)]
+            cache: ConnectionCache,
+            #[aeon(skip_serializing_if = "Option::is_none")]
+            timeout: Option<f64>,
+        },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Check {
+        Tcp {
+            port: u16,
+            #[aeon(skip)]
+            cache: ConnectionCache,
+        },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Balancer {
+        upstreams: Vec<Upstream>,
+        probe: Probe,
+        check: Check,
+    }
+
+    fn upstream(name: &str, weight: Option<u8>, tags: Vec<String>) -> Upstream {
+        Upstream {
+            name: name.into(),
+            cache: ConnectionCache { hits: 3 },
+            password: "secret".into(),
+            resolved: 443,
+            weight,
+            tags,
+        }
+    }
+
+    #[test]
+    pub fn test_skipped_fields_are_not_written() {
+        let balancer = Balancer {
+            upstreams: vec![
+                upstream("a", Some(2), vec!["eu".into()]),
+                upstream("b", None, Vec::new()),
+            ],
+            probe: Probe::Http {
+                path: "/health".into(),
+                cache: ConnectionCache { hits: 1 },
+                timeout: None,
+            },
+            check: Check::Tcp {
+                port: 22,
+                cache: ConnectionCache { hits: 1 },
+            },
+        };
+        let serialized = balancer.to_aeon().unwrap();
+        assert!(
+            serialized.contains("@Upstream(name, resolved, weight, tags)"),
+            "{}",
+            serialized
+        );
+        assert!(serialized.contains("kind: \"Http\""), "{}", serialized);
+        assert!(serialized.contains("@Tcp(port)"), "{}", serialized);
+        assert!(!serialized.contains("secret"), "{}", serialized);
+        assert!(!serialized.contains("nil"), "{}", serialized);
+        assert!(!serialized.contains("hits"), "{}", serialized);
+
+        let deserialized = Balancer::from_aeon(serialized.clone()).unwrap();
+        let first = &deserialized.upstreams[0];
+        assert_eq!(ConnectionCache::default(), first.cache);
+        assert_eq!("", first.password);
+        assert_eq!(0, first.resolved);
+        assert_eq!(Some(2), first.weight);
+        assert_eq!(vec!["eu".to_string()], first.tags);
+        let second = &deserialized.upstreams[1];
+        assert_eq!(None, second.weight);
+        assert!(second.tags.is_empty());
+        match &deserialized.probe {
+            Probe::Http { path, timeout, .. } => {
+                assert_eq!("/health", path);
+                assert_eq!(&None, timeout);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_skipped_fields_are_not_read() {
+        let aeon = r#"
+        @Tcp(port, cache)
+        upstreams: [{ name: "a", password: "secret", resolved: 80, cache: 1 }]
+        probe: { kind: "Http", path: "/", timeout: 1.5, cache: 1 }
+        check: Tcp(22, 1)
+        "#;
+        let balancer = Balancer::from_aeon(aeon.to_string()).unwrap();
+        let upstream = &balancer.upstreams[0];
+        assert_eq!("secret", upstream.password);
+        assert_eq!(0, upstream.resolved);
+        assert_eq!(ConnectionCache::default(), upstream.cache);
+        let expected = Check::Tcp {
+            port: 22,
+            cache: ConnectionCache::default(),
+        };
+        assert_eq!(expected, balancer.check);
+
+        let schema = Schema::document_of::<Upstream>();
+        assert!(schema.get_path("password").is_some());
+        assert!(schema.get_path("resolved").is_none());
+        assert!(schema.get_path("cache").is_none());
+    }
+
+    #[derive(AeonSchema, Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Connection(String, #[aeon(skip)] ConnectionCache, u16);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[aeon(tag = "t", content = "c")]
+    pub enum Session {
+        Open(u16, #[aeon(skip)] ConnectionCache),
+        Closed(#[aeon(skip_deserializing)] u8, String, bool),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Pooled {
+        conn: Connection,
+        session: Session,
+    }
+
+    #[test]
+    pub fn test_skipped_unnamed_fields() {
+        let pooled = Pooled {
+            conn: Connection("db".into(), ConnectionCache { hits: 5 }, 5432),
+            session: Session::Open(22, ConnectionCache { hits: 1 }),
+        };
+        let serialized = pooled.to_aeon().unwrap();
+        assert!(serialized.contains("@Connection(f0, f2)"), "{}", serialized);
+        assert!(serialized.contains("c: 22"), "{}", serialized);
+        assert!(!serialized.contains("hits"), "{}", serialized);
+        let deserialized = Pooled::from_aeon(serialized.clone()).unwrap();
+        let expected = Pooled {
+            conn: Connection("db".into(), ConnectionCache::default(), 5432),
+            session: Session::Open(22, ConnectionCache::default()),
+        };
+        assert_eq!(expected, deserialized, "{}", serialized);
+
+        let aeon = r#"
+        conn: ["db", 5432]
+        session: { t: "Closed", c: ["done", true] }
+        "#;
+        let pooled = Pooled::from_aeon(aeon.to_string()).unwrap();
+        assert_eq!(expected.conn, pooled.conn);
+        assert_eq!(Session::Closed(0, "done".into(), true), pooled.session);
+        let err =
+            Pooled::from_aeon(aeon.replace("[\"db\", 5432]", "[\"db\", 1, 5432]")).unwrap_err();
+        assert_eq!("Expected 2 items for `Connection`, found 3", err.message);
+
+        let schema = Schema::document_of::<Connection>();
+        assert!(schema.get_path("f0").is_some());
+        assert!(schema.get_path("f1").is_none());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum HostCheck {
+        Tcp { port: u16 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum HostProbe {
+        Tcp { host: String, port: u16 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum RenamedProbe {
+        #[aeon(rename = "TcpProbe")]
+        Tcp { host: String, port: u16 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct Monitor {
+        check: HostCheck,
+        probe: HostProbe,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub struct RenamedMonitor {
+        check: HostCheck,
+        probe: RenamedProbe,
+    }
+
+    #[test]
+    pub fn test_variants_sharing_a_name_are_not_mixed_up() {
+        let monitor = Monitor {
+            check: HostCheck::Tcp { port: 22 },
+            probe: HostProbe::Tcp {
+                host: "h".into(),
+                port: 80,
+            },
+        };
+        let err = monitor.to_aeon().unwrap_err();
+        assert!(
+            err.message
+                .contains("macro `Tcp`, as @Tcp(port) and as @Tcp(host, port)"),
+            "{}",
+            err.message
+        );
+
+        let monitor = RenamedMonitor {
+            check: HostCheck::Tcp { port: 22 },
+            probe: RenamedProbe::Tcp {
+                host: "h".into(),
+                port: 80,
+            },
+        };
+        let serialized = monitor.to_aeon().unwrap();
+        assert!(serialized.contains("@Tcp(port)"), "{}", serialized);
+        assert!(
+            serialized.contains("@TcpProbe(host, port)"),
+            "{}",
+            serialized
+        );
+        let deserialized = RenamedMonitor::from_aeon(serialized.clone()).unwrap();
+        assert_eq!(monitor, deserialized, "{}", serialized);
+    }
 }
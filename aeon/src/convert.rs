@@ -1,9 +1,14 @@
 use crate::document::{AeonDocument, AeonMacro};
+use crate::error::{AeonPathError, AeonPathErrorCode};
+use crate::ordered_map::OrderedMap;
+use crate::path::{appends, empty_path, list_index, not_a_container, not_found, segments};
+use crate::query::{Query, QueryMatch};
 use crate::value::AeonValue;
 use crate::{
-    AeonDeserializeError, AeonDeserializeProperty, AeonSerializeProperty, DeserializeResult,
-    SerializeResult,
+    AeonDeserializeError, AeonDeserializeProperty, AeonSchema, AeonSerializeProperty,
+    DeserializeResult, PathResult, SerializeResult,
 };
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 pub trait AeonConvert {
@@ -12,7 +17,7 @@ pub trait AeonConvert {
     fn str(self) -> Option<String>;
     fn int(self) -> Option<i64>;
     fn double(self) -> Option<f64>;
-    fn object(self) -> Option<HashMap<String, AeonValue>>;
+    fn object(self) -> Option<OrderedMap<String, AeonValue>>;
     fn list(self) -> Option<Vec<AeonValue>>;
     fn list_of<T: AeonDeserializeProperty>(self) -> Option<Vec<T>>;
     fn of<T: AeonDeserializeProperty>(self) -> Option<T>;
@@ -50,8 +55,11 @@ impl AeonConvert for AeonValue {
         try_convert!(self, AeonValue::Double)
     }
 
-    fn object(self) -> Option<HashMap<String, AeonValue>> {
-        try_convert!(self, AeonValue::Object)
+    fn object(self) -> Option<OrderedMap<String, AeonValue>> {
+        match self {
+            AeonValue::Object(v) | AeonValue::Record(_, v) => Some(v),
+            _ => None,
+        }
     }
 
     fn list(self) -> Option<Vec<AeonValue>> {
@@ -69,14 +77,14 @@ impl AeonConvert for AeonValue {
 
     fn get(&self, prop: &str) -> Option<AeonValue> {
         match self {
-            AeonValue::Object(v) => v.get(prop).cloned(),
+            AeonValue::Object(v) | AeonValue::Record(_, v) => v.get(prop).cloned(),
             _ => None,
         }
     }
 
     fn remove(&mut self, prop: &str) -> Option<AeonValue> {
         match self {
-            AeonValue::Object(v) => v.remove(prop),
+            AeonValue::Object(v) | AeonValue::Record(_, v) => v.remove(prop),
             _ => None,
         }
     }
@@ -121,7 +129,7 @@ impl AeonConvert for Option<AeonValue> {
         opt_convert!(self, double)
     }
 
-    fn object(self) -> Option<HashMap<String, AeonValue>> {
+    fn object(self) -> Option<OrderedMap<String, AeonValue>> {
         opt_convert!(self, object)
     }
 
@@ -146,9 +154,28 @@ impl AeonConvert for Option<AeonValue> {
     }
 }
 
+/// Access to the values of a document by path.
+///
+/// Paths are property names followed by map keys and list indexes, separated by '/',
+/// e.g. `servers/1/port`. Negative indexes count from the end of a list, so `servers/-1`
+/// is the last server, and `servers/-` refers to a new item after the end of the list
+/// when setting or inserting.
 pub trait AeonObjectConvert {
     fn get(&self, prop: &str) -> Option<AeonValue>;
     fn get_path(&self, path: &str) -> Option<AeonValue>;
+    /// The value at `path`, or which segment of the path doesn't exist
+    fn get_path_ref(&self, path: &str) -> PathResult<&AeonValue>;
+    fn get_path_mut(&mut self, path: &str) -> PathResult<&mut AeonValue>;
+    /// Replaces the value at `path`, returning the old value if there was one.
+    /// Missing properties and keys along the path, and nil values, become empty objects.
+    fn set_path(&mut self, path: &str, value: AeonValue) -> PathResult<Option<AeonValue>>;
+    /// Adds a new property, map entry or list item, failing if the key already exists.
+    /// List items are inserted before the item at the index.
+    fn insert_path(&mut self, path: &str, value: AeonValue) -> PathResult<()>;
+    /// Removes the property, map entry or list item at `path`
+    fn delete_path(&mut self, path: &str) -> PathResult<AeonValue>;
+    /// Every value matched by `query`, which may contain wildcards and filters, see [`Query`]
+    fn query(&self, query: &str) -> PathResult<Vec<QueryMatch<'_>>>;
     fn remove(&mut self, prop: &str) -> Option<AeonValue>;
     fn remove_path(&mut self, path: &str) -> Option<AeonValue>;
 }
@@ -159,19 +186,136 @@ impl AeonObjectConvert for AeonDocument {
     }
 
     fn get_path(&self, path: &str) -> Option<AeonValue> {
-        let fragments = path.split('/');
-        let mut iter = fragments.filter(|&f| !f.is_empty());
-        let mut current: Option<AeonValue>;
-        if let Some(frag) = iter.next() {
-            current = self.get(frag);
-        } else {
-            return None;
+        self.get_path_ref(path).ok().cloned()
+    }
+
+    fn get_path_ref(&self, path: &str) -> PathResult<&AeonValue> {
+        let segments = segments(path);
+        let (first, rest) = segments.split_first().ok_or_else(|| empty_path(path))?;
+        let mut current = match self.properties.get(*first) {
+            Some(prop) => &prop.value,
+            None => return Err(not_found(first, path, 0)),
+        };
+        for (idx, segment) in rest.iter().enumerate() {
+            current = child(current, segment, path, idx + 1)?;
         }
+        Ok(current)
+    }
 
-        for frag in iter {
-            current = current.get(frag);
+    fn get_path_mut(&mut self, path: &str) -> PathResult<&mut AeonValue> {
+        let segments = segments(path);
+        if segments.is_empty() {
+            return Err(empty_path(path));
         }
-        current
+        walk_mut(self, path, &segments)
+    }
+
+    fn set_path(&mut self, path: &str, value: AeonValue) -> PathResult<Option<AeonValue>> {
+        let segments = segments(path);
+        let (key, parents) = segments.split_last().ok_or_else(|| empty_path(path))?;
+        let (first, rest) = match parents.split_first() {
+            Some(split) => split,
+            None => {
+                return Ok(match self.properties.get_mut(*key) {
+                    Some(prop) => Some(std::mem::replace(&mut prop.value, value)),
+                    None => {
+                        self.add_or_replace_property(key.to_string(), value);
+                        None
+                    }
+                })
+            }
+        };
+        if !self.properties.contains_key(*first) {
+            self.add_or_replace_property(first.to_string(), AeonValue::Object(OrderedMap::new()));
+        }
+        let mut parent = &mut self.properties.get_mut(*first).unwrap().value;
+        for (idx, segment) in rest.iter().enumerate() {
+            parent = child_or_insert(parent, segment, path, idx + 1)?;
+        }
+        if let AeonValue::Nil = parent {
+            *parent = AeonValue::Object(OrderedMap::new());
+        }
+        match parent {
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                Ok(map.insert(key.to_string(), value))
+            }
+            AeonValue::List(items) => {
+                if appends(key, items.len()) {
+                    items.push(value);
+                    return Ok(None);
+                }
+                let idx = list_index(key, items.len(), path, parents.len())?;
+                Ok(Some(std::mem::replace(&mut items[idx], value)))
+            }
+            v => Err(not_a_container(v, path, parents.len() - 1)),
+        }
+    }
+
+    fn insert_path(&mut self, path: &str, value: AeonValue) -> PathResult<()> {
+        let segments = segments(path);
+        let (key, parents) = segments.split_last().ok_or_else(|| empty_path(path))?;
+        let last = parents.len();
+        if parents.is_empty() {
+            if self.properties.contains_key(*key) {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::AlreadyExists,
+                    format!("Property '{}' already exists", key),
+                    path,
+                    last,
+                ));
+            }
+            self.add_or_replace_property(key.to_string(), value);
+            return Ok(());
+        }
+        match walk_mut(self, path, parents)? {
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                if map.contains_key(*key) {
+                    return Err(AeonPathError::new(
+                        AeonPathErrorCode::AlreadyExists,
+                        format!("Key '{}' already exists", key),
+                        path,
+                        last,
+                    ));
+                }
+                map.insert(key.to_string(), value);
+            }
+            AeonValue::List(items) => {
+                let idx = if appends(key, items.len()) {
+                    items.len()
+                } else {
+                    list_index(key, items.len(), path, last)?
+                };
+                items.insert(idx, value);
+            }
+            v => return Err(not_a_container(v, path, last - 1)),
+        }
+        Ok(())
+    }
+
+    fn delete_path(&mut self, path: &str) -> PathResult<AeonValue> {
+        let segments = segments(path);
+        let (key, parents) = segments.split_last().ok_or_else(|| empty_path(path))?;
+        let last = parents.len();
+        if parents.is_empty() {
+            return match self.properties.remove(*key) {
+                Some(prop) => Ok(prop.value),
+                None => Err(not_found(key, path, last)),
+            };
+        }
+        match walk_mut(self, path, parents)? {
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                map.remove(*key).ok_or_else(|| not_found(key, path, last))
+            }
+            AeonValue::List(items) => {
+                let idx = list_index(key, items.len(), path, last)?;
+                Ok(items.remove(idx))
+            }
+            v => Err(not_a_container(v, path, last - 1)),
+        }
+    }
+
+    fn query(&self, query: &str) -> PathResult<Vec<QueryMatch<'_>>> {
+        Ok(Query::parse(query)?.find_in(self))
     }
 
     fn remove(&mut self, prop: &str) -> Option<AeonValue> {
@@ -199,6 +343,83 @@ impl AeonObjectConvert for AeonDocument {
     }
 }
 
+/// The value at the non-empty `segments` of `path`
+fn walk_mut<'a>(
+    doc: &'a mut AeonDocument,
+    path: &str,
+    segments: &[&str],
+) -> PathResult<&'a mut AeonValue> {
+    let mut current = match doc.properties.get_mut(segments[0]) {
+        Some(prop) => &mut prop.value,
+        None => return Err(not_found(segments[0], path, 0)),
+    };
+    for (idx, segment) in segments.iter().enumerate().skip(1) {
+        current = child_mut(current, segment, path, idx)?;
+    }
+    Ok(current)
+}
+
+/// The value inside `value` at `segment`, which is segment `idx` of `path`
+fn child<'a>(
+    value: &'a AeonValue,
+    segment: &str,
+    path: &str,
+    idx: usize,
+) -> PathResult<&'a AeonValue> {
+    match value {
+        AeonValue::Object(map) | AeonValue::Record(_, map) => map
+            .get(segment)
+            .ok_or_else(|| not_found(segment, path, idx)),
+        AeonValue::List(items) => Ok(&items[list_index(segment, items.len(), path, idx)?]),
+        v => Err(not_a_container(v, path, idx - 1)),
+    }
+}
+
+fn child_mut<'a>(
+    value: &'a mut AeonValue,
+    segment: &str,
+    path: &str,
+    idx: usize,
+) -> PathResult<&'a mut AeonValue> {
+    match value {
+        AeonValue::Object(map) | AeonValue::Record(_, map) => map
+            .get_mut(segment)
+            .ok_or_else(|| not_found(segment, path, idx)),
+        AeonValue::List(items) => {
+            let i = list_index(segment, items.len(), path, idx)?;
+            Ok(&mut items[i])
+        }
+        v => Err(not_a_container(v, path, idx - 1)),
+    }
+}
+
+/// Like [`child_mut`], but creates an empty object if there's nothing at `segment`
+fn child_or_insert<'a>(
+    value: &'a mut AeonValue,
+    segment: &str,
+    path: &str,
+    idx: usize,
+) -> PathResult<&'a mut AeonValue> {
+    if let AeonValue::Nil = value {
+        *value = AeonValue::Object(OrderedMap::new());
+    }
+    match value {
+        AeonValue::Object(map) | AeonValue::Record(_, map) => Ok(
+            map.get_or_insert_with(segment.to_string(), || AeonValue::Object(OrderedMap::new()))
+        ),
+        AeonValue::List(items) => {
+            let i = if appends(segment, items.len()) {
+                items.push(AeonValue::Object(OrderedMap::new()));
+                items.len() - 1
+            } else {
+                list_index(segment, items.len(), path, idx)?
+            };
+            Ok(&mut items[i])
+        }
+        v => Err(not_a_container(v, path, idx - 1)),
+    }
+}
+
 macro_rules! gen_deserialize {
     ($ty:path, $conv:ident) => {
         impl AeonDeserializeProperty for $ty {
@@ -218,16 +439,40 @@ macro_rules! gen_deserialize {
     };
 }
 
+macro_rules! gen_deserialize_int {
+    ($ty:path) => {
+        impl AeonDeserializeProperty for $ty {
+            fn from_property(field: AeonValue) -> DeserializeResult<Self> {
+                let field_type = field.tag();
+                match field.int() {
+                    Some(i) => <$ty>::try_from(i).map_err(|_| {
+                        AeonDeserializeError::conversion(format!(
+                            "Integer {} is out of range for '{}'",
+                            i,
+                            stringify!($ty)
+                        ))
+                    }),
+                    None => Err(AeonDeserializeError::conversion(format!(
+                        "Failed to convert '{}' to '{}'",
+                        AeonValue::tag_to_str(field_type),
+                        stringify!($ty)
+                    ))),
+                }
+            }
+        }
+    };
+}
+
 gen_deserialize!(bool, bool);
 gen_deserialize!(String, str);
-gen_deserialize!(i64, int);
-gen_deserialize!(i32, int);
-gen_deserialize!(i16, int);
-gen_deserialize!(i8, int);
-gen_deserialize!(u64, int);
-gen_deserialize!(u32, int);
-gen_deserialize!(u16, int);
-gen_deserialize!(u8, int);
+gen_deserialize_int!(i64);
+gen_deserialize_int!(i32);
+gen_deserialize_int!(i16);
+gen_deserialize_int!(i8);
+gen_deserialize_int!(u64);
+gen_deserialize_int!(u32);
+gen_deserialize_int!(u16);
+gen_deserialize_int!(u8);
 gen_deserialize!(f64, double);
 gen_deserialize!(f32, double);
 
@@ -238,7 +483,76 @@ pub fn maybe<T: AeonDeserializeProperty>(thing: Option<AeonValue>) -> Deserializ
 }
 
 pub fn expected<T: AeonDeserializeProperty>(thing: Option<AeonValue>) -> DeserializeResult<T> {
-    thing.map(AeonDeserializeProperty::from_property).unwrap()
+    thing
+        .map(AeonDeserializeProperty::from_property)
+        .unwrap_or_else(|| {
+            Err(AeonDeserializeError::conversion(format!(
+                "Expected a value to convert to '{}', found nothing",
+                std::any::type_name::<T>()
+            )))
+        })
+}
+
+/// The enum variant that `value` names, as a string or the name of its macro
+pub fn variant_name(value: &AeonValue) -> Option<&str> {
+    match value {
+        AeonValue::String(name) | AeonValue::Record(name, _) => Some(name),
+        _ => None,
+    }
+}
+
+/// Error for a value that isn't one of the `variants` of the enum `type_name`
+pub fn unknown_variant(
+    type_name: &str,
+    variants: &[&str],
+    value: &AeonValue,
+) -> AeonDeserializeError {
+    let message = match variant_name(value) {
+        Some(name) => format!("Unknown variant `{}` of `{}`", name, type_name),
+        None => format!(
+            "Expected a variant of `{}`, found {}",
+            type_name,
+            AeonValue::tag_to_str(value.tag())
+        ),
+    };
+    let variants: Vec<String> = variants.iter().map(|v| format!("`{}`", v)).collect();
+    AeonDeserializeError::conversion(message)
+        .with_help(format!("the variants are {}", variants.join(", ")))
+}
+
+/// The items of `value` if it's a list of `required` to `len` items, e.g. the fields of the tuple
+/// `type_name`, where the last fields may be left out if they have defaults
+pub fn tuple_items(
+    value: AeonValue,
+    required: usize,
+    len: usize,
+    type_name: &str,
+) -> DeserializeResult<std::vec::IntoIter<AeonValue>> {
+    match value {
+        AeonValue::List(items) if (required..=len).contains(&items.len()) => Ok(items.into_iter()),
+        AeonValue::List(items) => Err(AeonDeserializeError::conversion(format!(
+            "Expected {} items for `{}`, found {}",
+            if required == len {
+                len.to_string()
+            } else {
+                format!("{} to {}", required, len)
+            },
+            type_name,
+            items.len()
+        ))),
+        v => Err(AeonDeserializeError::conversion(format!(
+            "Expected a list for `{}`, found {}",
+            type_name,
+            AeonValue::tag_to_str(v.tag())
+        ))),
+    }
+}
+
+// always owned, since the value it's deserialized from is owned
+impl AeonDeserializeProperty for Cow<'_, str> {
+    fn from_property(field: AeonValue) -> DeserializeResult<Self> {
+        String::from_property(field).map(Cow::Owned)
+    }
 }
 
 impl<T: AeonDeserializeProperty> AeonDeserializeProperty for Option<T> {
@@ -266,7 +580,7 @@ impl<T: AeonDeserializeProperty> AeonDeserializeProperty for Vec<T> {
     }
 }
 
-impl<T: AeonDeserializeProperty> AeonDeserializeProperty for HashMap<String, T> {
+impl<T: AeonDeserializeProperty> AeonDeserializeProperty for OrderedMap<String, T> {
     fn from_property(field: AeonValue) -> DeserializeResult<Self> {
         let field_type = field.tag();
         field
@@ -278,7 +592,7 @@ impl<T: AeonDeserializeProperty> AeonDeserializeProperty for HashMap<String, T>
             })
             .unwrap_or_else(|| {
                 Err(AeonDeserializeError::deserialization(format!(
-                    "Failed to convert {:?} to HashMap<String, {:?}>",
+                    "Failed to convert {:?} to OrderedMap<String, {:?}>",
                     AeonValue::tag_to_str(field_type),
                     std::any::type_name::<T>()
                 )))
@@ -286,18 +600,53 @@ impl<T: AeonDeserializeProperty> AeonDeserializeProperty for HashMap<String, T>
     }
 }
 
-impl AeonDeserializeProperty for HashMap<String, AeonValue> {
+impl AeonDeserializeProperty for OrderedMap<String, AeonValue> {
     fn from_property(field: AeonValue) -> DeserializeResult<Self> {
         let field_type = field.tag();
         field.object().map(Ok).unwrap_or_else(|| {
             Err(AeonDeserializeError::deserialization(format!(
-                "Failed to convert {:?} to HashMap<String, AeonValue>",
+                "Failed to convert {:?} to OrderedMap<String, AeonValue>",
                 AeonValue::tag_to_str(field_type)
             )))
         })
     }
 }
 
+impl<T: AeonDeserializeProperty> AeonDeserializeProperty for HashMap<String, T> {
+    fn from_property(field: AeonValue) -> DeserializeResult<Self> {
+        let field_type = field.tag();
+        field
+            .object()
+            .map(|m| {
+                m.into_iter()
+                    .map(|(k, v)| Ok((k, T::from_property(v)?)))
+                    .collect()
+            })
+            .unwrap_or_else(|| {
+                Err(AeonDeserializeError::deserialization(format!(
+                    "Failed to convert {:?} to HashMap<String, {:?}>",
+                    AeonValue::tag_to_str(field_type),
+                    std::any::type_name::<T>()
+                )))
+            })
+    }
+}
+
+impl AeonDeserializeProperty for HashMap<String, AeonValue> {
+    fn from_property(field: AeonValue) -> DeserializeResult<Self> {
+        let field_type = field.tag();
+        field
+            .object()
+            .map(|m| Ok(m.into_iter().collect()))
+            .unwrap_or_else(|| {
+                Err(AeonDeserializeError::deserialization(format!(
+                    "Failed to convert {:?} to HashMap<String, AeonValue>",
+                    AeonValue::tag_to_str(field_type)
+                )))
+            })
+    }
+}
+
 macro_rules! gen_serialize {
     ($ty:path, $val:ident, $conv:path) => {
         impl AeonSerializeProperty for $ty {
@@ -305,10 +654,8 @@ macro_rules! gen_serialize {
                 Ok(AeonValue::$val(self.clone() as $conv))
             }
 
-            fn create_property_macros(
-                _insert_self: bool,
-            ) -> HashMap<String, crate::document::AeonMacro> {
-                std::collections::HashMap::new()
+            fn create_property_macros(_insert_self: bool) -> OrderedMap<String, AeonMacro> {
+                OrderedMap::new()
             }
         }
     };
@@ -327,6 +674,16 @@ gen_serialize!(u8, Integer, i64);
 gen_serialize!(f64, Double, f64);
 gen_serialize!(f32, Double, f64);
 
+impl AeonSerializeProperty for Cow<'_, str> {
+    fn serialize_property(&self) -> SerializeResult<AeonValue> {
+        Ok(AeonValue::String(self.to_string()))
+    }
+
+    fn create_property_macros(_insert_self: bool) -> OrderedMap<String, AeonMacro> {
+        OrderedMap::new()
+    }
+}
+
 // blanket impl for Option<T>
 impl<T: AeonSerializeProperty> AeonSerializeProperty for Option<T> {
     fn serialize_property(&self) -> SerializeResult<AeonValue> {
@@ -335,9 +692,15 @@ impl<T: AeonSerializeProperty> AeonSerializeProperty for Option<T> {
             .unwrap_or(Ok(AeonValue::Nil))
     }
 
-    fn create_property_macros(insert_self: bool) -> HashMap<String, AeonMacro> {
+    fn create_property_macros(insert_self: bool) -> OrderedMap<String, AeonMacro> {
         T::create_property_macros(insert_self)
     }
+
+    fn try_create_property_macros(
+        insert_self: bool,
+    ) -> SerializeResult<OrderedMap<String, AeonMacro>> {
+        T::try_create_property_macros(insert_self)
+    }
 }
 
 // blanket impl for Vec<T>
@@ -348,32 +711,182 @@ impl<T: AeonSerializeProperty> AeonSerializeProperty for Vec<T> {
         Ok(AeonValue::List(converted?))
     }
 
-    fn create_property_macros(insert_self: bool) -> HashMap<String, AeonMacro> {
+    fn create_property_macros(insert_self: bool) -> OrderedMap<String, AeonMacro> {
         T::create_property_macros(insert_self)
     }
+
+    fn try_create_property_macros(
+        insert_self: bool,
+    ) -> SerializeResult<OrderedMap<String, AeonMacro>> {
+        T::try_create_property_macros(insert_self)
+    }
 }
 
-// blanket impl for HashMap<String, T>
+// blanket impl for OrderedMap<String, T>
+impl<T: AeonSerializeProperty> AeonSerializeProperty for OrderedMap<String, T> {
+    fn serialize_property(&self) -> SerializeResult<AeonValue> {
+        let converted: SerializeResult<OrderedMap<String, AeonValue>> = self
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), T::serialize_property(v)?)))
+            .collect();
+        Ok(AeonValue::Object(converted?))
+    }
+
+    fn create_property_macros(insert_self: bool) -> OrderedMap<String, AeonMacro> {
+        T::create_property_macros(insert_self)
+    }
+
+    fn try_create_property_macros(
+        insert_self: bool,
+    ) -> SerializeResult<OrderedMap<String, AeonMacro>> {
+        T::try_create_property_macros(insert_self)
+    }
+}
+
+impl AeonSerializeProperty for OrderedMap<String, AeonValue> {
+    fn serialize_property(&self) -> SerializeResult<AeonValue> {
+        Ok(AeonValue::Object(self.clone()))
+    }
+
+    fn create_property_macros(_insert_self: bool) -> OrderedMap<String, AeonMacro> {
+        OrderedMap::new()
+    }
+}
+
+// blanket impl for HashMap<String, T>, keys are sorted since a HashMap has no order of its own
 impl<T: AeonSerializeProperty> AeonSerializeProperty for HashMap<String, T> {
     fn serialize_property(&self) -> SerializeResult<AeonValue> {
-        Ok(AeonValue::Object(
-            self.iter()
-                .map(|(k, v)| (k.clone(), T::serialize_property(v).unwrap()))
-                .collect(),
-        ))
+        let converted: SerializeResult<HashMap<String, AeonValue>> = self
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), T::serialize_property(v)?)))
+            .collect();
+        Ok(AeonValue::Object(converted?.into()))
     }
 
-    fn create_property_macros(insert_self: bool) -> HashMap<String, AeonMacro> {
+    fn create_property_macros(insert_self: bool) -> OrderedMap<String, AeonMacro> {
         T::create_property_macros(insert_self)
     }
+
+    fn try_create_property_macros(
+        insert_self: bool,
+    ) -> SerializeResult<OrderedMap<String, AeonMacro>> {
+        T::try_create_property_macros(insert_self)
+    }
 }
 
 impl AeonSerializeProperty for HashMap<String, AeonValue> {
     fn serialize_property(&self) -> SerializeResult<AeonValue> {
-        Ok(AeonValue::Object(self.clone()))
+        Ok(AeonValue::Object(self.clone().into()))
+    }
+
+    fn create_property_macros(_insert_self: bool) -> OrderedMap<String, AeonMacro> {
+        OrderedMap::new()
+    }
+}
+
+/// A schema node of values of the type `type_name`, see [`crate::schema::Schema`]
+fn schema_of_type(type_name: &str) -> OrderedMap<String, AeonValue> {
+    let mut node = OrderedMap::new();
+    node.insert("type".into(), AeonValue::String(type_name.into()));
+    node
+}
+
+macro_rules! gen_schema {
+    ($ty:path, $type_name:literal $(, $min:expr, $max:expr)?) => {
+        impl AeonSchema for $ty {
+            #[allow(unused_mut)]
+            fn property_schema() -> AeonValue {
+                let mut node = schema_of_type($type_name);
+                $(
+                    node.insert("min".into(), AeonValue::Integer($min as i64));
+                    node.insert("max".into(), AeonValue::Integer($max as i64));
+                )?
+                AeonValue::Object(node)
+            }
+        }
+    };
+}
+
+gen_schema!(bool, "bool");
+gen_schema!(String, "string");
+gen_schema!(i64, "int");
+gen_schema!(i32, "int", i32::MIN, i32::MAX);
+gen_schema!(i16, "int", i16::MIN, i16::MAX);
+gen_schema!(i8, "int", i8::MIN, i8::MAX);
+gen_schema!(u64, "int", u64::MIN, i64::MAX);
+gen_schema!(u32, "int", u32::MIN, u32::MAX);
+gen_schema!(u16, "int", u16::MIN, u16::MAX);
+gen_schema!(u8, "int", u8::MIN, u8::MAX);
+gen_schema!(f64, "double");
+gen_schema!(f32, "double");
+
+impl AeonSchema for Cow<'_, str> {
+    fn property_schema() -> AeonValue {
+        String::property_schema()
     }
+}
+
+// blanket impl for Option<T>, which may be left out or nil
+impl<T: AeonSchema> AeonSchema for Option<T> {
+    fn property_schema() -> AeonValue {
+        let mut node = match T::property_schema() {
+            AeonValue::Object(node) | AeonValue::Record(_, node) => node,
+            types => {
+                let mut node = OrderedMap::new();
+                node.insert("type".into(), types);
+                node
+            }
+        };
+        let nullable = |t: &mut AeonValue| {
+            if let AeonValue::String(name) = t {
+                if name != "any" && !name.ends_with('?') {
+                    name.push('?');
+                }
+            }
+        };
+        match node.get_mut("type") {
+            Some(AeonValue::List(types)) => types.iter_mut().for_each(nullable),
+            Some(t) => nullable(t),
+            None => (),
+        }
+        node.insert("required".into(), AeonValue::Bool(false));
+        AeonValue::Object(node)
+    }
+}
+
+// blanket impl for Vec<T>
+impl<T: AeonSchema> AeonSchema for Vec<T> {
+    fn property_schema() -> AeonValue {
+        let mut node = schema_of_type("list");
+        node.insert("items".into(), T::property_schema());
+        AeonValue::Object(node)
+    }
+}
+
+// blanket impl for OrderedMap<String, T>
+impl<T: AeonSchema> AeonSchema for OrderedMap<String, T> {
+    fn property_schema() -> AeonValue {
+        let mut node = schema_of_type("object");
+        node.insert("values".into(), T::property_schema());
+        AeonValue::Object(node)
+    }
+}
+
+impl AeonSchema for OrderedMap<String, AeonValue> {
+    fn property_schema() -> AeonValue {
+        AeonValue::Object(schema_of_type("object"))
+    }
+}
+
+// blanket impl for HashMap<String, T>
+impl<T: AeonSchema> AeonSchema for HashMap<String, T> {
+    fn property_schema() -> AeonValue {
+        OrderedMap::<String, T>::property_schema()
+    }
+}
 
-    fn create_property_macros(_insert_self: bool) -> HashMap<String, AeonMacro> {
-        HashMap::new()
+impl AeonSchema for HashMap<String, AeonValue> {
+    fn property_schema() -> AeonValue {
+        AeonValue::Object(schema_of_type("object"))
     }
 }
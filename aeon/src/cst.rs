@@ -0,0 +1,1201 @@
+use crate::deserializer::{Deserializer, MAX_DEPTH};
+use crate::document::{AeonArgType, AeonDocument, AeonMacro};
+use crate::error::{AeonDeserializeError, AeonPathError, AeonPathErrorCode};
+use crate::include::IncludeResolver;
+use crate::lexer::Lexer;
+use crate::path::{appends, empty_path, list_index, not_found, segments};
+use crate::serializer;
+use crate::span::Span;
+use crate::token::{SpannedToken, Token};
+use crate::value::AeonValue;
+use crate::{DeserializeResult, PathResult};
+use std::fmt::{Display, Formatter};
+
+/// A lossless syntax tree of an Aeon document, for editing hand-written files.
+///
+/// Unlike [`AeonDocument`], it keeps the original text with its comments, blank lines,
+/// indentation and macro calls. Edits only rewrite the text of what was edited,
+/// everything else is kept byte-for-byte.
+///
+/// Paths are the same `/`-separated paths as [`crate::convert::AeonObjectConvert::get_path`],
+/// with numbers indexing into lists and macro calls, negative numbers counting from the end
+/// of lists, and argument names indexing into macro calls.
+///
+/// ```
+/// use aeon::cst::CstDocument;
+/// use aeon::value::AeonValue;
+///
+/// let mut cst = CstDocument::parse("# the port\nport: 80 # default\n".into()).unwrap();
+/// cst.set("port", &AeonValue::Integer(8080)).unwrap();
+/// assert_eq!("# the port\nport: 8080 # default\n", cst.source());
+/// ```
+#[derive(Debug)]
+pub struct CstDocument {
+    source: String,
+    items: Vec<CstItem>,
+}
+
+#[derive(Debug)]
+pub enum CstItem {
+    Macro(CstMacro),
+    Property(CstProperty),
+    Include(CstInclude),
+}
+
+/// An `@include("path")` directive
+#[derive(Debug)]
+pub struct CstInclude {
+    pub path: String,
+    /// From the '@' to the closing ')'
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct CstMacro {
+    pub name: String,
+    pub args: Vec<String>,
+    /// Default value of each argument in `args`, if it has one
+    pub defaults: Vec<Option<CstNode>>,
+    /// Declared type of each argument in `args`, if it has one
+    pub types: Vec<Option<AeonArgType>>,
+    /// From the '@' to the closing ')'
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct CstProperty {
+    pub name: String,
+    pub name_span: Span,
+    pub value: CstNode,
+}
+
+#[derive(Debug)]
+pub struct CstNode {
+    pub kind: CstKind,
+    /// For lists and maps this includes the brackets, for macro calls the name and parentheses
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum CstKind {
+    Literal(AeonValue),
+    List(Vec<CstElement>),
+    Map(Vec<CstElement>),
+    MacroCall {
+        name: String,
+        args: Vec<CstElement>,
+    },
+    /// `&name value`, the span of the node includes the anchor
+    Anchor {
+        name: String,
+        value: Box<CstNode>,
+    },
+    /// `*name`
+    Alias(String),
+}
+
+/// An item in a list, an entry in a map or an argument to a macro call
+#[derive(Debug)]
+pub struct CstElement {
+    /// Key and key span of a map entry
+    pub key: Option<(String, Span)>,
+    pub value: CstNode,
+    /// The ',' after the element, if there is one
+    pub comma: Option<Span>,
+}
+
+impl CstItem {
+    pub fn span(&self) -> Span {
+        match self {
+            CstItem::Macro(m) => m.span,
+            CstItem::Property(p) => p.name_span.to(p.value.span),
+            CstItem::Include(i) => i.span,
+        }
+    }
+}
+
+impl CstNode {
+    /// The value after `&name` if this is an anchor, otherwise the node itself
+    pub fn without_anchor(&self) -> &CstNode {
+        match &self.kind {
+            CstKind::Anchor { value, .. } => value.without_anchor(),
+            _ => self,
+        }
+    }
+}
+
+impl CstElement {
+    pub fn span(&self) -> Span {
+        match &self.key {
+            Some((_, key_span)) => key_span.to(self.value.span),
+            None => self.value.span,
+        }
+    }
+}
+
+impl CstDocument {
+    pub fn parse(source: String) -> DeserializeResult<CstDocument> {
+        let items = Parser::new(&source).parse()?;
+        Ok(CstDocument { source, items })
+    }
+
+    /// The current text of the document, including all edits
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn items(&self) -> &[CstItem] {
+        &self.items
+    }
+
+    pub fn to_document(&self) -> DeserializeResult<AeonDocument> {
+        crate::deserialize(self.source.clone())
+    }
+
+    /// Like [`CstDocument::to_document`], for documents with `@include("path")` directives
+    pub fn to_document_with_resolver(
+        &self,
+        resolver: &dyn IncludeResolver,
+    ) -> DeserializeResult<AeonDocument> {
+        crate::deserialize_with_resolver(self.source.clone(), resolver)
+    }
+
+    pub fn get(&self, path: &str) -> PathResult<&CstNode> {
+        let segments = segments(path);
+        self.resolve(path, &segments)
+    }
+
+    /// Where the value at `path` is in [`CstDocument::source`]
+    pub fn span_of(&self, path: &str) -> Option<Span> {
+        self.get(path).ok().map(|n| n.span)
+    }
+
+    /// The source text of the value at `path`
+    pub fn text_of(&self, path: &str) -> Option<&str> {
+        self.span_of(path)
+            .map(|s| &self.source[s.start.offset..s.end.offset])
+    }
+
+    /// Replaces the value at `path`, or inserts it if only the last segment of the path is missing.
+    pub fn set(&mut self, path: &str, value: &AeonValue) -> PathResult<()> {
+        let segments = segments(path);
+        let span = match self.resolve(path, &segments) {
+            Ok(node) => node.span,
+            Err(e)
+                if e.segment + 1 == segments.len()
+                    && matches!(
+                        e.code,
+                        AeonPathErrorCode::NotFound | AeonPathErrorCode::IndexOutOfRange
+                    ) =>
+            {
+                return self.insert(path, value);
+            }
+            Err(e) => return Err(e),
+        };
+        let text = self.render(path, value)?;
+        self.splice(path, vec![(span.start.offset, span.end.offset, text)])
+    }
+
+    /// Inserts a new property, map entry or list item.
+    ///
+    /// The last segment of `path` is the new key, or the index to insert the list item at,
+    /// where `-` appends to the end of the list.
+    pub fn insert(&mut self, path: &str, value: &AeonValue) -> PathResult<()> {
+        let segments = segments(path);
+        let (key, parent_segments) = match segments.split_last() {
+            Some(split) => split,
+            None => return Err(empty_path(path)),
+        };
+        let last = parent_segments.len();
+        let text = self.render(path, value)?;
+        if parent_segments.is_empty() {
+            if self.property(key).is_some() {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::AlreadyExists,
+                    format!("Property '{}' already exists", key),
+                    path,
+                    last,
+                ));
+            }
+            if !serializer::is_valid_identifier(key) {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::InvalidEdit,
+                    format!("'{}' is not a valid property name", key),
+                    path,
+                    last,
+                ));
+            }
+            let edit = self.insert_property(format!("{}: {}", key, text));
+            return self.splice(path, vec![edit]);
+        }
+
+        let parent = self.resolve(path, parent_segments)?;
+        let edit = match &parent.kind {
+            CstKind::Map(entries) => {
+                if entries.iter().any(|e| key_of(e) == Some(key)) {
+                    return Err(AeonPathError::new(
+                        AeonPathErrorCode::AlreadyExists,
+                        format!("Key '{}' already exists", key),
+                        path,
+                        last,
+                    ));
+                }
+                let mut entry = String::new();
+                serializer::serialize_key(key, &mut entry);
+                entry.push_str(": ");
+                entry.push_str(&text);
+                self.append_element(parent, entries, entry)
+            }
+            CstKind::List(items) => {
+                if appends(key, items.len()) {
+                    self.append_element(parent, items, text)
+                } else {
+                    let idx = list_index(key, items.len(), path, last)?;
+                    self.insert_element(parent, items, idx, text)
+                }
+            }
+            CstKind::MacroCall { name, .. } => {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::InvalidEdit,
+                    format!("Cannot add arguments to a call of macro '{}'", name),
+                    path,
+                    last,
+                ))
+            }
+            kind @ (CstKind::Literal(_) | CstKind::Anchor { .. } | CstKind::Alias(_)) => {
+                return Err(not_a_container(kind, path, last - 1))
+            }
+        };
+        self.splice(path, vec![edit])
+    }
+
+    /// Removes a property, map entry or list item, along with its comma and,
+    /// if it was on lines of its own, those lines.
+    pub fn remove(&mut self, path: &str) -> PathResult<()> {
+        let segments = segments(path);
+        let (key, parent_segments) = match segments.split_last() {
+            Some(split) => split,
+            None => return Err(empty_path(path)),
+        };
+        let last = parent_segments.len();
+        if parent_segments.is_empty() {
+            let span = match self.items.iter().find_map(|i| match i {
+                CstItem::Property(p) if p.name == *key => Some(i.span()),
+                _ => None,
+            }) {
+                Some(span) => span,
+                None => return Err(not_found(key, path, 0)),
+            };
+            let edit = self.removal_range(span.start.offset, span.end.offset);
+            return self.splice(path, vec![edit]);
+        }
+
+        let parent = self.resolve(path, parent_segments)?;
+        let edits = match &parent.kind {
+            CstKind::Map(entries) => match entries.iter().position(|e| key_of(e) == Some(key)) {
+                Some(idx) => self.remove_element(parent, entries, idx),
+                None => return Err(not_found(key, path, last)),
+            },
+            CstKind::List(items) => {
+                let idx = list_index(key, items.len(), path, last)?;
+                self.remove_element(parent, items, idx)
+            }
+            CstKind::MacroCall { name, .. } => {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::InvalidEdit,
+                    format!("Cannot remove arguments from a call of macro '{}'", name),
+                    path,
+                    last,
+                ))
+            }
+            kind @ (CstKind::Literal(_) | CstKind::Anchor { .. } | CstKind::Alias(_)) => {
+                return Err(not_a_container(kind, path, last - 1))
+            }
+        };
+        self.splice(path, edits)
+    }
+
+    fn property(&self, name: &str) -> Option<&CstProperty> {
+        // the first definition wins, like in AeonDocument::add_property
+        self.items.iter().find_map(|i| match i {
+            CstItem::Property(p) if p.name == name => Some(p),
+            _ => None,
+        })
+    }
+
+    fn macro_def(&self, name: &str) -> Option<&CstMacro> {
+        self.items.iter().find_map(|i| match i {
+            CstItem::Macro(m) if m.name == name => Some(m),
+            _ => None,
+        })
+    }
+
+    fn resolve(&self, path: &str, segments: &[&str]) -> PathResult<&CstNode> {
+        let first = match segments.first() {
+            Some(first) => first,
+            None => return Err(empty_path(path)),
+        };
+        let mut node = match self.property(first) {
+            Some(p) => p.value.without_anchor(),
+            None => return Err(not_found(first, path, 0)),
+        };
+        for (idx, segment) in segments.iter().enumerate().skip(1) {
+            let element = match &node.kind {
+                CstKind::Map(entries) => {
+                    match entries.iter().find(|e| key_of(e) == Some(segment)) {
+                        Some(e) => e,
+                        None => return Err(not_found(segment, path, idx)),
+                    }
+                }
+                CstKind::List(items) => &items[list_index(segment, items.len(), path, idx)?],
+                CstKind::MacroCall { name, args } => {
+                    let positional = || {
+                        self.macro_def(name)
+                            .and_then(|m| m.args.iter().position(|a| a == segment))
+                            .and_then(|a| args.get(a))
+                            .filter(|e| e.key.is_none())
+                    };
+                    let element = args
+                        .iter()
+                        .find(|e| key_of(e) == Some(segment))
+                        .or_else(positional)
+                        .or_else(|| segment.parse::<usize>().ok().and_then(|a| args.get(a)));
+                    match element {
+                        Some(e) => e,
+                        None => {
+                            return Err(AeonPathError::new(
+                                AeonPathErrorCode::NotFound,
+                                format!("'{}' is not an argument of macro '{}'", segment, name),
+                                path,
+                                idx,
+                            ))
+                        }
+                    }
+                }
+                kind @ (CstKind::Literal(_) | CstKind::Anchor { .. } | CstKind::Alias(_)) => {
+                    return Err(not_a_container(kind, path, idx - 1))
+                }
+            };
+            node = element.value.without_anchor();
+        }
+        Ok(node)
+    }
+
+    /// The macros of the document, with the types and default values of their arguments
+    fn macros(&self) -> AeonDocument {
+        let mut macros = AeonDocument::new();
+        for item in &self.items {
+            if let CstItem::Macro(m) = item {
+                let defaults: Vec<Option<AeonValue>> = m
+                    .defaults
+                    .iter()
+                    .map(|default| {
+                        let span = default.as_ref()?.span;
+                        let text = &self.source[span.start.offset..span.end.offset];
+                        Deserializer::new(text).deserialize_value(&mut macros).ok()
+                    })
+                    .collect();
+                let args = m.args.iter().cloned().zip(defaults).collect();
+                macros.add_macro(
+                    AeonMacro::with_defaults(m.name.clone(), args).with_types(m.types.clone()),
+                );
+            }
+        }
+        macros
+    }
+
+    fn render(&self, path: &str, value: &AeonValue) -> PathResult<String> {
+        let macros = self.macros();
+        check_macro_args(&macros, value).map_err(|e| {
+            AeonPathError::new(
+                AeonPathErrorCode::InvalidEdit,
+                format!("Invalid value: {}", e.message),
+                path,
+                segments(path).len().saturating_sub(1),
+            )
+        })?;
+        let mut s = String::new();
+        serializer::serialize_value_inline(&macros, value, &mut s);
+        Ok(s)
+    }
+
+    /// Applies the edits, which must not overlap, and parses the result
+    fn splice(&mut self, path: &str, mut edits: Vec<(usize, usize, String)>) -> PathResult<()> {
+        let mut source = self.source.clone();
+        edits.sort_by_key(|e| std::cmp::Reverse(e.0));
+        for (start, end, text) in edits {
+            source.replace_range(start..end, &text);
+        }
+        match Parser::new(&source).parse() {
+            Ok(items) => {
+                self.source = source;
+                self.items = items;
+                Ok(())
+            }
+            Err(e) => Err(AeonPathError::new(
+                AeonPathErrorCode::InvalidEdit,
+                format!("Edit would produce an invalid document: {}", e),
+                path,
+                segments(path).len().saturating_sub(1),
+            )),
+        }
+    }
+
+    fn newline(&self) -> &'static str {
+        if self.source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.source[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// The whitespace that the line containing `offset` is indented with
+    fn indent_at(&self, offset: usize) -> String {
+        self.source[self.line_start(offset)..offset]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
+    }
+
+    /// Skips spaces and a trailing comment after `offset`, returning where the line ends,
+    /// or `None` if something else follows on the same line
+    fn end_of_line(&self, offset: usize) -> Option<usize> {
+        let rest = &self.source[offset..];
+        let trimmed = rest.trim_start_matches([' ', '\t']);
+        let at = offset + rest.len() - trimmed.len();
+        match trimmed.chars().next() {
+            None | Some('\n') => Some(at),
+            Some('\r') if trimmed.starts_with("\r\n") => Some(at),
+            Some('#') => Some(at + trimmed.find('\n').unwrap_or(trimmed.len())),
+            _ => None,
+        }
+    }
+
+    /// The range to remove for the text from `start` to `end`, which is the whole line
+    /// if nothing but whitespace and a comment share the line with it
+    fn removal_range(&self, start: usize, end: usize) -> (usize, usize, String) {
+        let line_start = self.line_start(start);
+        let own_line = self.source[line_start..start].trim().is_empty();
+        match self.end_of_line(end) {
+            Some(eol) if own_line => {
+                let newline = if self.source[eol..].starts_with("\r\n") {
+                    2
+                } else {
+                    usize::from(eol < self.source.len())
+                };
+                (line_start, eol + newline, String::new())
+            }
+            _ => {
+                let rest = &self.source[end..];
+                let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+                (start, end + spaces, String::new())
+            }
+        }
+    }
+
+    fn insert_property(&self, property: String) -> (usize, usize, String) {
+        match self.items.last() {
+            None => {
+                let prefix = if self.source.is_empty() || self.source.ends_with('\n') {
+                    ""
+                } else {
+                    self.newline()
+                };
+                let at = self.source.len();
+                (at, at, format!("{}{}{}", prefix, property, self.newline()))
+            }
+            Some(last) => {
+                let span = last.span();
+                let at = self.end_of_line(span.end.offset).unwrap_or(span.end.offset);
+                let indent = self.indent_at(span.start.offset);
+                (at, at, format!("{}{}{}", self.newline(), indent, property))
+            }
+        }
+    }
+
+    fn append_element(
+        &self,
+        container: &CstNode,
+        elements: &[CstElement],
+        text: String,
+    ) -> (usize, usize, String) {
+        let open = container.span.start.offset + 1;
+        let close = container.span.end.offset - 1;
+        let last = match elements.last() {
+            Some(last) => last,
+            None if self.source[open..close].trim().is_empty() => return (open, close, text),
+            None => return (open, open, text),
+        };
+        let value_end = last.value.span.end.offset;
+        if last.span().start.line == container.span.start.line {
+            return match last.comma {
+                Some(comma) => (comma.end.offset, comma.end.offset, format!(" {},", text)),
+                None => (value_end, value_end, format!(", {}", text)),
+            };
+        }
+        let indent = self.indent_at(last.span().start.offset);
+        match last.comma {
+            Some(comma) => {
+                let at = self
+                    .end_of_line(comma.end.offset)
+                    .unwrap_or(comma.end.offset);
+                (at, at, format!("{}{}{},", self.newline(), indent, text))
+            }
+            None => {
+                let at = self.end_of_line(value_end).unwrap_or(value_end);
+                let trailing = &self.source[value_end..at];
+                (
+                    value_end,
+                    at,
+                    format!(",{}{}{}{}", trailing, self.newline(), indent, text),
+                )
+            }
+        }
+    }
+
+    fn insert_element(
+        &self,
+        container: &CstNode,
+        elements: &[CstElement],
+        idx: usize,
+        text: String,
+    ) -> (usize, usize, String) {
+        let start = elements[idx].span().start;
+        let previous_line = match idx {
+            0 => container.span.start.line,
+            _ => elements[idx - 1].span().end.line,
+        };
+        let separator = if start.line > previous_line {
+            format!(",{}{}", self.newline(), self.indent_at(start.offset))
+        } else {
+            ", ".to_string()
+        };
+        (start.offset, start.offset, format!("{}{}", text, separator))
+    }
+
+    fn remove_element(
+        &self,
+        container: &CstNode,
+        elements: &[CstElement],
+        idx: usize,
+    ) -> Vec<(usize, usize, String)> {
+        let element = &elements[idx];
+        let start = element.span().start.offset;
+        let end = element
+            .comma
+            .map_or(element.value.span.end.offset, |c| c.end.offset);
+        if elements.len() == 1 {
+            let open = container.span.start.offset + 1;
+            let close = container.span.end.offset - 1;
+            let outside = format!("{}{}", &self.source[open..start], &self.source[end..close]);
+            if outside.trim().is_empty() {
+                return vec![(open, close, String::new())];
+            }
+            return vec![self.removal_range(start, end)];
+        }
+        if element.comma.is_some() {
+            return vec![self.removal_range(start, end)];
+        }
+        // the last element without a trailing comma, so the comma before it has to go too
+        let previous = &elements[idx - 1];
+        let line_start = self.line_start(start);
+        let own_line = self.source[line_start..start].trim().is_empty();
+        match previous.comma {
+            Some(comma) if own_line => vec![
+                self.removal_range(start, end),
+                (comma.start.offset, comma.end.offset, String::new()),
+            ],
+            _ => vec![(previous.value.span.end.offset, end, String::new())],
+        }
+    }
+}
+
+impl Display for CstDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Checks the arguments of the macro calls in `value` against the types declared by `macros`,
+/// since a call that doesn't match them would make the edited document invalid
+fn check_macro_args(macros: &AeonDocument, value: &AeonValue) -> DeserializeResult<()> {
+    match value {
+        AeonValue::Record(name, map) => {
+            if let Some(m) = macros.macros.get(name) {
+                for (idx, arg) in m.args.iter().enumerate() {
+                    if let Some(arg) = map.get(arg) {
+                        m.check_arg(idx, arg)?;
+                    }
+                }
+            }
+            map.values().try_for_each(|v| check_macro_args(macros, v))
+        }
+        AeonValue::Object(map) => map.values().try_for_each(|v| check_macro_args(macros, v)),
+        AeonValue::List(items) => items.iter().try_for_each(|v| check_macro_args(macros, v)),
+        _ => Ok(()),
+    }
+}
+
+fn key_of(element: &CstElement) -> Option<&str> {
+    element.key.as_ref().map(|(k, _)| k.as_str())
+}
+
+fn not_a_container(kind: &CstKind, path: &str, idx: usize) -> AeonPathError {
+    let message = match kind {
+        CstKind::Anchor { value, .. } => return not_a_container(&value.kind, path, idx),
+        CstKind::Alias(name) => format!(
+            "Cannot index into reference '*{}', edit the value it refers to instead",
+            name
+        ),
+        CstKind::Literal(value) => format!(
+            "Cannot index into a value of type '{}'",
+            AeonValue::tag_to_str(value.tag())
+        ),
+        CstKind::List(_) | CstKind::Map(_) | CstKind::MacroCall { .. } => {
+            "Cannot index into this value".to_string()
+        }
+    };
+    AeonPathError::new(AeonPathErrorCode::NotAContainer, message, path, idx)
+}
+
+/// Parses the same grammar as [`crate::deserializer::Deserializer`], but keeps the spans
+/// of everything and doesn't apply macros
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<SpannedToken>,
+    depth: usize,
+}
+
+/// Which elements of a list, map or macro call have keys
+#[derive(Clone, Copy)]
+enum Keys {
+    Never,
+    Always,
+    /// Named arguments in macro calls
+    Optional,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Parser<'a> {
+        Parser {
+            lexer: Lexer::new(source),
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    fn next_token(&mut self) -> DeserializeResult<Option<SpannedToken>> {
+        match self.peeked.take() {
+            Some(tok) => Ok(Some(tok)),
+            None => self.lexer.next(),
+        }
+    }
+
+    fn peek_is_colon(&mut self) -> DeserializeResult<bool> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next()?;
+        }
+        Ok(matches!(
+            self.peeked,
+            Some(SpannedToken {
+                token: Token::Colon,
+                ..
+            })
+        ))
+    }
+
+    fn unexpected(tok: &SpannedToken, expected: &str) -> AeonDeserializeError {
+        AeonDeserializeError::deserialization(format!("Expected {}, found {}", expected, tok.token))
+            .with_span(tok.span)
+    }
+
+    fn next_or(&mut self, expected: &str) -> DeserializeResult<SpannedToken> {
+        match self.next_token()? {
+            Some(tok) => Ok(tok),
+            None => Err(AeonDeserializeError::deserialization(format!(
+                "Expected {}, found end of input",
+                expected
+            ))
+            .with_span(Span::at(self.lexer.position()))),
+        }
+    }
+
+    fn parse(&mut self) -> DeserializeResult<Vec<CstItem>> {
+        let mut items = Vec::new();
+        while let Some(tok) = self.next_token()? {
+            match tok.token {
+                Token::At => items.push(self.parse_macro(tok.span)?),
+                Token::Identifier(name) => {
+                    let colon = self.next_or("':' after property name")?;
+                    if !matches!(colon.token, Token::Colon) {
+                        return Err(Self::unexpected(&colon, "':' after property name"));
+                    }
+                    let value = self.next_or("property value")?;
+                    items.push(CstItem::Property(CstProperty {
+                        name,
+                        name_span: tok.span,
+                        value: self.parse_value(value)?,
+                    }));
+                }
+                _ => return Err(Self::unexpected(&tok, "a property or a macro definition")),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Parses a macro definition or an include directive
+    fn parse_macro(&mut self, at: Span) -> DeserializeResult<CstItem> {
+        let name = match self.next_or("macro name")? {
+            SpannedToken {
+                token: Token::Identifier(name),
+                ..
+            } => name,
+            tok => return Err(Self::unexpected(&tok, "macro name")),
+        };
+        let open = self.next_or("'(' after macro name")?;
+        if !matches!(open.token, Token::LeftParenthesis) {
+            return Err(Self::unexpected(&open, "'(' after macro name"));
+        }
+        let mut args = Vec::new();
+        let mut defaults = Vec::new();
+        let mut types = Vec::new();
+        loop {
+            let tok = self.next_or("argument name or ')' in macro definition")?;
+            match tok.token {
+                Token::Identifier(arg) => args.push(arg),
+                Token::String(path) if name == "include" && args.is_empty() => {
+                    let close = self.next_or("')' after included path")?;
+                    if !matches!(close.token, Token::RightParenthesis) {
+                        return Err(Self::unexpected(&close, "')' after included path"));
+                    }
+                    return Ok(CstItem::Include(CstInclude {
+                        path,
+                        span: at.to(close.span),
+                    }));
+                }
+                Token::RightParenthesis => {
+                    return Ok(CstItem::Macro(CstMacro {
+                        name,
+                        args,
+                        defaults,
+                        types,
+                        span: at.to(tok.span),
+                    }))
+                }
+                _ => return Err(Self::unexpected(&tok, "argument name or ')'")),
+            }
+            let mut tok = self.next_or("',' or ')' in macro definition")?;
+            if let Token::Colon = tok.token {
+                let typ = self.next_or("argument type")?;
+                let type_name = match &typ.token {
+                    Token::Identifier(type_name) => type_name.as_str(),
+                    Token::Nil => "nil",
+                    _ => return Err(Self::unexpected(&typ, "argument type")),
+                };
+                tok = self.next_or("',' or ')' in macro definition")?;
+                let nullable = matches!(tok.token, Token::Question);
+                types.push(Some(
+                    AeonArgType::from_name(type_name, nullable).ok_or_else(|| {
+                        AeonDeserializeError::deserialization(format!(
+                            "Unknown type '{}'",
+                            type_name
+                        ))
+                        .with_span(typ.span)
+                    })?,
+                ));
+                if nullable {
+                    tok = self.next_or("',' or ')' in macro definition")?;
+                }
+            } else {
+                types.push(None);
+            }
+            if let Token::Equals = tok.token {
+                let value = self.next_or("default value")?;
+                defaults.push(Some(self.parse_value(value)?));
+                tok = self.next_or("',' or ')' in macro definition")?;
+            } else {
+                defaults.push(None);
+            }
+            match tok.token {
+                Token::Comma => (),
+                Token::RightParenthesis => {
+                    return Ok(CstItem::Macro(CstMacro {
+                        name,
+                        args,
+                        defaults,
+                        types,
+                        span: at.to(tok.span),
+                    }))
+                }
+                _ => return Err(Self::unexpected(&tok, "',', ':', '=' or ')'")),
+            }
+        }
+    }
+
+    fn parse_value(&mut self, tok: SpannedToken) -> DeserializeResult<CstNode> {
+        if self.depth == MAX_DEPTH {
+            return Err(AeonDeserializeError::deserialization(format!(
+                "Values are nested more than {} levels deep",
+                MAX_DEPTH
+            ))
+            .with_span(tok.span));
+        }
+        self.depth += 1;
+        let node = self.parse_value_inner(tok);
+        self.depth -= 1;
+        node
+    }
+
+    fn parse_value_inner(&mut self, tok: SpannedToken) -> DeserializeResult<CstNode> {
+        let literal = match tok.token {
+            Token::LeftBracket => {
+                let (items, close) = self.parse_elements(tok.span, Keys::Never, ']')?;
+                return Ok(CstNode {
+                    kind: CstKind::List(items),
+                    span: tok.span.to(close),
+                });
+            }
+            Token::LeftBrace => {
+                let (entries, close) = self.parse_elements(tok.span, Keys::Always, '}')?;
+                return Ok(CstNode {
+                    kind: CstKind::Map(entries),
+                    span: tok.span.to(close),
+                });
+            }
+            Token::Identifier(name) => {
+                let open = self.next_or("'(' after macro name")?;
+                if !matches!(open.token, Token::LeftParenthesis) {
+                    return Err(Self::unexpected(&open, "'(' after macro name"));
+                }
+                let (args, close) = self.parse_elements(open.span, Keys::Optional, ')')?;
+                return Ok(CstNode {
+                    kind: CstKind::MacroCall { name, args },
+                    span: tok.span.to(close),
+                });
+            }
+            Token::Anchor(name) => {
+                let value = self.next_or("value after anchor")?;
+                let value = self.parse_value(value)?;
+                return Ok(CstNode {
+                    span: tok.span.to(value.span),
+                    kind: CstKind::Anchor {
+                        name,
+                        value: Box::new(value),
+                    },
+                });
+            }
+            Token::Alias(name) => {
+                return Ok(CstNode {
+                    kind: CstKind::Alias(name),
+                    span: tok.span,
+                })
+            }
+            Token::String(s) => AeonValue::String(s),
+            Token::Integer(i) => AeonValue::Integer(i),
+            Token::Double(d) => AeonValue::Double(d),
+            Token::True => AeonValue::Bool(true),
+            Token::False => AeonValue::Bool(false),
+            Token::Nil => AeonValue::Nil,
+            _ => return Err(Self::unexpected(&tok, "a value")),
+        };
+        Ok(CstNode {
+            kind: CstKind::Literal(literal),
+            span: tok.span,
+        })
+    }
+
+    /// Parses the elements of a list, map or macro call up to and including the `close` delimiter
+    fn parse_elements(
+        &mut self,
+        open: Span,
+        keys: Keys,
+        close: char,
+    ) -> DeserializeResult<(Vec<CstElement>, Span)> {
+        let mut elements = Vec::new();
+        loop {
+            let tok = self.next_or("a value or closing delimiter")?;
+            if closing(&tok.token).is_some() {
+                return Self::expect_close(&tok, close).map(|span| (elements, span));
+            }
+            let keyed = match keys {
+                Keys::Never => false,
+                Keys::Always => true,
+                Keys::Optional => {
+                    matches!(tok.token, Token::Identifier(_)) && self.peek_is_colon()?
+                }
+            };
+            let (key, tok) = if keyed {
+                let key = match tok.token {
+                    Token::Identifier(key) | Token::String(key) => (key, tok.span),
+                    _ => return Err(Self::unexpected(&tok, "map key")),
+                };
+                let colon = self.next_or("':' after map key")?;
+                if !matches!(colon.token, Token::Colon) {
+                    return Err(Self::unexpected(&colon, "':' after map key"));
+                }
+                (Some(key), self.next_or("map value")?)
+            } else {
+                (None, tok)
+            };
+            let value = self.parse_value(tok)?;
+            let separator = match self.next_token()? {
+                Some(separator) => separator,
+                None => {
+                    return Err(AeonDeserializeError::deserialization(
+                        "Unclosed list, map or macro call".to_string(),
+                    )
+                    .with_span(open))
+                }
+            };
+            let comma = match separator.token {
+                Token::Comma => Some(separator.span),
+                ref t if closing(t).is_some() => None,
+                _ => return Err(Self::unexpected(&separator, &format!("',' or '{}'", close))),
+            };
+            elements.push(CstElement { key, value, comma });
+            if comma.is_none() {
+                return Self::expect_close(&separator, close).map(|span| (elements, span));
+            }
+        }
+    }
+
+    /// The span of `tok` if it is the `close` delimiter, an error if it closes something else
+    fn expect_close(tok: &SpannedToken, close: char) -> DeserializeResult<Span> {
+        if closing(&tok.token) == Some(close) {
+            return Ok(tok.span);
+        }
+        let what = match close {
+            ']' => "list",
+            '}' => "map",
+            _ => "macro call",
+        };
+        Err(
+            Self::unexpected(tok, &format!("'{}' to close the {}", close, what))
+                .with_help(format!("add a '{}' to close the {}", close, what)),
+        )
+    }
+}
+
+fn closing(token: &Token) -> Option<char> {
+    match token {
+        Token::RightBracket => Some(']'),
+        Token::RightBrace => Some('}'),
+        Token::RightParenthesis => Some(')'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{AeonConvert, AeonObjectConvert};
+    use crate::cst::CstDocument;
+    use crate::error::AeonPathErrorCode;
+    use crate::value::AeonValue;
+
+    const CONFIG: &str = "# servers we know of
+@server(id, name, port)
+
+servers: [
+    server(1, \"alpha\", 80), # the old one
+    server(2, \"beta\", 8080),
+]
+
+limits: {
+    # per minute
+    requests: 100,
+    burst:    20
+}
+tags: [\"a\", \"b\"]
+";
+
+    fn parse(source: &str) -> CstDocument {
+        CstDocument::parse(source.to_string()).expect("failed to parse")
+    }
+
+    #[test]
+    pub fn unedited_document_is_unchanged() {
+        let cst = parse(CONFIG);
+        assert_eq!(CONFIG, cst.source());
+        assert_eq!(CONFIG, cst.to_string());
+    }
+
+    #[test]
+    pub fn set_replaces_only_the_value() {
+        let mut cst = parse(CONFIG);
+        cst.set("limits/burst", &AeonValue::Integer(50)).unwrap();
+        cst.set("servers/1/port", &AeonValue::Integer(9090))
+            .unwrap();
+        cst.set("servers/0/2", &AeonValue::Integer(81)).unwrap();
+        assert_eq!(
+            CONFIG
+                .replace("burst:    20", "burst:    50")
+                .replace("8080", "9090")
+                .replace("80),", "81),"),
+            cst.source()
+        );
+    }
+
+    #[test]
+    pub fn set_uses_macros_of_the_document() {
+        let mut cst = parse(CONFIG);
+        let server = AeonValue::Object(crate::map![
+            "id".into() => AeonValue::Integer(3),
+            "name".into() => AeonValue::String("gamma".into()),
+            "port".into() => AeonValue::Integer(1),
+        ]);
+        cst.set("servers/0", &server).unwrap();
+        assert!(cst
+            .source()
+            .contains("    server(3, \"gamma\", 1), # the old one\n"));
+        let doc = cst.to_document().unwrap();
+        assert_eq!(
+            2,
+            doc.properties["servers"]
+                .value
+                .clone()
+                .list()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    pub fn insert_follows_surrounding_layout() {
+        let mut cst = parse(CONFIG);
+        cst.insert("limits/window", &AeonValue::Integer(60))
+            .unwrap();
+        cst.insert("tags/-", &AeonValue::String("c".into()))
+            .unwrap();
+        cst.insert("tags/0", &AeonValue::String("z".into()))
+            .unwrap();
+        cst.insert("servers/-", &AeonValue::Nil).unwrap();
+        cst.set("timeout", &AeonValue::Double(1.5)).unwrap();
+        assert_eq!(
+            CONFIG
+                .replace("burst:    20\n", "burst:    20,\n    window: 60\n")
+                .replace("[\"a\", \"b\"]", "[\"z\", \"a\", \"b\", \"c\"]")
+                .replace("8080),\n", "8080),\n    nil,\n")
+                + "timeout: 1.5\n",
+            cst.source()
+        );
+    }
+
+    #[test]
+    pub fn remove_takes_lines_and_commas_with_it() {
+        let mut cst = parse(CONFIG);
+        cst.remove("servers/0").unwrap();
+        cst.remove("limits/burst").unwrap();
+        cst.remove("tags/1").unwrap();
+        assert_eq!(
+            CONFIG
+                .replace("    server(1, \"alpha\", 80), # the old one\n", "")
+                .replace("requests: 100,\n    burst:    20\n", "requests: 100\n")
+                .replace("[\"a\", \"b\"]", "[\"a\"]"),
+            cst.source()
+        );
+        cst.remove("tags/0").unwrap();
+        cst.remove("limits").unwrap();
+        assert!(cst.source().ends_with("8080),\n]\n\ntags: []\n"), "{}", cst);
+    }
+
+    #[test]
+    pub fn edit_errors_name_the_failing_segment() {
+        let mut cst = parse(CONFIG);
+        let err = cst.set("limits/burst/x", &AeonValue::Nil).unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::NotAContainer));
+        assert_eq!(Some("burst"), err.segment_str());
+        let err = cst.remove("servers/5").unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::IndexOutOfRange));
+        let err = cst
+            .insert("tags/0", &AeonValue::Nil)
+            .map(|_| cst.insert("limits/requests", &AeonValue::Nil));
+        assert!(matches!(err, Ok(Err(e)) if matches!(e.code, AeonPathErrorCode::AlreadyExists)));
+        assert!(cst.remove("servers/0/name").is_err());
+    }
+
+    #[test]
+    pub fn span_of_points_into_source() {
+        let cst = parse(CONFIG);
+        assert_eq!(Some("\"beta\""), cst.text_of("servers/1/name"));
+        let span = cst.span_of("limits/requests").unwrap();
+        assert_eq!((11, 15), (span.start.line, span.start.column));
+        assert!(cst.span_of("limits/nothing").is_none());
+    }
+
+    #[test]
+    pub fn negative_indexes_count_from_the_end() {
+        let mut cst = parse(CONFIG);
+        assert_eq!(Some("server(2, \"beta\", 8080)"), cst.text_of("servers/-1"));
+        assert_eq!(Some("\"beta\""), cst.text_of("servers/-1/name"));
+        assert!(cst.get("servers/-3").is_err());
+        cst.insert("tags/-1", &AeonValue::String("c".into()))
+            .unwrap();
+        cst.remove("servers/-2").unwrap();
+        assert_eq!(Some("\"beta\""), cst.text_of("servers/0/name"));
+        let doc = crate::deserialize(cst.source().to_string()).unwrap();
+        let tags = ["a", "c", "b"]
+            .map(|t| AeonValue::String(t.into()))
+            .to_vec();
+        assert_eq!(Some(AeonValue::List(tags)), doc.get_path("tags"));
+    }
+
+    #[test]
+    pub fn named_macro_arguments_can_be_edited() {
+        let mut cst = parse("@server(id, name, port)\ns: server(1, port: 80, name: \"a\")\n");
+        assert_eq!(Some("80"), cst.text_of("s/port"));
+        assert_eq!(Some("1"), cst.text_of("s/id"));
+        cst.set("s/name", &AeonValue::String("b".into())).unwrap();
+        assert_eq!(
+            "@server(id, name, port)\ns: server(1, port: 80, name: \"b\")\n",
+            cst.source()
+        );
+    }
+
+    #[test]
+    pub fn edits_follow_argument_types_and_defaults() {
+        let source = "@p(x: int, y: string = \"a\")\n";
+        let mut cst = parse(source);
+        let point = |x: AeonValue| {
+            AeonValue::Record(
+                "p".into(),
+                crate::map!["x".into() => x, "y".into() => AeonValue::String("a".into())],
+            )
+        };
+        let err = cst
+            .set("q", &point(AeonValue::String("oops".into())))
+            .unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::InvalidEdit));
+        assert!(err.message.contains("must be int"), "{}", err);
+        let err = cst.insert("l", &AeonValue::List(vec![point(AeonValue::Nil)]));
+        assert!(err.is_err());
+        assert_eq!(source, cst.source());
+
+        cst.set("q", &point(AeonValue::Integer(1))).unwrap();
+        assert_eq!(Some("p(1)"), cst.text_of("q"));
+        assert!(cst.to_document().is_ok());
+    }
+
+    #[test]
+    pub fn anchored_values_can_be_edited() {
+        let mut cst = parse(
+            "retry: &retry { tries: 3 }
+other: *retry
+",
+        );
+        assert_eq!(Some("{ tries: 3 }"), cst.text_of("retry"));
+        cst.set("retry/tries", &AeonValue::Integer(5)).unwrap();
+        assert_eq!(
+            "retry: &retry { tries: 5 }
+other: *retry
+",
+            cst.source()
+        );
+        let err = cst.set("other/tries", &AeonValue::Nil).unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::NotAContainer));
+        assert!(err.message.contains("'*retry'"), "{}", err);
+    }
+}
@@ -1,46 +1,72 @@
-use crate::document::{AeonDocument, AeonMacro};
+use crate::document::{AeonArgType, AeonDocument, AeonMacro};
 use crate::error::AeonDeserializeError;
+use crate::include::IncludeResolver;
 use crate::lexer::Lexer;
-use crate::token::Token;
+use crate::ordered_map::OrderedMap;
+use crate::span::Span;
+use crate::token::{SpannedToken, Token};
 use crate::value::AeonValue;
 use crate::DeserializeResult;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+/// How deeply lists, maps and macro calls may be nested before giving up,
+/// so that hostile input can't overflow the stack
+pub(crate) const MAX_DEPTH: usize = 128;
+
+/// How many values references may copy in total, so that references to anchors
+/// that themselves contain references can't make the document grow exponentially
+const MAX_REFERENCED_VALUES: usize = 1 << 20;
 
 pub struct Deserializer<'a> {
     lexer: Lexer<'a>,
+    peeked: Option<SpannedToken>,
+    /// Span of the last token returned by `next_token`
+    last_span: Span,
+    depth: usize,
+    /// Finds documents for `@include("path")`, includes are an error without one
+    resolver: Option<&'a dyn IncludeResolver>,
+    /// Ids of the included documents that led to this one, ending with this one
+    chain: Vec<String>,
+    /// Names of included properties and macros, which this document may replace
+    included_properties: HashSet<String>,
+    included_macros: HashSet<String>,
+    /// Values named with `&name`, and how many values each contains
+    anchors: HashMap<String, (AeonValue, usize)>,
+    /// Anchors whose values are being deserialized, which can't be referenced yet
+    open_anchors: Vec<String>,
+    /// Number of values copied by references so far
+    referenced_values: usize,
+}
+
+/// An argument in a macro call, before it's matched to the macro's parameters
+struct CallArg {
+    name: Option<(String, Span)>,
+    value: AeonValue,
+    value_span: Span,
+    span: Span,
 }
 
 macro_rules! require {
-    ($actual:expr, arg $ok:path) => {
-        if let Some(it) = $actual? {
-            match it {
-                $ok(a) => Ok(a),
-                e => Err(AeonDeserializeError::deserialization(format!(
-                    "Unexpected token: {:?}",
-                    e
-                ))),
-            }
-        } else {
-            Err(AeonDeserializeError::deserialization(format!(
-                "Unexpected token: {:?}",
-                $actual
-            )))
+    ($self:ident, arg $ok:path, $expected:literal) => {
+        match $self.next_token()? {
+            Some(SpannedToken { token: $ok(a), .. }) => Ok(a),
+            Some(e) => Err(AeonDeserializeError::deserialization(format!(
+                "Expected {}, found {}",
+                $expected, e.token
+            ))
+            .with_span(e.span)),
+            None => Err($self.unexpected_end($expected)),
         }
     };
-    ($actual:expr, $ok:path) => {
-        if let Some(it) = $actual? {
-            match it {
-                $ok => Ok(()),
-                e => Err(AeonDeserializeError::deserialization(format!(
-                    "Unexpected token: {:?}",
-                    e
-                ))),
-            }
-        } else {
-            Err(AeonDeserializeError::deserialization(format!(
-                "Unexpected token: {:?}",
-                $actual
-            )))
+    ($self:ident, $ok:path, $expected:literal) => {
+        match $self.next_token()? {
+            Some(SpannedToken { token: $ok, .. }) => Ok(()),
+            Some(e) => Err(AeonDeserializeError::deserialization(format!(
+                "Expected {}, found {}",
+                $expected, e.token
+            ))
+            .with_span(e.span)),
+            None => Err($self.unexpected_end($expected)),
         }
     };
 }
@@ -49,16 +75,94 @@ impl<'a> Deserializer<'a> {
     pub fn new(code: &'a str) -> Deserializer<'a> {
         Deserializer {
             lexer: Lexer::new(code),
+            peeked: None,
+            last_span: Span::default(),
+            depth: 0,
+            resolver: None,
+            chain: Vec::new(),
+            included_properties: HashSet::new(),
+            included_macros: HashSet::new(),
+            anchors: HashMap::new(),
+            open_anchors: Vec::new(),
+            referenced_values: 0,
         }
     }
 
+    pub fn with_resolver(
+        code: &'a str,
+        resolver: &'a dyn IncludeResolver,
+        chain: Vec<String>,
+    ) -> Deserializer<'a> {
+        Deserializer {
+            resolver: Some(resolver),
+            chain,
+            ..Self::new(code)
+        }
+    }
+
+    fn next_token(&mut self) -> DeserializeResult<Option<SpannedToken>> {
+        let tok = match self.peeked.take() {
+            Some(tok) => Some(tok),
+            None => self.lexer.next()?,
+        };
+        if let Some(tok) = &tok {
+            self.last_span = tok.span;
+        }
+        Ok(tok)
+    }
+
+    fn peek_token(&mut self) -> DeserializeResult<Option<&SpannedToken>> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn eof_span(&self) -> Span {
+        Span::at(self.lexer.position())
+    }
+
+    fn unexpected_end(&self, expected: &str) -> AeonDeserializeError {
+        AeonDeserializeError::deserialization(format!("Expected {}, found end of input", expected))
+            .with_span(self.eof_span())
+    }
+
+    fn unclosed(&self, what: &str, open: Span, close: char) -> AeonDeserializeError {
+        AeonDeserializeError::deserialization(format!("Unclosed {}", what))
+            .with_span(open)
+            .with_help(format!("add a '{}' to close the {}", close, what))
+    }
+
     pub fn deserialize(&mut self) -> DeserializeResult<AeonDocument> {
+        self.deserialize_document()
+            .map_err(|e| e.with_include_chain(&self.chain))
+    }
+
+    /// Deserializes a single value, which must make up all of the code,
+    /// calling the macros of `aeon`
+    pub fn deserialize_value(&mut self, aeon: &mut AeonDocument) -> DeserializeResult<AeonValue> {
+        let tok = match self.next_token()? {
+            Some(tok) => tok,
+            None => return Err(self.unexpected_end("value")),
+        };
+        let value = self.deserialize_property_value(&tok, aeon)?;
+        match self.next_token()? {
+            None => Ok(value),
+            Some(tok) => Err(AeonDeserializeError::deserialization(format!(
+                "Unexpected {} after value",
+                tok.token
+            ))
+            .with_span(tok.span)),
+        }
+    }
+
+    fn deserialize_document(&mut self) -> DeserializeResult<AeonDocument> {
         let mut aeon = AeonDocument::new();
         'outer: loop {
             // result<option>
-            let res = self.lexer.next()?;
+            let res = self.next_token()?;
             if let Some(it) = res {
-                match it {
+                match it.token {
                     Token::At => {
                         // deserialize macro
                         self.deserialize_macro(&mut aeon)?;
@@ -67,12 +171,14 @@ impl<'a> Deserializer<'a> {
                         // deserialize property
                         self.deserialize_property(&mut aeon, ident)?;
                     }
-                    _ => {
-                        return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in main scope {:?}",
-                            it
-                        )))
-                    }
+                    t => return Err(AeonDeserializeError::deserialization(format!(
+                        "Unexpected {} at top level",
+                        t
+                    ))
+                    .with_span(it.span)
+                    .with_help(
+                        "expected a property (`name: value`) or a macro definition (`@name(args)`)",
+                    )),
                 }
             } else {
                 break 'outer;
@@ -83,125 +189,492 @@ impl<'a> Deserializer<'a> {
     }
 
     fn deserialize_macro(&mut self, aeon: &mut AeonDocument) -> DeserializeResult<()> {
-        let ident = require!(self.lexer.next(), arg Token::Identifier)?;
-        require!(self.lexer.next(), Token::LeftParenthesis)?;
-        let mut args = Vec::<String>::new();
-        while let Some(tok) = self.lexer.next()? {
-            match tok {
-                Token::Identifier(id) => {
-                    args.push(id);
-                }
+        let ident = require!(self, arg Token::Identifier, "macro name")?;
+        require!(self, Token::LeftParenthesis, "'(' after macro name")?;
+        if ident == "include"
+            && matches!(
+                self.peek_token()?,
+                Some(SpannedToken {
+                    token: Token::String(_),
+                    ..
+                })
+            )
+        {
+            return self.deserialize_include(aeon);
+        }
+        let mut args = Vec::<(String, Option<AeonValue>)>::new();
+        let mut types = Vec::<Option<AeonArgType>>::new();
+        loop {
+            let tok = match self.next_token()? {
+                Some(tok) => tok,
+                None => return Err(self.unexpected_end("argument name or ')' in macro definition")),
+            };
+            let arg = match tok.token {
+                Token::Identifier(id) => id,
                 Token::RightParenthesis => break,
                 e => {
                     return Err(AeonDeserializeError::deserialization(format!(
-                        "Unexpected token in macro definition: {:?}",
-                        e
-                    )))
+                        "Expected argument name in definition of macro '{}', found {}",
+                        ident, e
+                    ))
+                    .with_span(tok.span))
                 }
-            }
-            if let Some(comma_or_parens) = self.lexer.next()? {
-                match comma_or_parens {
-                    Token::Comma => (),
-                    Token::RightParenthesis => break,
-                    e => {
-                        return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in macro definition: {:?}",
-                            e
-                        )))
-                    }
+            };
+            let mut next = match self.next_token()? {
+                Some(next) => next,
+                None => return Err(self.unexpected_end("',' or ')' in macro definition")),
+            };
+            let typ = if let Token::Colon = next.token {
+                let typ = self.deserialize_arg_type(&ident, &arg)?;
+                next = match self.next_token()? {
+                    Some(next) => next,
+                    None => return Err(self.unexpected_end("',' or ')' in macro definition")),
+                };
+                Some(typ)
+            } else {
+                None
+            };
+            let default = if let Token::Equals = next.token {
+                let value_tok = match self.next_token()? {
+                    Some(value_tok) => value_tok,
+                    None => return Err(self.unexpected_end("default value")),
+                };
+                let value = self.deserialize_property_value(&value_tok, aeon)?;
+                if let Some(typ) = typ.filter(|t| !t.accepts(&value)) {
+                    return Err(AeonDeserializeError::deserialization(format!(
+                        "Default value of argument '{}' of macro '{}' must be {}, found {}",
+                        arg,
+                        ident,
+                        typ,
+                        AeonValue::tag_to_str(value.tag())
+                    ))
+                    .with_span(value_tok.span.to(self.last_span)));
                 }
+                next = match self.next_token()? {
+                    Some(next) => next,
+                    None => return Err(self.unexpected_end("',' or ')' in macro definition")),
+                };
+                Some(value)
             } else {
+                None
+            };
+            if default.is_none() && args.last().is_some_and(|(_, d)| d.is_some()) {
                 return Err(AeonDeserializeError::deserialization(format!(
-                    "Unterminated macro definition {}",
-                    ident
-                )));
+                    "Argument '{}' of macro '{}' has no default value, but follows an argument that does",
+                    arg, ident
+                ))
+                .with_span(tok.span)
+                .with_help("arguments with default values must come after all arguments without"));
+            }
+            args.push((arg, default));
+            types.push(typ);
+            match next.token {
+                Token::Comma => (),
+                Token::RightParenthesis => break,
+                e => {
+                    return Err(AeonDeserializeError::deserialization(format!(
+                        "Expected ',', ':', '=' or ')' in definition of macro '{}', found {}",
+                        ident, e
+                    ))
+                    .with_span(next.span))
+                }
+            }
+        }
+        let mac = AeonMacro::with_defaults(ident, args).with_types(types);
+        if self.included_macros.remove(&mac.name) {
+            aeon.add_or_replace_macro(mac);
+        } else {
+            aeon.add_macro(mac);
+        }
+        Ok(())
+    }
+
+    /// Deserializes the document in `@include("path")` and adds its macros and properties
+    fn deserialize_include(&mut self, aeon: &mut AeonDocument) -> DeserializeResult<()> {
+        let (path, span) = match self.next_token()? {
+            Some(SpannedToken {
+                token: Token::String(path),
+                span,
+            }) => (path, span),
+            _ => unreachable!("include path is peeked before calling deserialize_include"),
+        };
+        require!(self, Token::RightParenthesis, "')' after included path")?;
+        let resolver = self.resolver.ok_or_else(|| {
+            AeonDeserializeError::deserialization(format!(
+                "Cannot include '{}' without an include resolver",
+                path
+            ))
+            .with_span(span)
+            .with_help(
+                "use aeon::deserialize_with_resolver or aeon::include::load for documents with includes",
+            )
+        })?;
+        let included = resolver
+            .resolve(&path, self.chain.last().map(String::as_str))
+            .map_err(|e| {
+                AeonDeserializeError::deserialization(format!(
+                    "Could not include '{}': {}",
+                    path, e
+                ))
+                .with_span(span)
+            })?;
+        if self.chain.contains(&included.id) {
+            let start = self.chain.iter().position(|c| *c == included.id).unwrap();
+            return Err(AeonDeserializeError::deserialization(format!(
+                "Include cycle: {} -> {}",
+                self.chain[start..].join(" -> "),
+                included.id
+            ))
+            .with_span(span));
+        }
+        let mut chain = self.chain.clone();
+        chain.push(included.id);
+        let doc = Deserializer::with_resolver(&included.source, resolver, chain).deserialize()?;
+        for (name, mac) in doc.macros {
+            if !aeon.macros.contains_key(&name) {
+                self.included_macros.insert(name);
             }
+            aeon.add_macro(mac);
+        }
+        for (name, prop) in doc.properties {
+            if !aeon.properties.contains_key(&name) {
+                self.included_properties.insert(name);
+            }
+            aeon.add_property(&prop.name, prop.value);
         }
-        aeon.add_macro(AeonMacro::new(ident, args));
         Ok(())
     }
 
+    /// Parses the type after `arg:` in a macro definition, e.g. `int` or `string?`
+    fn deserialize_arg_type(&mut self, mac: &str, arg: &str) -> DeserializeResult<AeonArgType> {
+        let tok = match self.next_token()? {
+            Some(tok) => tok,
+            None => return Err(self.unexpected_end("argument type")),
+        };
+        let name = match &tok.token {
+            Token::Identifier(name) => name.as_str(),
+            Token::Nil => "nil",
+            e => {
+                return Err(AeonDeserializeError::deserialization(format!(
+                    "Expected type of argument '{}' of macro '{}', found {}",
+                    arg, mac, e
+                ))
+                .with_span(tok.span))
+            }
+        };
+        let nullable = matches!(
+            self.peek_token()?,
+            Some(SpannedToken {
+                token: Token::Question,
+                ..
+            })
+        );
+        let typ = AeonArgType::from_name(name, nullable).ok_or_else(|| {
+            AeonDeserializeError::deserialization(format!(
+                "Unknown type '{}' for argument '{}' of macro '{}'",
+                name, arg, mac
+            ))
+            .with_span(tok.span)
+            .with_help("the types are nil, bool, string, int, double, object and list")
+        })?;
+        if nullable {
+            self.next_token()?; // '?'
+        }
+        Ok(typ)
+    }
+
     fn deserialize_property(
         &mut self,
         aeon: &mut AeonDocument,
         prop_name: String,
     ) -> DeserializeResult<()> {
-        require!(self.lexer.next(), Token::Colon)?;
-        if let Some(tok) = self.lexer.next()? {
+        require!(self, Token::Colon, "':' after property name")?;
+        if let Some(tok) = self.next_token()? {
             let val = self.deserialize_property_value(&tok, aeon)?;
-            aeon.add_property(&prop_name, val);
+            if self.included_properties.remove(&prop_name) {
+                aeon.add_or_replace_property(prop_name, val);
+            } else {
+                aeon.add_property(&prop_name, val);
+            }
         } else {
-            return Err(AeonDeserializeError::deserialization(format!(
-                "Unterminated property value {}",
-                prop_name
-            )));
+            return Err(self.unexpected_end("property value"));
         }
         Ok(())
     }
 
     fn deserialize_property_value(
         &mut self,
-        tok: &Token,
+        tok: &SpannedToken,
+        aeon: &mut AeonDocument,
+    ) -> DeserializeResult<AeonValue> {
+        if self.depth == MAX_DEPTH {
+            return Err(AeonDeserializeError::deserialization(format!(
+                "Values are nested more than {} levels deep",
+                MAX_DEPTH
+            ))
+            .with_span(tok.span));
+        }
+        self.depth += 1;
+        let value = match &tok.token {
+            Token::Identifier(id) => self.deserialize_macro_use(id.clone(), tok.span, aeon),
+            Token::LeftBracket => self.deserialize_list(tok.span, aeon),
+            Token::LeftBrace => self.deserialize_map(tok.span, aeon),
+            Token::Anchor(name) => self.deserialize_anchor(name.clone(), aeon),
+            Token::Alias(name) => self.deserialize_alias(name, tok.span),
+            _ => self.deserialize_constants(tok),
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn deserialize_anchor(
+        &mut self,
+        name: String,
         aeon: &mut AeonDocument,
     ) -> DeserializeResult<AeonValue> {
-        match tok {
-            Token::Identifier(id) => self.deserialize_macro_use(id.clone(), aeon),
-            Token::LeftBracket => self.deserialize_list(aeon),
-            Token::LeftBrace => self.deserialize_map(aeon),
-            maybe => match self.deserialize_constants(maybe) {
-                t @ Ok(_) => t,
-                Err(e) => Err(AeonDeserializeError::deserialization(format!(
-                    "Unexpected token in property value: {:?}",
-                    e
-                ))),
-            },
+        let tok = match self.next_token()? {
+            Some(tok) => tok,
+            None => return Err(self.unexpected_end("value after anchor")),
+        };
+        self.open_anchors.push(name);
+        let value = self.deserialize_property_value(&tok, aeon);
+        let name = self.open_anchors.pop().unwrap();
+        let value = value?;
+        self.anchors
+            .insert(name, (value.clone(), count_values(&value)));
+        Ok(value)
+    }
+
+    fn deserialize_alias(&mut self, name: &str, span: Span) -> DeserializeResult<AeonValue> {
+        if self.open_anchors.iter().any(|a| a == name) {
+            return Err(AeonDeserializeError::deserialization(format!(
+                "Reference '*{}' is inside the value it refers to",
+                name
+            ))
+            .with_span(span)
+            .with_help("a value can't contain itself"));
         }
+        let (value, count) = match self.anchors.get(name) {
+            Some(anchor) => anchor,
+            None => {
+                return Err(AeonDeserializeError::deserialization(format!(
+                    "Unknown reference '*{}'",
+                    name
+                ))
+                .with_span(span)
+                .with_help(format!(
+                    "name a value with '&{} value' before referring to it",
+                    name
+                )))
+            }
+        };
+        self.referenced_values += count;
+        if self.referenced_values > MAX_REFERENCED_VALUES {
+            return Err(AeonDeserializeError::deserialization(format!(
+                "References copy more than {} values",
+                MAX_REFERENCED_VALUES
+            ))
+            .with_span(span));
+        }
+        Ok(value.clone())
     }
 
     fn deserialize_macro_use(
         &mut self,
         name: String,
+        name_span: Span,
         aeon: &mut AeonDocument,
     ) -> DeserializeResult<AeonValue> {
-        require!(self.lexer.next(), Token::LeftParenthesis)?;
-        let mut values = Vec::<AeonValue>::new();
-        while let Some(tok) = self.lexer.next()? {
-            match tok {
-                Token::RightParenthesis => break,
-                maybe => match self.deserialize_property_value(&maybe, aeon) {
-                    Ok(t) => values.push(t),
-                    Err(e) => {
-                        return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in macro call: {:?}",
-                            e
-                        )))
-                    }
-                },
+        let open = match self.next_token()? {
+            Some(SpannedToken {
+                token: Token::LeftParenthesis,
+                span,
+            }) => span,
+            Some(e) => {
+                return Err(AeonDeserializeError::deserialization(format!(
+                    "Expected '(' after macro name '{}', found {}",
+                    name, e.token
+                ))
+                .with_span(e.span)
+                .with_help("identifiers can only be used as values when calling a macro, did you mean to write a string?"))
             }
-            if let Some(comma_or_parens) = self.lexer.next()? {
-                match comma_or_parens {
+            None => return Err(self.unexpected_end("'(' after macro name")),
+        };
+        let mut args = Vec::<CallArg>::new();
+        let close = loop {
+            let tok = match self.next_token()? {
+                Some(tok) => tok,
+                None => return Err(self.unclosed("macro call", open, ')')),
+            };
+            match tok.token {
+                Token::RightParenthesis => break tok.span,
+                _ => args.push(self.deserialize_macro_arg(tok, aeon)?),
+            }
+            if let (Some((_, named)), Some(positional)) = (
+                args.iter().rev().skip(1).find_map(|a| a.name.as_ref()),
+                args.last().filter(|a| a.name.is_none()),
+            ) {
+                return Err(AeonDeserializeError::deserialization(format!(
+                    "Positional argument after named argument in call to macro '{}'",
+                    name
+                ))
+                .with_span(positional.span)
+                .with_help(format!(
+                    "positional arguments must come before named arguments like '{}: ...'",
+                    named
+                )));
+            }
+            if let Some(comma_or_parens) = self.next_token()? {
+                match comma_or_parens.token {
                     Token::Comma => (),
-                    Token::RightParenthesis => break,
+                    Token::RightParenthesis => break comma_or_parens.span,
                     e => {
                         return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in macro call: {:?}",
-                            e
-                        )))
+                            "Expected ',' or ')' after argument to macro '{}', found {}",
+                            name, e
+                        ))
+                        .with_span(comma_or_parens.span))
                     }
                 }
             } else {
+                return Err(self.unclosed("macro call", open, ')'));
+            }
+        };
+
+        let call = name_span.to(close);
+        let values = Self::bind_macro_args(&name, call, args, aeon)?;
+        aeon.apply_macro(name, values)
+            .map_err(|e| e.with_span(call))
+    }
+
+    fn deserialize_macro_arg(
+        &mut self,
+        tok: SpannedToken,
+        aeon: &mut AeonDocument,
+    ) -> DeserializeResult<CallArg> {
+        let is_named = matches!(tok.token, Token::Identifier(_))
+            && matches!(
+                self.peek_token()?,
+                Some(SpannedToken {
+                    token: Token::Colon,
+                    ..
+                })
+            );
+        let name = match &tok.token {
+            Token::Identifier(name) if is_named => name.clone(),
+            _ => {
+                let value = self.deserialize_property_value(&tok, aeon)?;
+                return Ok(CallArg {
+                    name: None,
+                    value,
+                    value_span: tok.span.to(self.last_span),
+                    span: tok.span,
+                });
+            }
+        };
+        self.next_token()?; // ':'
+        let value_tok = match self.next_token()? {
+            Some(value_tok) => value_tok,
+            None => return Err(self.unexpected_end("argument value")),
+        };
+        let value = self.deserialize_property_value(&value_tok, aeon)?;
+        Ok(CallArg {
+            name: Some((name, tok.span)),
+            value,
+            value_span: value_tok.span.to(self.last_span),
+            span: tok.span.to(value_tok.span),
+        })
+    }
+
+    /// Puts named arguments in the position of the macro parameter with the same name
+    /// and checks that every argument has the type the macro declares for it
+    fn bind_macro_args(
+        name: &str,
+        call: Span,
+        args: Vec<CallArg>,
+        aeon: &AeonDocument,
+    ) -> DeserializeResult<Vec<AeonValue>> {
+        let mac = match aeon.macros.get(name) {
+            Some(mac) if args.iter().any(|a| a.name.is_some()) => mac,
+            Some(mac) => {
+                // apply_macro reports the wrong number of args
+                for (idx, arg) in args.iter().enumerate().take(mac.len()) {
+                    mac.check_arg(idx, &arg.value)
+                        .map_err(|e| e.with_span(arg.value_span))?;
+                }
+                return Ok(args.into_iter().map(|a| a.value).collect());
+            }
+            // apply_macro reports unknown macros
+            None => return Ok(args.into_iter().map(|a| a.value).collect()),
+        };
+        let mut slots: Vec<Option<AeonValue>> = vec![None; mac.len()];
+        for (idx, arg) in args.into_iter().enumerate() {
+            let slot = match &arg.name {
+                None if idx < mac.len() => idx,
+                None => {
+                    return Err(AeonDeserializeError::deserialization(format!(
+                        "Too many args to macro {}: expected {}",
+                        name,
+                        mac.len()
+                    ))
+                    .with_span(arg.span)
+                    .with_help(format!(
+                        "macro '{}' is defined as {}",
+                        name,
+                        mac.signature()
+                    )))
+                }
+                Some((arg_name, arg_span)) => match mac.arg_index(arg_name) {
+                    Some(slot) => slot,
+                    None => {
+                        return Err(AeonDeserializeError::deserialization(format!(
+                            "Macro {} has no argument named '{}'",
+                            name, arg_name
+                        ))
+                        .with_span(*arg_span)
+                        .with_help(format!(
+                            "macro '{}' is defined as {}",
+                            name,
+                            mac.signature()
+                        )))
+                    }
+                },
+            };
+            if slots[slot].is_some() {
                 return Err(AeonDeserializeError::deserialization(format!(
-                    "Unterminated macro call {}",
-                    name
-                )));
+                    "Argument '{}' to macro {} is given more than once",
+                    mac.args[slot], name
+                ))
+                .with_span(arg.span));
             }
+            mac.check_arg(slot, &arg.value)
+                .map_err(|e| e.with_span(arg.value_span))?;
+            slots[slot] = Some(arg.value);
         }
-
-        Ok(aeon.apply_macro(name, values))
+        slots
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| {
+                value
+                    .or_else(|| mac.default_of(idx).cloned())
+                    .ok_or_else(|| {
+                        AeonDeserializeError::deserialization(format!(
+                            "Missing argument '{}' in call to macro {}",
+                            mac.args[idx], name
+                        ))
+                        .with_span(call)
+                        .with_help(format!(
+                            "macro '{}' is defined as {}",
+                            name,
+                            mac.signature()
+                        ))
+                    })
+            })
+            .collect()
     }
 
-    fn deserialize_constants(&mut self, tok: &Token) -> DeserializeResult<AeonValue> {
-        match tok {
+    fn deserialize_constants(&mut self, tok: &SpannedToken) -> DeserializeResult<AeonValue> {
+        match &tok.token {
             Token::String(s) => Ok(AeonValue::String(s.clone())),
             Token::Integer(i) => Ok(AeonValue::Integer(*i)),
             Token::Double(d) => Ok(AeonValue::Double(*d)),
@@ -209,43 +682,43 @@ impl<'a> Deserializer<'a> {
             Token::False => Ok(AeonValue::Bool(false)),
             Token::Nil => Ok(AeonValue::Nil),
             s => Err(AeonDeserializeError::deserialization(format!(
-                "Unexpected token {:?} when constant was expected",
+                "Expected a value, found {}",
                 s
-            ))),
+            ))
+            .with_span(tok.span)),
         }
     }
 
-    fn deserialize_list(&mut self, aeon: &mut AeonDocument) -> DeserializeResult<AeonValue> {
+    fn deserialize_list(
+        &mut self,
+        open: Span,
+        aeon: &mut AeonDocument,
+    ) -> DeserializeResult<AeonValue> {
         let mut values = Vec::<AeonValue>::new();
-        while let Some(tok) = self.lexer.next()? {
-            match tok {
+        loop {
+            let tok = match self.next_token()? {
+                Some(tok) => tok,
+                None => return Err(self.unclosed("list", open, ']')),
+            };
+            match tok.token {
                 Token::RightBracket => break,
-                maybe => match self.deserialize_property_value(&maybe, aeon) {
-                    Ok(t) => values.push(t),
-                    Err(e) => {
-                        return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in list: {:?}",
-                            e
-                        )))
-                    }
-                },
+                _ => values.push(self.deserialize_property_value(&tok, aeon)?),
             }
-            if let Some(comma_or_parens) = self.lexer.next()? {
-                match comma_or_parens {
+            if let Some(comma_or_parens) = self.next_token()? {
+                match comma_or_parens.token {
                     Token::Comma => (),
                     Token::RightBracket => break,
                     e => {
                         return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in list: {:?}",
+                            "Expected ',' or ']' after list item, found {}",
                             e
-                        )))
+                        ))
+                        .with_span(comma_or_parens.span)
+                        .with_help("list items are separated by ','"))
                     }
                 }
             } else {
-                return Err(AeonDeserializeError::deserialization(format!(
-                    "Unterminated list with values {:?}",
-                    values
-                )));
+                return Err(self.unclosed("list", open, ']'));
             }
         }
         Ok(AeonValue::List(values))
@@ -256,27 +729,27 @@ impl<'a> Deserializer<'a> {
         key: String,
         aeon: &mut AeonDocument,
     ) -> DeserializeResult<(String, AeonValue)> {
-        require!(self.lexer.next(), Token::Colon)?;
-        if let Some(next_tok) = self.lexer.next()? {
-            match self.deserialize_property_value(&next_tok, aeon) {
-                Ok(val) => Ok((key, val)),
-                Err(e) => Err(AeonDeserializeError::deserialization(format!(
-                    "Unexpected token in map: {:?}",
-                    e
-                ))),
-            }
+        require!(self, Token::Colon, "':' after map key")?;
+        if let Some(next_tok) = self.next_token()? {
+            let val = self.deserialize_property_value(&next_tok, aeon)?;
+            Ok((key, val))
         } else {
-            Err(AeonDeserializeError::deserialization(format!(
-                "Unterminated map with key {:?}",
-                key
-            )))
+            Err(self.unexpected_end("map value"))
         }
     }
 
-    fn deserialize_map(&mut self, aeon: &mut AeonDocument) -> DeserializeResult<AeonValue> {
-        let mut values = HashMap::<String, AeonValue>::new();
-        while let Some(tok) = self.lexer.next()? {
-            match tok {
+    fn deserialize_map(
+        &mut self,
+        open: Span,
+        aeon: &mut AeonDocument,
+    ) -> DeserializeResult<AeonValue> {
+        let mut values = OrderedMap::<String, AeonValue>::new();
+        loop {
+            let tok = match self.next_token()? {
+                Some(tok) => tok,
+                None => return Err(self.unclosed("map", open, '}')),
+            };
+            match tok.token {
                 Token::String(key) => {
                     let entry = self.deserialize_map_entry(key, aeon)?;
                     values.insert(entry.0, entry.1);
@@ -288,30 +761,42 @@ impl<'a> Deserializer<'a> {
                 Token::RightBrace => break,
                 e => {
                     return Err(AeonDeserializeError::deserialization(format!(
-                        "Unexpected token in map: was {:?}, expected string key",
+                        "Expected map key, found {}",
                         e
-                    )))
+                    ))
+                    .with_span(tok.span)
+                    .with_help("map keys are identifiers or strings"))
                 }
             }
 
-            if let Some(comma_or_parens) = self.lexer.next()? {
-                match comma_or_parens {
+            if let Some(comma_or_parens) = self.next_token()? {
+                match comma_or_parens.token {
                     Token::Comma => (),
                     Token::RightBrace => break,
                     e => {
                         return Err(AeonDeserializeError::deserialization(format!(
-                            "Unexpected token in map: {:?}",
+                            "Expected ',' or '}}' after map entry, found {}",
                             e
-                        )))
+                        ))
+                        .with_span(comma_or_parens.span)
+                        .with_help("map entries are separated by ','"))
                     }
                 }
             } else {
-                return Err(AeonDeserializeError::deserialization(format!(
-                    "Unterminated map with values {:?}",
-                    values
-                )));
+                return Err(self.unclosed("map", open, '}'));
             }
         }
         Ok(AeonValue::Object(values))
     }
 }
+
+/// Number of values in `value`, counting itself and everything it contains
+fn count_values(value: &AeonValue) -> usize {
+    match value {
+        AeonValue::List(items) => 1 + items.iter().map(count_values).sum::<usize>(),
+        AeonValue::Object(map) | AeonValue::Record(_, map) => {
+            1 + map.values().map(count_values).sum::<usize>()
+        }
+        _ => 1,
+    }
+}
@@ -0,0 +1,235 @@
+use crate::error::AeonDeserializeError;
+use crate::span::Span;
+use std::fmt::{Display, Formatter};
+
+const TAB_WIDTH: usize = 4;
+
+/// Renders an [`AeonDeserializeError`] together with the input it came from,
+/// underlining the part of the input that caused the error, e.g.
+///
+/// ```text
+/// error: Expected ',' or ']' after list item, found integer 4
+///  --> servers.aeon:3:7
+///   |
+/// 3 |     3 4]
+///   |       ^
+///   = help: list items are separated by ','
+/// ```
+pub struct Diagnostic<'a> {
+    error: &'a AeonDeserializeError,
+    source: &'a str,
+    file_name: Option<&'a str>,
+    help: Option<&'a str>,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// `source` should be the same input that produced `error`, or the included
+    /// document it occurred in if its `include_chain()` isn't empty
+    pub fn new(error: &'a AeonDeserializeError, source: &'a str) -> Diagnostic<'a> {
+        Diagnostic {
+            error,
+            source,
+            file_name: None,
+            help: None,
+        }
+    }
+
+    pub fn with_file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Adds a help note below the snippet, in addition to any help the error carries
+    pub fn with_help(mut self, help: &'a str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        self.to_string()
+    }
+
+    fn write_snippet(&self, f: &mut Formatter<'_>, span: Span, gutter: usize) -> std::fmt::Result {
+        let lines: Vec<&str> = self
+            .source
+            .split('\n')
+            .map(|l| l.strip_suffix('\r').unwrap_or(l))
+            .collect();
+        let start_line = span.start.line.clamp(1, lines.len());
+        // a span that ends right after a newline ends on the previous line
+        let mut end_line = span.end.line.clamp(start_line, lines.len());
+        let mut end_column = span.end.column;
+        if end_line > start_line && end_column == 1 {
+            end_line -= 1;
+            end_column = lines[end_line - 1].chars().count() + 1;
+        }
+
+        writeln!(f, "{:gutter$} |", "", gutter = gutter)?;
+        for line in [start_line, end_line] {
+            if line == end_line && line != start_line {
+                if end_line - start_line > 1 {
+                    writeln!(f, "{:gutter$} ...", "", gutter = gutter)?;
+                }
+            } else if line != start_line {
+                continue;
+            }
+            let text = lines[line - 1];
+            let (from, to) = match (line == start_line, line == end_line) {
+                (true, true) => (span.start.column, end_column.max(span.start.column + 1)),
+                (true, false) => (span.start.column, text.chars().count() + 2),
+                _ => (1, end_column),
+            };
+            let from = display_width(text, from);
+            let to = display_width(text, to).max(from + 1);
+            writeln!(
+                f,
+                "{:>gutter$} | {}",
+                line,
+                expand_tabs(text),
+                gutter = gutter
+            )?;
+            writeln!(
+                f,
+                "{:gutter$} | {:from$}{}",
+                "",
+                "",
+                "^".repeat(to - from),
+                gutter = gutter,
+                from = from
+            )?;
+            if start_line == end_line {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "error: {}", self.error.message)?;
+        let gutter = self
+            .error
+            .span
+            .map_or(1, |s| s.end.line.max(s.start.line).to_string().len());
+        let file_name = self
+            .error
+            .include_chain()
+            .last()
+            .map(String::as_str)
+            .or(self.file_name);
+        match (file_name, self.error.span) {
+            (Some(file), Some(span)) => {
+                writeln!(f, "{:gutter$}--> {}:{}", "", file, span, gutter = gutter)?
+            }
+            (None, Some(span)) => writeln!(f, "{:gutter$}--> {}", "", span, gutter = gutter)?,
+            (Some(file), None) => writeln!(f, "{:gutter$}--> {}", "", file, gutter = gutter)?,
+            (None, None) => (),
+        }
+        if let Some(span) = self.error.span {
+            self.write_snippet(f, span, gutter)?;
+        }
+        for help in self.error.help.as_deref().into_iter().chain(self.help) {
+            writeln!(f, "{:gutter$} = help: {}", "", help, gutter = gutter)?;
+        }
+        if let Some((_, outer)) = self.error.include_chain().split_last() {
+            for file in outer.iter().rev() {
+                writeln!(
+                    f,
+                    "{:gutter$} = note: included from {}",
+                    "",
+                    file,
+                    gutter = gutter
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Width of `text` up to the 1-based `column`, with tabs expanded
+fn display_width(text: &str, column: usize) -> usize {
+    let mut chars = text.chars();
+    let mut width = 0;
+    for _ in 1..column {
+        width += match chars.next() {
+            Some('\t') => TAB_WIDTH,
+            _ => 1,
+        };
+    }
+    width
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+
+    fn render(source: &str, file_name: Option<&str>) -> String {
+        let err = crate::deserialize(source.to_string()).expect_err("expected error");
+        let diagnostic = Diagnostic::new(&err, source);
+        match file_name {
+            Some(name) => diagnostic.with_file_name(name).render(),
+            None => diagnostic.render(),
+        }
+    }
+
+    #[test]
+    pub fn render_points_at_offending_token() {
+        let rendered = render("first: 1\nsecond: [1, 2,\n    3 4]\n", Some("servers.aeon"));
+        assert_eq!(
+            "error: Expected ',' or ']' after list item, found integer 4\n \
+             --> servers.aeon:3:7\n  \
+             |\n\
+             3 |     3 4]\n  \
+             |       ^\n  \
+             = help: list items are separated by ','\n",
+            rendered
+        );
+    }
+
+    #[test]
+    pub fn render_underlines_whole_token_and_expands_tabs() {
+        let rendered = render("\tname \"text\"", None);
+        assert_eq!(
+            "error: Expected ':' after property name, found string \"text\"\n \
+             --> 1:7\n  \
+             |\n\
+             1 |     name \"text\"\n  \
+             |          ^^^^^^\n",
+            rendered
+        );
+    }
+
+    #[test]
+    pub fn render_spans_over_multiple_lines() {
+        let rendered = render("text: \"one\ntwo\nthree", None);
+        assert!(
+            rendered.contains("1 | text: \"one\n  |       ^^^^^\n  ...\n3 | three\n  | ^^^^^\n"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    pub fn render_with_extra_help_and_wide_gutter() {
+        let source = format!("{}list: [1, 2", "\n".repeat(11));
+        let err = crate::deserialize(source.clone()).unwrap_err();
+        let rendered = Diagnostic::new(&err, &source)
+            .with_help("check the generated file")
+            .render();
+        assert_eq!(
+            "error: Unclosed list\n  \
+             --> 12:7\n   \
+             |\n\
+             12 | list: [1, 2\n   \
+             |       ^\n   \
+             = help: add a ']' to close the list\n   \
+             = help: check the generated file\n",
+            rendered
+        );
+    }
+}
@@ -0,0 +1,706 @@
+use crate::convert::AeonObjectConvert;
+use crate::document::{AeonDocument, AeonMacro};
+use crate::error::{AeonDeserializeError, AeonPathError, AeonPathErrorCode};
+use crate::ordered_map::OrderedMap;
+use crate::path::join;
+use crate::value::{inline, properties, AeonValue};
+use crate::{DeserializeResult, PathResult};
+use std::fmt::{Display, Formatter};
+
+/// Lists are aligned by their longest common subsequence, which takes time and memory
+/// proportional to the product of their lengths. Lists whose differing parts are larger than
+/// this are compared item by item instead.
+const MAX_ALIGNMENT_CELLS: usize = 1 << 22;
+
+/// A difference between two documents, found by [`diff`].
+///
+/// Paths of removed values and the `from` of moves refer to the old document,
+/// all other paths refer to the new one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added {
+        path: String,
+        value: AeonValue,
+    },
+    Removed {
+        path: String,
+        value: AeonValue,
+    },
+    Changed {
+        path: String,
+        old: AeonValue,
+        new: AeonValue,
+    },
+    /// A list item that was moved to another index without being changed
+    Moved {
+        from: String,
+        path: String,
+        value: AeonValue,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. }
+            | Change::Moved { path, .. } => path,
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, inline(value)),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, inline(value)),
+            Change::Changed { path, old, new } => {
+                write!(f, "~ {}: {} -> {}", path, inline(old), inline(new))
+            }
+            Change::Moved { from, path, .. } => write!(f, "> {} -> {}", from, path),
+        }
+    }
+}
+
+/// The changes that turn the properties of `old` into those of `new`.
+///
+/// Maps are compared by key, so the order of their keys never makes a difference.
+/// The macros that objects were written with aren't compared, only the values in them.
+pub fn diff(old: &AeonDocument, new: &AeonDocument) -> Vec<Change> {
+    diff_values(&properties(old), &properties(new))
+}
+
+/// Like [`diff`], with paths relative to `old` and `new`
+pub fn diff_values(old: &AeonValue, new: &AeonValue) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_value(old, new, "", &mut changes);
+    changes
+}
+
+fn diff_value(old: &AeonValue, new: &AeonValue, path: &str, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (
+            AeonValue::Object(a) | AeonValue::Record(_, a),
+            AeonValue::Object(b) | AeonValue::Record(_, b),
+        ) => {
+            for (key, value) in a.iter() {
+                match b.get(key) {
+                    Some(other) => diff_value(value, other, &join(path, key), changes),
+                    None => changes.push(Change::Removed {
+                        path: join(path, key),
+                        value: value.clone(),
+                    }),
+                }
+            }
+            for (key, value) in b.iter().filter(|(k, _)| !a.contains_key(*k)) {
+                changes.push(Change::Added {
+                    path: join(path, key),
+                    value: value.clone(),
+                });
+            }
+        }
+        (AeonValue::List(a), AeonValue::List(b)) => diff_list(a, b, path, changes),
+        (a, b) if same(a, b) => (),
+        (a, b) => changes.push(Change::Changed {
+            path: path.to_string(),
+            old: a.clone(),
+            new: b.clone(),
+        }),
+    }
+}
+
+fn diff_list(old: &[AeonValue], new: &[AeonValue], path: &str, changes: &mut Vec<Change>) {
+    let edits = align(old, new);
+    // items removed in one place and added unchanged in another were moved
+    let mut removed: Vec<usize> = Vec::new();
+    let mut added: Vec<usize> = Vec::new();
+    for edit in &edits {
+        if let Edit::Replace(dels, ins) = edit {
+            removed.extend(dels);
+            added.extend(ins);
+        }
+    }
+    let mut moves = Vec::new();
+    for &j in &added {
+        if let Some(k) = removed.iter().position(|&i| same(&old[i], &new[j])) {
+            moves.push((removed.remove(k), j));
+        }
+    }
+    let moved_from = |i: usize| moves.iter().any(|&(from, _)| from == i);
+    let moved_to = |j: usize| moves.iter().any(|&(_, to)| to == j);
+
+    for edit in edits {
+        let (dels, ins) = match edit {
+            Edit::Keep => continue,
+            Edit::Replace(dels, ins) => (dels, ins),
+        };
+        for &(from, to) in moves.iter().filter(|(_, to)| ins.contains(to)) {
+            changes.push(Change::Moved {
+                from: join(path, &from.to_string()),
+                path: join(path, &to.to_string()),
+                value: new[to].clone(),
+            });
+        }
+        let dels: Vec<usize> = dels.into_iter().filter(|&i| !moved_from(i)).collect();
+        let ins: Vec<usize> = ins.into_iter().filter(|&j| !moved_to(j)).collect();
+        for k in 0..dels.len().max(ins.len()) {
+            match (dels.get(k), ins.get(k)) {
+                (Some(&i), Some(&j)) => {
+                    diff_value(&old[i], &new[j], &join(path, &j.to_string()), changes)
+                }
+                (Some(&i), None) => changes.push(Change::Removed {
+                    path: join(path, &i.to_string()),
+                    value: old[i].clone(),
+                }),
+                (None, Some(&j)) => changes.push(Change::Added {
+                    path: join(path, &j.to_string()),
+                    value: new[j].clone(),
+                }),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// How the items of two lists line up
+#[derive(Debug)]
+enum Edit {
+    /// The same item in both lists
+    Keep,
+    /// Consecutive items of the old list replaced by consecutive items of the new list,
+    /// either of which may be empty
+    Replace(Vec<usize>, Vec<usize>),
+}
+
+fn align(old: &[AeonValue], new: &[AeonValue]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let (n, m) = (old.len() - prefix - suffix, new.len() - prefix - suffix);
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|_| Edit::Keep).collect();
+    let mut gap = (Vec::new(), Vec::new());
+    let flush = |gap: &mut (Vec<usize>, Vec<usize>), edits: &mut Vec<Edit>| {
+        if !gap.0.is_empty() || !gap.1.is_empty() {
+            edits.push(Edit::Replace(
+                std::mem::take(&mut gap.0),
+                std::mem::take(&mut gap.1),
+            ));
+        }
+    };
+    if n.saturating_mul(m) > MAX_ALIGNMENT_CELLS {
+        gap = (
+            (prefix..prefix + n).collect(),
+            (prefix..prefix + m).collect(),
+        );
+    } else {
+        // lengths of the longest common subsequences of the suffixes of both lists
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        let at = |i: usize, j: usize| i * (m + 1) + j;
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[at(i, j)] = if same(&old[prefix + i], &new[prefix + j]) {
+                    lcs[at(i + 1, j + 1)] + 1
+                } else {
+                    lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && same(&old[prefix + i], &new[prefix + j]) {
+                flush(&mut gap, &mut edits);
+                edits.push(Edit::Keep);
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+                gap.0.push(prefix + i);
+                i += 1;
+            } else {
+                gap.1.push(prefix + j);
+                j += 1;
+            }
+        }
+    }
+    flush(&mut gap, &mut edits);
+    edits.extend((0..suffix).map(|_| Edit::Keep));
+    edits
+}
+
+/// Whether two values are equal, ignoring the macros objects were written with
+fn same(a: &AeonValue, b: &AeonValue) -> bool {
+    match (a, b) {
+        (
+            AeonValue::Object(a) | AeonValue::Record(_, a),
+            AeonValue::Object(b) | AeonValue::Record(_, b),
+        ) => a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| same(v, w))),
+        (AeonValue::List(a), AeonValue::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(v, w)| same(v, w))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// A list of operations on a document, like RFC 6902 JSON Patch, written in Aeon as e.g.
+///
+/// ```aeon
+/// @test(path, value)
+/// @replace(path, value)
+/// @add(path, value)
+/// @remove(path)
+/// @move(from, path)
+/// @copy(from, path)
+/// patch: [
+///     test("server/port", 80),
+///     replace("server/port", 8080),
+///     add("servers/-", { name: "new" }),
+///     remove("debug"),
+/// ]
+/// ```
+///
+/// Operations are objects in the `patch` list, named by the macro that wrote them
+/// or by an `op` key, as in `{ op: "remove", path: "debug" }`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Patch {
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchOperation {
+    /// Adds a property or map entry, replacing any existing one, or inserts a list item
+    Add {
+        path: String,
+        value: AeonValue,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replaces a value that must exist
+    Replace {
+        path: String,
+        value: AeonValue,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fails the patch unless the value at `path` is `value`
+    Test {
+        path: String,
+        value: AeonValue,
+    },
+}
+
+impl PatchOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    fn args(&self) -> &'static [&'static str] {
+        match self {
+            PatchOperation::Add { .. }
+            | PatchOperation::Replace { .. }
+            | PatchOperation::Test { .. } => &["path", "value"],
+            PatchOperation::Remove { .. } => &["path"],
+            PatchOperation::Move { .. } | PatchOperation::Copy { .. } => &["from", "path"],
+        }
+    }
+}
+
+impl Patch {
+    /// The patch that turns `old` into `new`
+    pub fn between(old: &AeonDocument, new: &AeonDocument) -> Patch {
+        let mut patch = Patch::default();
+        patch.add_changes(&properties(old), &properties(new), "");
+        patch
+    }
+
+    fn add_changes(&mut self, old: &AeonValue, new: &AeonValue, path: &str) {
+        match (old, new) {
+            (
+                AeonValue::Object(a) | AeonValue::Record(_, a),
+                AeonValue::Object(b) | AeonValue::Record(_, b),
+            ) => {
+                for (key, value) in a.iter() {
+                    match b.get(key) {
+                        Some(other) => self.add_changes(value, other, &join(path, key)),
+                        None => self.operations.push(PatchOperation::Remove {
+                            path: join(path, key),
+                        }),
+                    }
+                }
+                for (key, value) in b.iter().filter(|(k, _)| !a.contains_key(*k)) {
+                    self.operations.push(PatchOperation::Add {
+                        path: join(path, key),
+                        value: value.clone(),
+                    });
+                }
+            }
+            (AeonValue::List(a), AeonValue::List(b)) => {
+                // the index of the next item, in the list as patched so far
+                let mut idx = 0;
+                for edit in align(a, b) {
+                    let (dels, ins) = match edit {
+                        Edit::Keep => {
+                            idx += 1;
+                            continue;
+                        }
+                        Edit::Replace(dels, ins) => (dels, ins),
+                    };
+                    for k in 0..dels.len().max(ins.len()) {
+                        let item = join(path, &idx.to_string());
+                        match (dels.get(k), ins.get(k)) {
+                            (Some(&i), Some(&j)) => {
+                                self.add_changes(&a[i], &b[j], &item);
+                                idx += 1;
+                            }
+                            (Some(_), None) => {
+                                self.operations.push(PatchOperation::Remove { path: item })
+                            }
+                            (None, Some(&j)) => {
+                                self.operations.push(PatchOperation::Add {
+                                    path: item,
+                                    value: b[j].clone(),
+                                });
+                                idx += 1;
+                            }
+                            (None, None) => unreachable!(),
+                        }
+                    }
+                }
+            }
+            (a, b) if same(a, b) => (),
+            (_, b) => self.operations.push(PatchOperation::Replace {
+                path: path.to_string(),
+                value: b.clone(),
+            }),
+        }
+    }
+
+    /// Reads a patch from Aeon, see [`Patch`] for the format
+    pub fn parse(code: &str) -> DeserializeResult<Patch> {
+        Patch::from_document(&crate::deserialize(code.to_string())?)
+    }
+
+    pub fn from_document(doc: &AeonDocument) -> DeserializeResult<Patch> {
+        let operations = match doc.get_path_ref("patch") {
+            Ok(AeonValue::List(operations)) => operations,
+            Ok(_) => {
+                return Err(AeonDeserializeError::conversion(
+                    "'patch' must be a list of operations".to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(AeonDeserializeError::conversion(
+                    "Missing property 'patch' with the list of operations".to_string(),
+                ))
+            }
+        };
+        let mut patch = Patch::default();
+        for (idx, op) in operations.iter().enumerate() {
+            patch.operations.push(read_operation(idx, op)?);
+        }
+        Ok(patch)
+    }
+
+    /// Writes the patch as a document, defining macros for the operations it uses
+    pub fn to_document(&self) -> AeonDocument {
+        let mut doc = AeonDocument::new();
+        let mut operations = Vec::with_capacity(self.operations.len());
+        for op in &self.operations {
+            let name = op.name();
+            if !doc.macros.contains_key(name) {
+                doc.add_macro(AeonMacro::new_cloned(name, op.args().to_vec()));
+            }
+            let mut map = OrderedMap::new();
+            match op.clone() {
+                PatchOperation::Add { path, value }
+                | PatchOperation::Replace { path, value }
+                | PatchOperation::Test { path, value } => {
+                    map.insert("path".to_string(), AeonValue::String(path));
+                    map.insert("value".to_string(), value);
+                }
+                PatchOperation::Remove { path } => {
+                    map.insert("path".to_string(), AeonValue::String(path));
+                }
+                PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                    map.insert("from".to_string(), AeonValue::String(from));
+                    map.insert("path".to_string(), AeonValue::String(path));
+                }
+            }
+            operations.push(AeonValue::Record(name.to_string(), map));
+        }
+        doc.add_property("patch", AeonValue::List(operations));
+        doc
+    }
+
+    /// Applies every operation in order. If one fails, `doc` is left unchanged.
+    pub fn apply(&self, doc: &mut AeonDocument) -> PathResult<()> {
+        let mut patched = doc.clone();
+        for op in &self.operations {
+            apply_operation(&mut patched, op)?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+}
+
+fn apply_operation(doc: &mut AeonDocument, op: &PatchOperation) -> PathResult<()> {
+    match op {
+        PatchOperation::Add { path, value } => add(doc, path, value.clone()),
+        PatchOperation::Remove { path } => doc.delete_path(path).map(|_| ()),
+        PatchOperation::Replace { path, value } => {
+            *doc.get_path_mut(path)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            let inside = path.trim_matches('/').strip_prefix(from.trim_matches('/'));
+            if inside.is_some_and(|rest| rest.starts_with('/')) {
+                return Err(AeonPathError::new(
+                    AeonPathErrorCode::InvalidEdit,
+                    format!("Cannot move '{}' into itself", from),
+                    path,
+                    0,
+                ));
+            }
+            let value = doc.delete_path(from)?;
+            add(doc, path, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = doc.get_path_ref(from)?.clone();
+            add(doc, path, value)
+        }
+        PatchOperation::Test { path, value } => {
+            let actual = doc.get_path_ref(path)?;
+            if same(actual, value) {
+                return Ok(());
+            }
+            Err(AeonPathError::new(
+                AeonPathErrorCode::TestFailed,
+                format!("Expected {}, found {}", inline(value), inline(actual)),
+                path,
+                path.split('/')
+                    .filter(|s| !s.is_empty())
+                    .count()
+                    .saturating_sub(1),
+            ))
+        }
+    }
+}
+
+/// Inserts into lists and sets properties and map entries, whose parent must exist
+fn add(doc: &mut AeonDocument, path: &str, value: AeonValue) -> PathResult<()> {
+    let parent = match path.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) if !parent.trim_matches('/').is_empty() => parent,
+        _ => return doc.set_path(path, value).map(|_| ()),
+    };
+    match doc.get_path_ref(parent)? {
+        AeonValue::List(_) => doc.insert_path(path, value),
+        _ => doc.set_path(path, value).map(|_| ()),
+    }
+}
+
+fn read_operation(idx: usize, op: &AeonValue) -> DeserializeResult<PatchOperation> {
+    let invalid = |message: String| {
+        AeonDeserializeError::conversion(format!("Operation {} of the patch {}", idx, message))
+    };
+    let (name, map) = match op {
+        AeonValue::Record(name, map) if !map.contains_key("op") => (name.as_str(), map),
+        AeonValue::Object(map) | AeonValue::Record(_, map) => match map.get("op") {
+            Some(AeonValue::String(name)) => (name.as_str(), map),
+            _ => return Err(invalid("has no 'op'".to_string())),
+        },
+        _ => return Err(invalid("is not an object".to_string())),
+    };
+    let string = |key: &str| match map.get(key) {
+        Some(AeonValue::String(s)) => Ok(s.clone()),
+        _ => Err(invalid(format!("has no '{}' string", key))),
+    };
+    let value = || {
+        map.get("value")
+            .cloned()
+            .ok_or_else(|| invalid("has no 'value'".to_string()))
+    };
+    Ok(match name {
+        "add" => PatchOperation::Add {
+            path: string("path")?,
+            value: value()?,
+        },
+        "remove" => PatchOperation::Remove {
+            path: string("path")?,
+        },
+        "replace" => PatchOperation::Replace {
+            path: string("path")?,
+            value: value()?,
+        },
+        "move" => PatchOperation::Move {
+            from: string("from")?,
+            path: string("path")?,
+        },
+        "copy" => PatchOperation::Copy {
+            from: string("from")?,
+            path: string("path")?,
+        },
+        "test" => PatchOperation::Test {
+            path: string("path")?,
+            value: value()?,
+        },
+        _ => {
+            return Err(invalid(format!("has the unknown op '{}'", name))
+                .with_help("the ops are 'add', 'remove', 'replace', 'move', 'copy' and 'test'"))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{diff, Change, Patch, PatchOperation};
+    use crate::document::AeonDocument;
+    use crate::value::AeonValue;
+
+    const OLD: &str = r#"
+        @server(name, port)
+        servers: [server("a", 1), server("b", 2), server("c", 3), server("d", 4)]
+        db: { host: "localhost", port: 5432, user: "admin" }
+        debug: true
+    "#;
+    const NEW: &str = r#"
+        db: { user: "admin", port: 5433, host: "localhost", pool: 10 }
+        servers: [
+            { name: "a", port: 1 }, { name: "c", port: 3 }, { name: "b", port: 20 },
+            { name: "e", port: 5 }, { name: "d", port: 4 },
+        ]
+        name: "app"
+    "#;
+
+    fn doc(code: &str) -> AeonDocument {
+        crate::deserialize(code.to_string()).unwrap()
+    }
+
+    #[test]
+    pub fn diff_reports_changes_by_path() {
+        let changes: Vec<String> = diff(&doc(OLD), &doc(NEW))
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                // b was changed as well as moved, so it's removed and added
+                "- servers/1: {name: \"b\", port: 2}",
+                "+ servers/2: {name: \"b\", port: 20}",
+                "+ servers/3: {name: \"e\", port: 5}",
+                "~ db/port: 5432 -> 5433",
+                "+ db/pool: 10",
+                "- debug: true",
+                "+ name: \"app\"",
+            ],
+            changes
+        );
+        // key order and macros don't count as changes
+        assert!(diff(&doc(NEW), &doc(NEW)).is_empty());
+        assert!(diff(&doc("a: {x: 1, y: 2}"), &doc("a: {y: 2, x: 1}")).is_empty());
+    }
+
+    #[test]
+    pub fn unchanged_items_moved_in_lists_are_moves() {
+        let changes = diff(&doc("a: [1, 2, 3, 4]"), &doc("a: [4, 1, 2, 3]"));
+        assert_eq!(
+            vec![Change::Moved {
+                from: "a/3".into(),
+                path: "a/0".into(),
+                value: AeonValue::Integer(4)
+            }],
+            changes
+        );
+    }
+
+    #[test]
+    pub fn patch_between_documents_applies() {
+        let (old, new) = (doc(OLD), doc(NEW));
+        let patch = Patch::between(&old, &new);
+        let written = crate::serialize(&patch.to_document()).unwrap();
+        let read = Patch::parse(&written).unwrap();
+        assert_eq!(patch, read, "{}", written);
+
+        let mut patched = doc(OLD);
+        read.apply(&mut patched).unwrap();
+        assert!(
+            diff(&patched, &new).is_empty(),
+            "{:?}",
+            diff(&patched, &new)
+        );
+    }
+
+    #[test]
+    pub fn patch_operations() {
+        let patch = Patch::parse(
+            r#"
+            @test(path, value)
+            @copy(from, path)
+            patch: [
+                test("db/port", 5432),
+                { op: "replace", path: "db/port", value: 5433 },
+                { op: "add", path: "servers/0", value: { name: "z", port: 0 } },
+                { op: "move", from: "debug", path: "db/debug" },
+                copy("db/host", "host"),
+                { op: "remove", path: "servers/-1" },
+            ]"#,
+        )
+        .unwrap();
+        let mut patched = doc(OLD);
+        patch.apply(&mut patched).unwrap();
+        let expected = doc(r#"
+            servers: [{name: "z", port: 0}, {name: "a", port: 1}, {name: "b", port: 2}, {name: "c", port: 3}]
+            db: { host: "localhost", port: 5433, user: "admin", debug: true }
+            host: "localhost""#);
+        assert!(
+            diff(&patched, &expected).is_empty(),
+            "{:?}",
+            diff(&patched, &expected)
+        );
+    }
+
+    #[test]
+    pub fn failed_patch_leaves_document_unchanged() {
+        let patch = Patch {
+            operations: vec![
+                PatchOperation::Remove {
+                    path: "debug".into(),
+                },
+                PatchOperation::Test {
+                    path: "db/port".into(),
+                    value: AeonValue::Integer(1),
+                },
+            ],
+        };
+        let mut patched = doc(OLD);
+        let err = patch.apply(&mut patched).unwrap_err();
+        assert_eq!("Expected 1, found 5432", err.message);
+        assert_eq!(Some("port"), err.segment_str());
+        assert!(diff(&patched, &doc(OLD)).is_empty());
+
+        let err = Patch::parse("patch: [{ op: \"jump\", path: \"a\" }]").unwrap_err();
+        assert_eq!(
+            "Operation 0 of the patch has the unknown op 'jump'",
+            err.message
+        );
+        let err = Patch::parse("patch: [{ op: \"add\", path: \"a\" }]").unwrap_err();
+        assert_eq!("Operation 0 of the patch has no 'value'", err.message);
+    }
+}
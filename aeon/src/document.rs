@@ -1,25 +1,129 @@
+use crate::error::{AeonDeserializeError, AeonSerializeError, AeonSerializeErrorCode};
+use crate::ordered_map::OrderedMap;
+use crate::serializer;
 use crate::value::AeonValue;
-use std::collections::HashMap;
+use crate::{DeserializeResult, SerializeResult};
+use std::fmt::{Display, Formatter};
 use std::iter::FromIterator;
 
+/// The type of a macro argument, e.g. `int` or `string?`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AeonArgType {
+    /// One of the tags returned by `AeonValue::tag`
+    pub tag: u8,
+    /// Whether `nil` is also accepted
+    pub nullable: bool,
+}
+
+impl AeonArgType {
+    pub fn new(tag: u8, nullable: bool) -> AeonArgType {
+        AeonArgType { tag, nullable }
+    }
+
+    /// Parses a type name like `int`, using the names from `AeonValue::tag_to_str`
+    pub fn from_name(name: &str, nullable: bool) -> Option<AeonArgType> {
+        AeonValue::tag_from_str(name).map(|tag| Self::new(tag, nullable))
+    }
+
+    pub fn accepts(&self, value: &AeonValue) -> bool {
+        value.tag() == self.tag || (self.nullable && matches!(value, AeonValue::Nil))
+    }
+}
+
+impl Display for AeonArgType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(AeonValue::tag_to_str(self.tag))?;
+        if self.nullable {
+            f.write_str("?")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AeonMacro {
     pub name: String,
     pub args: Vec<String>,
+    /// The default value of each argument in `args`, if it has one.
+    /// Arguments with a default value always come after those without.
+    pub defaults: Vec<Option<AeonValue>>,
+    /// The declared type of each argument in `args`, if it has one
+    pub types: Vec<Option<AeonArgType>>,
 }
 
 impl AeonMacro {
     pub fn new(name: String, args: Vec<String>) -> AeonMacro {
-        AeonMacro { name, args }
+        let defaults = vec![None; args.len()];
+        let types = vec![None; args.len()];
+        AeonMacro {
+            name,
+            args,
+            defaults,
+            types,
+        }
     }
 
     pub fn new_cloned(name: &str, args: Vec<&str>) -> AeonMacro {
+        Self::new(
+            name.to_string(),
+            args.into_iter().map(|a| a.to_string()).collect(),
+        )
+    }
+
+    pub fn with_defaults(name: String, args: Vec<(String, Option<AeonValue>)>) -> AeonMacro {
+        let (args, defaults): (Vec<String>, _) = args.into_iter().unzip();
+        let types = vec![None; args.len()];
         AeonMacro {
-            name: name.to_string(),
-            args: args.into_iter().map(|a| a.to_string()).collect(),
+            name,
+            args,
+            defaults,
+            types,
+        }
+    }
+
+    /// Sets the declared type of each argument, `types` is in the same order as `args`
+    pub fn with_types(mut self, types: Vec<Option<AeonArgType>>) -> AeonMacro {
+        self.types = types;
+        self.types.resize(self.args.len(), None);
+        self
+    }
+
+    pub fn default_of(&self, index: usize) -> Option<&AeonValue> {
+        self.defaults.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn type_of(&self, index: usize) -> Option<AeonArgType> {
+        self.types.get(index).copied().flatten()
+    }
+
+    /// Checks that `value` matches the declared type of the argument at `index`
+    pub fn check_arg(&self, index: usize, value: &AeonValue) -> DeserializeResult<()> {
+        match self.type_of(index) {
+            Some(typ) if !typ.accepts(value) => {
+                Err(AeonDeserializeError::deserialization(format!(
+                    "Argument '{}' of macro {} must be {}, found {}",
+                    self.args[index],
+                    self.name,
+                    typ,
+                    AeonValue::tag_to_str(value.tag())
+                ))
+                .with_help(format!(
+                    "macro '{}' is defined as {}",
+                    self.name,
+                    self.signature()
+                )))
+            }
+            _ => Ok(()),
         }
     }
 
+    /// Number of arguments without a default value, which every call has to pass
+    pub fn required_len(&self) -> usize {
+        (0..self.len())
+            .take_while(|&idx| self.default_of(idx).is_none())
+            .count()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.args.is_empty()
     }
@@ -28,21 +132,48 @@ impl AeonMacro {
         self.args.len()
     }
 
-    pub fn can_apply(&self, map: &HashMap<String, AeonValue>) -> bool {
-        for arg in &self.args {
-            if !map.contains_key(arg) {
-                return false;
+    /// Position of the argument called `name`
+    pub fn arg_index(&self, name: &str) -> Option<usize> {
+        self.args.iter().position(|a| a == name)
+    }
+
+    /// The macro as it's written in a definition, e.g. `@server(id, name)`
+    pub fn signature(&self) -> String {
+        let mut s = format!("@{}(", self.name);
+        for (idx, arg) in self.args.iter().enumerate() {
+            if idx != 0 {
+                s.push_str(", ");
+            }
+            s.push_str(arg);
+            if let Some(typ) = self.type_of(idx) {
+                s.push_str(": ");
+                s.push_str(&typ.to_string());
+            }
+            if let Some(default) = self.default_of(idx) {
+                s.push_str(" = ");
+                serializer::serialize_value_inline(&AeonDocument::new(), default, &mut s);
+            }
+        }
+        s.push(')');
+        s
+    }
+
+    pub fn can_apply(&self, map: &OrderedMap<String, AeonValue>) -> bool {
+        for (idx, arg) in self.args.iter().enumerate() {
+            match map.get(arg) {
+                Some(value) if self.type_of(idx).is_none_or(|t| t.accepts(value)) => (),
+                _ => return false,
             }
         }
         true
     }
 
-    pub fn apply(&self, index: usize, value: AeonValue, map: &mut HashMap<String, AeonValue>) {
+    pub fn apply(&self, index: usize, value: AeonValue, map: &mut OrderedMap<String, AeonValue>) {
         map.insert(self.args[index].clone(), value);
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AeonProperty {
     pub name: String,
     pub value: AeonValue,
@@ -54,25 +185,25 @@ impl AeonProperty {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct AeonDocument {
-    pub macros: HashMap<String, AeonMacro>,
-    pub properties: HashMap<String, AeonProperty>,
+    pub macros: OrderedMap<String, AeonMacro>,
+    pub properties: OrderedMap<String, AeonProperty>,
     pub is_empty: bool,
 }
 
 impl AeonDocument {
     pub fn new() -> AeonDocument {
         AeonDocument {
-            macros: HashMap::new(),
-            properties: HashMap::new(),
+            macros: OrderedMap::new(),
+            properties: OrderedMap::new(),
             is_empty: true,
         }
     }
 
     pub fn try_from_object(object: AeonValue) -> Option<AeonDocument> {
         match object {
-            AeonValue::Object(obj) => Some(Self::from_iter(obj)),
+            AeonValue::Object(obj) | AeonValue::Record(_, obj) => Some(Self::from_iter(obj)),
             _ => None,
         }
     }
@@ -105,34 +236,59 @@ impl AeonDocument {
         self.is_empty = false;
     }
 
-    pub fn set_macros(&mut self, macros: HashMap<String, AeonMacro>) {
+    pub fn set_macros(&mut self, macros: OrderedMap<String, AeonMacro>) {
         self.macros = macros;
     }
 
-    pub fn apply_macro(&mut self, name: String, mut params: Vec<AeonValue>) -> AeonValue {
+    pub fn apply_macro(
+        &mut self,
+        name: String,
+        mut params: Vec<AeonValue>,
+    ) -> DeserializeResult<AeonValue> {
         if let Some(mac) = self.macros.get(name.as_str()) {
             let len = params.len();
-            if mac.len() != len {
-                panic!(
+            let required = mac.required_len();
+            if len < required || len > mac.len() {
+                let expected = if required == mac.len() {
+                    required.to_string()
+                } else {
+                    format!("{} to {}", required, mac.len())
+                };
+                return Err(AeonDeserializeError::deserialization(format!(
                     "Wrong number of args to macro {}: was {}, expected {}",
+                    name, len, expected
+                ))
+                .with_help(format!(
+                    "macro '{}' is defined as {}",
                     name,
-                    len,
-                    mac.len()
-                );
+                    mac.signature()
+                )));
             }
 
-            let mut map = HashMap::<String, AeonValue>::new();
+            let mut map = OrderedMap::<String, AeonValue>::new();
 
             for (idx, parameter) in params.drain(..).enumerate() {
+                mac.check_arg(idx, &parameter)?;
                 mac.apply(idx, parameter, &mut map);
             }
-            AeonValue::Object(map)
+            for idx in len..mac.len() {
+                if let Some(default) = mac.default_of(idx) {
+                    mac.apply(idx, default.clone(), &mut map);
+                }
+            }
+            Ok(AeonValue::Record(name, map))
         } else {
-            panic!("Macro does not exist: {}", name);
+            Err(
+                AeonDeserializeError::deserialization(format!("Macro does not exist: {}", name))
+                    .with_help(format!(
+                        "macros must be defined with @{}(...) before they are used",
+                        name
+                    )),
+            )
         }
     }
 
-    pub fn try_get_macro(&self, map: &HashMap<String, AeonValue>) -> Option<&AeonMacro> {
+    pub fn try_get_macro(&self, map: &OrderedMap<String, AeonValue>) -> Option<&AeonMacro> {
         if let Some((_, m)) = self
             .macros
             .iter()
@@ -144,11 +300,50 @@ impl AeonDocument {
         None
     }
 
+    /// Gets the macro called `name`, if it can be applied to `map`
+    pub fn try_get_named_macro(
+        &self,
+        name: &str,
+        map: &OrderedMap<String, AeonValue>,
+    ) -> Option<&AeonMacro> {
+        self.macros
+            .get(name)
+            .filter(|m| m.len() == map.len() && m.can_apply(map))
+    }
+
     pub fn copy_macros_to(&self, other: &mut AeonDocument) {
         other.macros.extend(self.macros.clone());
     }
 }
 
+/// Adds `other` to `macros`, failing if a macro of the same name has different arguments,
+/// since the values of one type would then be written with the macro of another
+pub fn merge_macros(
+    macros: &mut OrderedMap<String, AeonMacro>,
+    other: OrderedMap<String, AeonMacro>,
+) -> SerializeResult<()> {
+    for (name, m) in other {
+        match macros.get(&name) {
+            Some(existing) if existing.args != m.args => {
+                return Err(AeonSerializeError {
+                    code: AeonSerializeErrorCode::ConversionFailed,
+                    message: format!(
+                        "Two types are written with the macro `{}`, as {} and as {}, rename one of them with #[aeon(rename = \"...\")]",
+                        name,
+                        existing.signature(),
+                        m.signature()
+                    ),
+                })
+            }
+            Some(_) => (),
+            None => {
+                macros.insert(name, m);
+            }
+        }
+    }
+    Ok(())
+}
+
 impl FromIterator<(String, AeonValue)> for AeonDocument {
     fn from_iter<T: IntoIterator<Item = (String, AeonValue)>>(iter: T) -> Self {
         let mut doc = Self::new();
@@ -163,6 +358,7 @@ impl FromIterator<(String, AeonValue)> for AeonDocument {
 mod tests {
     use crate::convert::*;
     use crate::document::{AeonDocument, AeonMacro, AeonValue};
+    use crate::error::AeonPathErrorCode;
     use crate::map;
 
     #[test]
@@ -259,4 +455,83 @@ mod tests {
             aeon.remove_path("char/or_nothing/world").int().unwrap()
         );
     }
+
+    fn servers() -> AeonDocument {
+        crate::deserialize(
+            "servers: [{ port: 80 }, { port: 81 }, { port: 82 }]\nname: \"x\"".to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    pub fn paths_index_into_lists() {
+        let mut aeon = servers();
+        assert_eq!(
+            &AeonValue::Integer(81),
+            aeon.get_path_ref("servers/1/port").unwrap()
+        );
+        assert_eq!(
+            &AeonValue::Integer(82),
+            aeon.get_path_ref("servers/-1/port").unwrap()
+        );
+        assert_eq!(aeon.get_path("servers/-3/port").int(), Some(80));
+        *aeon.get_path_mut("servers/0/port").unwrap() = AeonValue::Integer(8080);
+        assert_eq!(aeon.get_path("servers/0/port").int(), Some(8080));
+    }
+
+    #[test]
+    pub fn set_insert_and_delete_paths() {
+        let mut aeon = servers();
+        assert_eq!(
+            Some(AeonValue::Integer(80)),
+            aeon.set_path("servers/0/port", AeonValue::Integer(1))
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            aeon.set_path("tls/cert/path", AeonValue::Nil).unwrap()
+        );
+        assert_eq!(&AeonValue::Nil, aeon.get_path_ref("tls/cert/path").unwrap());
+        aeon.set_path("servers/-/port", AeonValue::Integer(83))
+            .unwrap();
+        aeon.insert_path("servers/0", AeonValue::Nil).unwrap();
+        aeon.insert_path("servers/-", AeonValue::Nil).unwrap();
+        assert_eq!(aeon.get_path("servers").list().unwrap().len(), 6);
+        assert_eq!(aeon.get_path("servers/4/port").int(), Some(83));
+
+        assert_eq!(AeonValue::Nil, aeon.delete_path("servers/-1").unwrap());
+        assert_eq!(AeonValue::Nil, aeon.delete_path("servers/0").unwrap());
+        assert_eq!(
+            aeon.delete_path("servers/0").unwrap().get("port").int(),
+            Some(1)
+        );
+        assert_eq!(aeon.delete_path("name").unwrap().str(), Some("x".into()));
+        assert_eq!(None, aeon.get_path("name"));
+    }
+
+    #[test]
+    pub fn path_errors_name_the_failing_segment() {
+        let mut aeon = servers();
+        let bad = [
+            ("servers/3/port", "IndexOutOfRange", "3"),
+            ("servers/-4", "IndexOutOfRange", "-4"),
+            ("servers/x", "NotFound", "x"),
+            ("servers/0/host", "NotFound", "host"),
+            ("name/first", "NotAContainer", "name"),
+            ("nothing/1", "NotFound", "nothing"),
+        ];
+        for (path, code, segment) in bad {
+            let err = aeon.get_path_ref(path).unwrap_err();
+            assert_eq!(code, err.code.variant_name(), "{}", err);
+            assert_eq!(Some(segment), err.segment_str(), "{}", err);
+        }
+        let err = aeon
+            .insert_path("servers/0/port", AeonValue::Nil)
+            .unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::AlreadyExists));
+        let err = aeon.set_path("name/first", AeonValue::Nil).unwrap_err();
+        assert!(matches!(err.code, AeonPathErrorCode::NotAContainer));
+        let err = aeon.delete_path("").unwrap_err();
+        assert_eq!("Path is empty", err.message);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::span::Span;
 use std::fmt::{Display, Formatter};
 
 macro_rules! str_variants(
@@ -35,6 +36,18 @@ str_variants! {
     }
 }
 
+str_variants! {
+    pub enum AeonPathErrorCode {
+        NotFound,
+        IndexOutOfRange,
+        NotAContainer,
+        AlreadyExists,
+        InvalidEdit,
+        InvalidQuery,
+        TestFailed,
+    }
+}
+
 #[derive(Debug)]
 pub struct AeonSerializeError {
     pub code: AeonSerializeErrorCode,
@@ -45,6 +58,24 @@ pub struct AeonSerializeError {
 pub struct AeonDeserializeError {
     pub code: AeonDeserializeErrorCode,
     pub message: String,
+    /// Where in the source the error occurred, if known
+    pub span: Option<Span>,
+    /// Suggestion on how to fix the error, shown by [`crate::diagnostic::Diagnostic`]
+    pub help: Option<String>,
+    /// See [`AeonDeserializeError::include_chain`], boxed behind a thin pointer
+    /// to keep results with this error small
+    #[allow(clippy::box_collection)]
+    include_chain: Option<Box<Vec<String>>>,
+}
+
+#[derive(Debug)]
+pub struct AeonPathError {
+    pub code: AeonPathErrorCode,
+    pub message: String,
+    /// The whole path that was being resolved
+    pub path: String,
+    /// Index of the path segment that failed, ignoring empty segments
+    pub segment: usize,
 }
 
 impl Display for AeonSerializeError {
@@ -55,7 +86,46 @@ impl Display for AeonSerializeError {
 
 impl Display for AeonDeserializeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.code.variant_name(), self.message)
+        write!(f, "{}: {}", self.code.variant_name(), self.message)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}", span)?;
+        }
+        if let Some((file, outer)) = self.include_chain().split_last() {
+            write!(f, " in {}", file)?;
+            for outer in outer.iter().rev() {
+                write!(f, ", included from {}", outer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for AeonPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code.variant_name(), self.message)?;
+        match self.segment_str() {
+            Some(segment) => write!(f, " at segment '{}' of path '{}'", segment, self.path),
+            None => write!(f, " in path '{}'", self.path),
+        }
+    }
+}
+
+impl AeonPathError {
+    pub fn new(code: AeonPathErrorCode, message: String, path: &str, segment: usize) -> Self {
+        Self {
+            code,
+            message,
+            path: path.to_string(),
+            segment,
+        }
+    }
+
+    /// The path segment that failed
+    pub fn segment_str(&self) -> Option<&str> {
+        self.path
+            .split('/')
+            .filter(|f| !f.is_empty())
+            .nth(self.segment)
     }
 }
 
@@ -64,6 +134,9 @@ impl AeonDeserializeError {
         Self {
             code: AeonDeserializeErrorCode::LexingFailed,
             message,
+            span: None,
+            help: None,
+            include_chain: None,
         }
     }
 
@@ -71,6 +144,9 @@ impl AeonDeserializeError {
         Self {
             code: AeonDeserializeErrorCode::DeserializationFailed,
             message,
+            span: None,
+            help: None,
+            include_chain: None,
         }
     }
 
@@ -78,6 +154,37 @@ impl AeonDeserializeError {
         Self {
             code: AeonDeserializeErrorCode::ConversionFailed,
             message,
+            span: None,
+            help: None,
+            include_chain: None,
         }
     }
+
+    /// Attaches a span, unless the error already has a more specific one
+    pub fn with_span(mut self, span: Span) -> Self {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Attaches the chain of includes leading to the error, unless it already has one
+    pub fn with_include_chain(mut self, chain: &[String]) -> Self {
+        if self.include_chain.is_none() && !chain.is_empty() {
+            self.include_chain = Some(Box::new(chain.to_vec()));
+        }
+        self
+    }
+
+    /// The included documents the error occurred in, outermost first.
+    /// Empty if the error is in the document that was deserialized, otherwise `span`
+    /// points into the last document in the chain.
+    pub fn include_chain(&self) -> &[String] {
+        self.include_chain.as_deref().map_or(&[], Vec::as_slice)
+    }
 }
@@ -0,0 +1,94 @@
+use crate::deserializer::Deserializer;
+use crate::document::AeonDocument;
+use crate::error::AeonDeserializeError;
+use crate::DeserializeResult;
+use std::path::{Path, PathBuf};
+
+/// A document found by an [`IncludeResolver`]
+#[derive(Clone, Debug)]
+pub struct IncludeSource {
+    /// Identifies the document, e.g. its canonical path. Used to detect include cycles,
+    /// in error messages and as `from` when resolving includes inside the document.
+    pub id: String,
+    pub source: String,
+}
+
+impl IncludeSource {
+    pub fn new(id: impl Into<String>, source: impl Into<String>) -> IncludeSource {
+        IncludeSource {
+            id: id.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// Finds the documents pulled in by `@include("path")`
+pub trait IncludeResolver {
+    /// Resolves `path` as written in the include directive of the document `from`,
+    /// which is `None` when loading the first document with [`load`].
+    /// Errors are messages explaining why the document couldn't be included.
+    fn resolve(&self, path: &str, from: Option<&str>) -> Result<IncludeSource, String>;
+}
+
+/// Resolves includes as files, relative to the directory of the including file.
+///
+/// By default only files inside the base directory can be included,
+/// so that a document can't read arbitrary files through `../`.
+#[derive(Clone, Debug)]
+pub struct FileResolver {
+    base: PathBuf,
+    restrict_to_base: bool,
+}
+
+impl FileResolver {
+    /// Relative paths in documents loaded directly with [`load`] start from `base`
+    pub fn new(base: impl Into<PathBuf>) -> FileResolver {
+        FileResolver {
+            base: base.into(),
+            restrict_to_base: true,
+        }
+    }
+
+    /// Whether files outside of the base directory are rejected, defaults to true
+    pub fn restrict_to_base(mut self, restrict: bool) -> Self {
+        self.restrict_to_base = restrict;
+        self
+    }
+}
+
+impl IncludeResolver for FileResolver {
+    fn resolve(&self, path: &str, from: Option<&str>) -> Result<IncludeSource, String> {
+        let dir = match from.and_then(|f| Path::new(f).parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => self.base.clone(),
+        };
+        let full = dir
+            .join(path)
+            .canonicalize()
+            .map_err(|e| format!("{}: {}", dir.join(path).display(), e))?;
+        if self.restrict_to_base {
+            let base = self
+                .base
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", self.base.display(), e))?;
+            if !full.starts_with(&base) {
+                return Err(format!(
+                    "{} is outside of the base directory {}",
+                    full.display(),
+                    base.display()
+                ));
+            }
+        }
+        let source =
+            std::fs::read_to_string(&full).map_err(|e| format!("{}: {}", full.display(), e))?;
+        Ok(IncludeSource::new(full.to_string_lossy(), source))
+    }
+}
+
+/// Resolves `path` with `resolver` and deserializes it, along with everything it includes
+pub fn load(path: &str, resolver: &dyn IncludeResolver) -> DeserializeResult<AeonDocument> {
+    let included = resolver.resolve(path, None).map_err(|e| {
+        AeonDeserializeError::deserialization(format!("Could not load '{}': {}", path, e))
+    })?;
+    Deserializer::with_resolver(&included.source, resolver, vec![included.id]).deserialize()
+}
@@ -0,0 +1,350 @@
+use crate::convert::AeonObjectConvert;
+use crate::cst::CstDocument;
+use crate::document::AeonDocument;
+use crate::error::AeonDeserializeError;
+use crate::value::AeonValue;
+use crate::DeserializeResult;
+use std::collections::HashMap;
+
+/// Longest string interpolation may create, so that strings referring to each other
+/// several times can't grow exponentially
+const MAX_LEN: usize = 1 << 20;
+
+/// Looks up the environment variables used by [`interpolate`]
+pub trait EnvProvider {
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+/// The environment of the current process
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemEnv;
+
+impl EnvProvider for SystemEnv {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+impl EnvProvider for HashMap<String, String> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Expands `${...}` in every string of `doc`.
+///
+/// `${server/host}` is replaced with the value at that path of the document, as with
+/// [`AeonObjectConvert::get_path`]. If there is no such value, `${HOME}` is replaced
+/// with the environment variable `HOME`. `${PORT:-8080}` falls back to `8080`
+/// if `PORT` isn't set or is empty, and `$$` is written for a literal `$`.
+pub fn interpolate(doc: &mut AeonDocument, env: &dyn EnvProvider) -> DeserializeResult<()> {
+    expand_document(doc, env, &mut None)
+}
+
+/// Deserializes `source` and interpolates it, giving errors the span of the string they're in
+pub fn interpolate_source(source: &str, env: &dyn EnvProvider) -> DeserializeResult<AeonDocument> {
+    let cst = CstDocument::parse(source.to_string())?;
+    let mut doc = cst.to_document()?;
+    let mut failed_at = None;
+    match expand_document(&mut doc, env, &mut failed_at) {
+        Ok(()) => Ok(doc),
+        Err(e) => Err(match failed_at.and_then(|path| cst.span_of(&path)) {
+            Some(span) => e.with_span(span),
+            None => e,
+        }),
+    }
+}
+
+/// Expands the strings of `doc`, setting `failed_at` to the path of the string an error is in
+fn expand_document(
+    doc: &mut AeonDocument,
+    env: &dyn EnvProvider,
+    failed_at: &mut Option<String>,
+) -> DeserializeResult<()> {
+    let mut expanded = Vec::with_capacity(doc.properties.len());
+    {
+        let mut interpolator = Interpolator {
+            doc,
+            env,
+            resolved: HashMap::new(),
+            stack: Vec::new(),
+            failed_at: None,
+        };
+        for (name, prop) in doc.properties.iter() {
+            let value = interpolator.expand_value(&prop.value, name);
+            if value.is_err() {
+                *failed_at = interpolator.failed_at.take();
+            }
+            expanded.push((name.clone(), value?));
+        }
+    }
+    for (name, value) in expanded {
+        doc.add_or_replace_property(name, value);
+    }
+    Ok(())
+}
+
+struct Interpolator<'a> {
+    doc: &'a AeonDocument,
+    env: &'a dyn EnvProvider,
+    /// Expanded strings of the values referred to so far, by path
+    resolved: HashMap<String, String>,
+    /// Paths of the strings being expanded, to detect cycles
+    stack: Vec<String>,
+    /// Path of the string that the first error was in
+    failed_at: Option<String>,
+}
+
+impl Interpolator<'_> {
+    fn expand_value(&mut self, value: &AeonValue, path: &str) -> DeserializeResult<AeonValue> {
+        Ok(match value {
+            AeonValue::String(s) => AeonValue::String(self.expand_at(s, path)?),
+            AeonValue::List(items) => AeonValue::List(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.expand_value(item, &format!("{}/{}", path, i)))
+                    .collect::<DeserializeResult<_>>()?,
+            ),
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                let map = map
+                    .iter()
+                    .map(|(k, v)| {
+                        Ok((k.clone(), self.expand_value(v, &format!("{}/{}", path, k))?))
+                    })
+                    .collect::<DeserializeResult<_>>()?;
+                match value {
+                    AeonValue::Record(name, _) => AeonValue::Record(name.clone(), map),
+                    _ => AeonValue::Object(map),
+                }
+            }
+            v => v.clone(),
+        })
+    }
+
+    /// Expands the string `s`, which is the value at `path`
+    fn expand_at(&mut self, s: &str, path: &str) -> DeserializeResult<String> {
+        if let Some(done) = self.resolved.get(path) {
+            return Ok(done.clone());
+        }
+        self.stack.push(path.to_string());
+        let expanded = self.expand(s, path);
+        self.stack.pop();
+        if expanded.is_err() && self.failed_at.is_none() {
+            self.failed_at = Some(path.to_string());
+        }
+        let expanded = expanded?;
+        self.resolved.insert(path.to_string(), expanded.clone());
+        Ok(expanded)
+    }
+
+    fn expand(&mut self, s: &str, path: &str) -> DeserializeResult<String> {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(idx) = rest.find('$') {
+            out.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            if let Some(after) = rest.strip_prefix("$$") {
+                out.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("${") {
+                let end = match closing_brace(after) {
+                    Some(end) => end,
+                    None => {
+                        return Err(AeonDeserializeError::deserialization(format!(
+                            "Unclosed '${{' in string at '{}'",
+                            path
+                        ))
+                        .with_help("write '$$' for a literal '$'"))
+                    }
+                };
+                out.push_str(&self.substitute(&after[..end], path)?);
+                rest = &after[end + 1..];
+                if out.len() > MAX_LEN {
+                    return Err(AeonDeserializeError::deserialization(format!(
+                        "String at '{}' is longer than {} bytes after interpolation",
+                        path, MAX_LEN
+                    )));
+                }
+            } else {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// The replacement for `${expr}` in the string at `path`
+    fn substitute(&mut self, expr: &str, path: &str) -> DeserializeResult<String> {
+        let (name, default) = match expr.find(":-") {
+            Some(idx) => (&expr[..idx], Some(&expr[idx + 2..])),
+            None => (expr, None),
+        };
+        let name = name.trim();
+        let value = match self.lookup(name)? {
+            Some(value) => Some(value),
+            None => self.env.var(name),
+        };
+        match (value, default) {
+            (Some(value), Some(_)) if !value.is_empty() => Ok(value),
+            (_, Some(default)) => self.expand(default, path),
+            (Some(value), None) => Ok(value),
+            (None, None) => Err(AeonDeserializeError::deserialization(format!(
+                "Unknown variable '{}' in string at '{}'",
+                name, path
+            ))
+            .with_help(format!(
+                "set the environment variable or give a default with '${{{}:-default}}'",
+                name
+            ))),
+        }
+    }
+
+    /// The value at the path `name` of the document, if there is one
+    fn lookup(&mut self, name: &str) -> DeserializeResult<Option<String>> {
+        let value = match self.doc.get_path_ref(name) {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        match value {
+            AeonValue::Nil => Ok(None),
+            AeonValue::Bool(b) => Ok(Some(b.to_string())),
+            AeonValue::Integer(i) => Ok(Some(i.to_string())),
+            AeonValue::Double(d) => Ok(Some(d.to_string())),
+            AeonValue::String(s) => {
+                if let Some(start) = self.stack.iter().position(|p| p == name) {
+                    return Err(AeonDeserializeError::deserialization(format!(
+                        "Cyclic interpolation: {} -> {}",
+                        self.stack[start..].join(" -> "),
+                        name
+                    )));
+                }
+                self.expand_at(s, name).map(Some)
+            }
+            v => Err(AeonDeserializeError::deserialization(format!(
+                "Cannot interpolate '{}', which is of type '{}'",
+                name,
+                AeonValue::tag_to_str(v.tag())
+            ))
+            .with_help("only strings, numbers and bools can be put into strings")),
+        }
+    }
+}
+
+/// Index of the '}' closing a `${`, skipping nested `${...}` in defaults
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '$' if matches!(chars.peek(), Some((_, '{'))) => {
+                chars.next();
+                depth += 1;
+            }
+            '}' if depth == 0 => return Some(idx),
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{AeonConvert, AeonObjectConvert};
+    use crate::interpolate::{interpolate, interpolate_source};
+    use std::collections::HashMap;
+
+    fn env() -> HashMap<String, String> {
+        [("HOME", "/home/aeon"), ("EMPTY", "")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn expand(code: &str) -> crate::DeserializeResult<crate::document::AeonDocument> {
+        let mut doc = crate::deserialize(code.to_string())?;
+        interpolate(&mut doc, &env())?;
+        Ok(doc)
+    }
+
+    #[test]
+    pub fn expands_environment_and_document_values() {
+        let doc = expand(
+            r#"data: "${HOME}/data"
+            server: { host: "localhost", port: 8080, tls: false }
+            url: "http://${server/host}:${server/port}/?tls=${server/tls}"
+            hosts: ["${url}", "$${HOME} costs $5"]"#,
+        )
+        .unwrap();
+        assert_eq!(doc.get_path("data").str(), Some("/home/aeon/data".into()));
+        assert_eq!(
+            doc.get_path("url").str(),
+            Some("http://localhost:8080/?tls=false".into())
+        );
+        assert_eq!(
+            doc.get("hosts").list().unwrap()[1].clone().str(),
+            Some("${HOME} costs $5".into())
+        );
+    }
+
+    #[test]
+    pub fn defaults_are_used_for_missing_and_empty_values() {
+        let doc = expand(
+            r#"port: "${PORT:-8080}"
+            empty: "${EMPTY:-none}"
+            nested: "${NOPE:-${HOME}/x}"
+            nil_value: nil
+            from_nil: "${nil_value:-set}""#,
+        )
+        .unwrap();
+        assert_eq!(doc.get_path("port").str(), Some("8080".into()));
+        assert_eq!(doc.get_path("empty").str(), Some("none".into()));
+        assert_eq!(doc.get_path("nested").str(), Some("/home/aeon/x".into()));
+        assert_eq!(doc.get_path("from_nil").str(), Some("set".into()));
+    }
+
+    #[test]
+    pub fn bad_interpolation_is_error() {
+        let bad = [
+            (r#"a: "${b}" b: { c: "${a}" }"#, "Cannot interpolate 'b'"),
+            (
+                r#"a: "${b/c}" b: { c: "${a}" }"#,
+                "Cyclic interpolation: a -> b/c -> a",
+            ),
+            (r#"a: "${a}""#, "Cyclic interpolation: a -> a"),
+            (
+                r#"a: "${NOPE}""#,
+                "Unknown variable 'NOPE' in string at 'a'",
+            ),
+            (r#"a: ["${HOME"]"#, "Unclosed '${' in string at 'a/0'"),
+            (
+                r#"a: "${b}${b}${b}${b}" b: "${c}${c}${c}${c}" c: "${d}${d}${d}${d}"
+                d: "${e}${e}${e}${e}" e: "${f}${f}${f}${f}" f: "${g}${g}${g}${g}"
+                g: "${h}${h}${h}${h}" h: "${i}${i}${i}${i}" i: "${j}${j}${j}${j}"
+                j: "${k}${k}${k}${k}" k: "0123456789""#,
+                "String at 'b' is longer than",
+            ),
+        ];
+        for (code, message) in bad {
+            let err = expand(code).expect_err(code);
+            assert!(err.message.starts_with(message), "{}: {}", code, err);
+        }
+    }
+
+    #[test]
+    pub fn paths_reach_quoted_keys_and_errors_have_spans() {
+        let code = "hosts: { \"a.example\": \"10.0.0.1\" }\nurl: \"http://${hosts/a.example}/\"\nports: [80, 443]\nlast: \"${ports/-1}\"\nbad: [1, \"${NOPE}\"]";
+        let err = interpolate_source(code, &env()).unwrap_err();
+        assert!(err
+            .message
+            .starts_with("Unknown variable 'NOPE' in string at 'bad/1'"));
+        let span = err.span.unwrap();
+        assert_eq!((5, 10), (span.start.line, span.start.column));
+
+        let doc = interpolate_source(&code[..code.rfind('\n').unwrap()], &env()).unwrap();
+        assert_eq!(doc.get_path("url").str(), Some("http://10.0.0.1/".into()));
+        assert_eq!(doc.get_path("last").str(), Some("443".into()));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::document::AeonDocument;
+use crate::value::AeonValue;
+use std::collections::HashMap;
+
+/// How lists are merged when a later layer has a list at the same path as an earlier one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ListMerge {
+    /// The later list replaces the earlier one
+    #[default]
+    Replace,
+    /// The items of the later list are added after those of the earlier one
+    Append,
+    /// Objects in the later list are deep-merged into the object in the earlier list
+    /// with the same value for this key, other items are appended
+    MergeByKey(String),
+}
+
+/// Configuration made of several documents, where later layers override earlier ones, e.g.
+///
+/// ```
+/// use aeon::layers::{Layers, ListMerge};
+/// let defaults = aeon::deserialize("server: { host: \"localhost\", port: 80 }".into()).unwrap();
+/// let user = aeon::deserialize("server: { port: 8080 }".into()).unwrap();
+/// let merged = Layers::new()
+///     .layer("defaults", defaults)
+///     .layer("user", user)
+///     .merge(&ListMerge::Replace);
+/// assert_eq!(Some("defaults"), merged.origin("server/host"));
+/// assert_eq!(Some("user"), merged.origin("server/port"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Layers {
+    layers: Vec<(String, AeonDocument)>,
+}
+
+impl Layers {
+    pub fn new() -> Layers {
+        Layers { layers: Vec::new() }
+    }
+
+    /// Adds a layer on top of the ones added so far, `name` is reported by [`Merged::origin`]
+    pub fn layer(mut self, name: impl Into<String>, doc: AeonDocument) -> Self {
+        self.layers.push((name.into(), doc));
+        self
+    }
+
+    /// Deep-merges the layers in the order they were added
+    pub fn merge(self, lists: &ListMerge) -> Merged {
+        let mut merged = Merged {
+            document: AeonDocument::new(),
+            layers: Vec::with_capacity(self.layers.len()),
+            origins: HashMap::new(),
+        };
+        for (layer, (name, doc)) in self.layers.into_iter().enumerate() {
+            merged.layers.push(name);
+            let mut merger = Merger {
+                lists,
+                layer,
+                origins: &mut merged.origins,
+            };
+            merger.merge_document(&mut merged.document, doc);
+        }
+        merged
+    }
+}
+
+/// The result of [`Layers::merge`]
+#[derive(Debug)]
+pub struct Merged {
+    pub document: AeonDocument,
+    layers: Vec<String>,
+    /// Index of the layer that last set each path
+    origins: HashMap<String, usize>,
+}
+
+impl Merged {
+    /// Name of the layer that supplied the value at `path`, with segments separated by '/'.
+    /// For objects and lists that's the last layer that changed anything inside them.
+    pub fn origin(&self, path: &str) -> Option<&str> {
+        let path = normalize(path);
+        self.origins
+            .get(&path)
+            .map(|&layer| self.layers[layer].as_str())
+    }
+
+    /// Every path of the merged document with the name of the layer that supplied it
+    pub fn origins(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.origins
+            .iter()
+            .map(|(path, &layer)| (path.as_str(), self.layers[layer].as_str()))
+    }
+}
+
+/// Deep-merges `overlay` into `base`, which is the same as merging two [`Layers`]
+pub fn merge(base: &mut AeonDocument, overlay: AeonDocument, lists: &ListMerge) {
+    let mut origins = HashMap::new();
+    Merger {
+        lists,
+        layer: 0,
+        origins: &mut origins,
+    }
+    .merge_document(base, overlay);
+}
+
+fn normalize(path: &str) -> String {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+struct Merger<'a> {
+    lists: &'a ListMerge,
+    layer: usize,
+    origins: &'a mut HashMap<String, usize>,
+}
+
+impl Merger<'_> {
+    fn merge_document(&mut self, base: &mut AeonDocument, overlay: AeonDocument) {
+        for (_, mac) in overlay.macros {
+            base.add_or_replace_macro(mac);
+        }
+        for (name, prop) in overlay.properties {
+            match base.properties.get_mut(&name) {
+                Some(existing) => self.merge_value(&mut existing.value, prop.value, &name),
+                None => {
+                    self.insert_origins(&name, &prop.value);
+                    base.add_or_replace_property(name, prop.value);
+                }
+            }
+        }
+    }
+
+    fn merge_value(&mut self, base: &mut AeonValue, overlay: AeonValue, path: &str) {
+        match (base, overlay) {
+            (
+                base @ (AeonValue::Object(_) | AeonValue::Record(..)),
+                overlay @ (AeonValue::Object(_) | AeonValue::Record(..)),
+            ) => {
+                let (name, overlay) = match overlay {
+                    AeonValue::Record(name, map) => (Some(name), map),
+                    AeonValue::Object(map) => (None, map),
+                    _ => unreachable!(),
+                };
+                let map = match base {
+                    AeonValue::Object(map) | AeonValue::Record(_, map) => map,
+                    _ => unreachable!(),
+                };
+                for (key, value) in overlay {
+                    let child = format!("{}/{}", path, key);
+                    match map.get_mut(&key) {
+                        Some(existing) => self.merge_value(existing, value, &child),
+                        None => {
+                            self.insert_origins(&child, &value);
+                            map.insert(key, value);
+                        }
+                    }
+                }
+                if let Some(name) = name {
+                    *base = AeonValue::Record(name, std::mem::take(map));
+                }
+                self.origins.insert(path.to_string(), self.layer);
+            }
+            (AeonValue::List(items), AeonValue::List(overlay))
+                if *self.lists != ListMerge::Replace =>
+            {
+                for value in overlay {
+                    let existing = match self.lists {
+                        ListMerge::MergeByKey(key) => items.iter().position(|item| {
+                            let id = item_key(item, key);
+                            id.is_some() && id == item_key(&value, key)
+                        }),
+                        _ => None,
+                    };
+                    match existing {
+                        Some(idx) => {
+                            self.merge_value(&mut items[idx], value, &format!("{}/{}", path, idx))
+                        }
+                        None => {
+                            self.insert_origins(&format!("{}/{}", path, items.len()), &value);
+                            items.push(value);
+                        }
+                    }
+                }
+                self.origins.insert(path.to_string(), self.layer);
+            }
+            (base, overlay) => {
+                if matches!(
+                    base,
+                    AeonValue::Object(_) | AeonValue::Record(..) | AeonValue::List(_)
+                ) {
+                    // forget the origins of what's being replaced
+                    let prefix = format!("{}/", path);
+                    self.origins.retain(|p, _| !p.starts_with(&prefix));
+                }
+                self.insert_origins(path, &overlay);
+                *base = overlay;
+            }
+        }
+    }
+
+    /// Records this layer as the origin of `value` at `path` and everything inside it
+    fn insert_origins(&mut self, path: &str, value: &AeonValue) {
+        self.origins.insert(path.to_string(), self.layer);
+        match value {
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                for (key, value) in map.iter() {
+                    self.insert_origins(&format!("{}/{}", path, key), value);
+                }
+            }
+            AeonValue::List(items) => {
+                for (idx, value) in items.iter().enumerate() {
+                    self.insert_origins(&format!("{}/{}", path, idx), value);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn item_key<'a>(item: &'a AeonValue, key: &str) -> Option<&'a AeonValue> {
+    match item {
+        AeonValue::Object(map) | AeonValue::Record(_, map) => map.get(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{AeonConvert, AeonObjectConvert};
+    use crate::layers::{Layers, ListMerge};
+    use crate::value::AeonValue;
+
+    const DEFAULTS: &str = r#"
+        server: { host: "localhost", port: 80, tags: ["a"] }
+        users: [{ name: "root", admin: true }, { name: "guest", admin: false }]
+        debug: false
+    "#;
+    const SYSTEM: &str = r#"
+        server: { port: 8080, tags: ["b"] }
+        users: [{ name: "guest", admin: true }, { name: "new", admin: false }]
+    "#;
+    const OVERRIDES: &str = "debug: true";
+
+    fn merge_layers(lists: ListMerge) -> crate::layers::Merged {
+        let doc = |s: &str| crate::deserialize(s.to_string()).unwrap();
+        Layers::new()
+            .layer("defaults", doc(DEFAULTS))
+            .layer("system", doc(SYSTEM))
+            .layer("overrides", doc(OVERRIDES))
+            .merge(&lists)
+    }
+
+    fn names(value: Option<AeonValue>) -> Vec<String> {
+        value
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|u| u.get("name").str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    pub fn objects_are_deep_merged() {
+        let merged = merge_layers(ListMerge::Replace);
+        let doc = &merged.document;
+        assert_eq!(doc.get_path("server/host").str(), Some("localhost".into()));
+        assert_eq!(doc.get_path("server/port").int(), Some(8080));
+        assert_eq!(doc.get_path("debug").bool(), Some(true));
+        assert_eq!(vec!["guest", "new"], names(doc.get_path("users")));
+    }
+
+    #[test]
+    pub fn lists_are_appended_or_merged_by_key() {
+        let merged = merge_layers(ListMerge::Append);
+        assert_eq!(
+            vec!["root", "guest", "guest", "new"],
+            names(merged.document.get_path("users"))
+        );
+        assert_eq!(
+            2,
+            merged
+                .document
+                .get_path("server/tags")
+                .list()
+                .unwrap()
+                .len()
+        );
+
+        let merged = merge_layers(ListMerge::MergeByKey("name".into()));
+        let users = merged.document.get_path("users").list().unwrap();
+        assert_eq!(3, users.len());
+        assert_eq!(Some(true), users[1].clone().get("admin").bool());
+        assert_eq!(Some("system"), merged.origin("users/1/admin"));
+        assert_eq!(Some("defaults"), merged.origin("users/0/name"));
+        assert_eq!(Some("system"), merged.origin("users/2"));
+    }
+
+    #[test]
+    pub fn origins_name_the_layer_of_each_value() {
+        let merged = merge_layers(ListMerge::Replace);
+        assert_eq!(Some("defaults"), merged.origin("server/host"));
+        assert_eq!(Some("system"), merged.origin("/server/port/"));
+        assert_eq!(Some("system"), merged.origin("server"));
+        assert_eq!(Some("system"), merged.origin("users/0/name"));
+        assert_eq!(Some("overrides"), merged.origin("debug"));
+        // replaced lists don't keep the origins of their old items
+        assert_eq!(None, merged.origin("users/2"));
+        assert_eq!(None, merged.origin("server/nothing"));
+    }
+}
@@ -1,72 +1,92 @@
 use crate::error::AeonDeserializeError;
 use crate::flags;
-use crate::token::Token;
+use crate::span::{Position, Span};
+use crate::token::{SpannedToken, Token};
 use crate::DeserializeResult;
+use std::iter::Peekable;
 use std::str::Chars;
 
 pub struct Lexer<'a> {
-    code: Chars<'a>,
-    prev: Option<char>,
+    code: Peekable<Chars<'a>>,
+    pos: Position,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(code: &'a str) -> Lexer<'a> {
         Lexer {
-            code: code.chars(),
-            prev: None,
+            code: code.chars().peekable(),
+            pos: Position::start(),
         }
     }
 
-    pub fn next(&mut self) -> DeserializeResult<Option<Token>> {
-        match self.prev {
-            Some(p) => {
-                self.prev = None;
-                if p == '#' {
-                    self.skip_comment();
-                    self.perform_full_match()
-                } else if p.is_whitespace() {
-                    self.perform_full_match()
-                } else {
-                    self.perform_match(p)
-                }
-            }
-            None => self.perform_full_match(),
+    /// Position of the next char that will be read
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    pub fn next(&mut self) -> DeserializeResult<Option<SpannedToken>> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        let now = match self.bump() {
+            Some(now) => now,
+            None => return Ok(None),
+        };
+        match self.perform_match(now) {
+            Ok(token) => Ok(Some(SpannedToken {
+                token,
+                span: Span::new(start, self.pos),
+            })),
+            Err(e) => Err(e.with_span(Span::new(start, self.pos))),
         }
     }
 
-    fn skip_comment(&mut self) {
-        for t in self.code.by_ref() {
-            if t == '\n' {
-                break;
-            }
+    fn bump(&mut self) -> Option<char> {
+        let now = self.code.next()?;
+        self.pos.offset += now.len_utf8();
+        if now == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
         }
+        Some(now)
     }
 
-    fn perform_full_match(&mut self) -> DeserializeResult<Option<Token>> {
-        while let Some(now) = self.code.next() {
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&now) = self.code.peek() {
             if now == '#' {
                 self.skip_comment();
-                continue;
-            }
-            if !now.is_whitespace() {
-                return self.perform_match(now);
+            } else if now.is_whitespace() {
+                self.bump();
+            } else {
+                break;
             }
         }
+    }
 
-        Ok(None)
+    fn skip_comment(&mut self) {
+        while let Some(t) = self.bump() {
+            if t == '\n' {
+                break;
+            }
+        }
     }
 
-    fn perform_match(&mut self, now: char) -> DeserializeResult<Option<Token>> {
+    fn perform_match(&mut self, now: char) -> DeserializeResult<Token> {
         match now {
-            '(' => Ok(Some(Token::LeftParenthesis)),
-            ')' => Ok(Some(Token::RightParenthesis)),
-            '[' => Ok(Some(Token::LeftBracket)),
-            ']' => Ok(Some(Token::RightBracket)),
-            '{' => Ok(Some(Token::LeftBrace)),
-            '}' => Ok(Some(Token::RightBrace)),
-            ':' => Ok(Some(Token::Colon)),
-            ',' => Ok(Some(Token::Comma)),
-            '@' => Ok(Some(Token::At)),
+            '(' => Ok(Token::LeftParenthesis),
+            ')' => Ok(Token::RightParenthesis),
+            '[' => Ok(Token::LeftBracket),
+            ']' => Ok(Token::RightBracket),
+            '{' => Ok(Token::LeftBrace),
+            '}' => Ok(Token::RightBrace),
+            ':' => Ok(Token::Colon),
+            ',' => Ok(Token::Comma),
+            '=' => Ok(Token::Equals),
+            '?' => Ok(Token::Question),
+            '@' => Ok(Token::At),
+            '&' => self.get_anchor_name(now).map(Token::Anchor),
+            '*' => self.get_anchor_name(now).map(Token::Alias),
             'a'..='z' | 'A'..='Z' => self.get_identifier(now),
             '"' => self.get_string(),
             '0'..='9' | '-' => self.get_number(now),
@@ -77,44 +97,77 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn get_identifier(&mut self, now: char) -> DeserializeResult<Option<Token>> {
+    fn get_identifier(&mut self, now: char) -> DeserializeResult<Token> {
         let mut t_str = String::with_capacity(10);
         t_str.push(now);
-        for t in self.code.by_ref() {
+        while let Some(&t) = self.code.peek() {
             match t {
                 'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
                     t_str.push(t);
+                    self.bump();
                 }
-                p => {
-                    self.prev = Some(p);
-                    t_str.shrink_to_fit();
-                    break;
+                // a '-' joins two words, so `a-` and `a--b` are not identifiers
+                '-' => {
+                    t_str.push(t);
+                    self.bump();
+                    match self.code.peek() {
+                        Some(c) if c.is_ascii_alphanumeric() || *c == '_' => (),
+                        _ => {
+                            return Err(AeonDeserializeError::lexing(format!(
+                                "Expected a letter, digit or '_' after '-' in identifier '{}'",
+                                t_str
+                            )))
+                        }
+                    }
                 }
+                _ => break,
             }
         }
+        t_str.shrink_to_fit();
 
         match t_str.as_str() {
-            "nil" => Ok(Some(Token::Nil)),
-            "true" => Ok(Some(Token::True)),
-            "false" => Ok(Some(Token::False)),
-            _ => Ok(Some(Token::Identifier(t_str))),
+            "nil" => Ok(Token::Nil),
+            "true" => Ok(Token::True),
+            "false" => Ok(Token::False),
+            _ => Ok(Token::Identifier(t_str)),
+        }
+    }
+
+    /// The name after '&' or '*', which is written like an identifier
+    fn get_anchor_name(&mut self, sigil: char) -> DeserializeResult<String> {
+        match self.code.peek() {
+            Some(&c) if c.is_ascii_alphabetic() => {
+                self.bump();
+                match self.get_identifier(c)? {
+                    Token::Identifier(name) => Ok(name),
+                    _ => Err(AeonDeserializeError::lexing(format!(
+                        "Expected a name after '{}', found a keyword",
+                        sigil
+                    ))),
+                }
+            }
+            _ => Err(AeonDeserializeError::lexing(format!(
+                "Expected a name after '{}'",
+                sigil
+            ))),
         }
     }
 
     const FLAG_HAS_DECIMAL_POINT: u8 = 1;
     const FLAG_HAS_DECIMALS: u8 = 2;
 
-    fn get_number(&mut self, c: char) -> DeserializeResult<Option<Token>> {
+    fn get_number(&mut self, c: char) -> DeserializeResult<Token> {
         let mut t_str = String::with_capacity(10);
         let mut num_flags = 0u8;
         t_str.push(c);
-        for t in self.code.by_ref() {
+        while let Some(&t) = self.code.peek() {
             match t {
                 '0'..='9' | '_' => {
                     if flags::has(num_flags, Self::FLAG_HAS_DECIMAL_POINT) {
                         flags::add(&mut num_flags, Self::FLAG_HAS_DECIMALS);
                     }
                     t_str.push(t);
+                    self.bump();
                 }
                 '.' => {
                     if flags::has(num_flags, Self::FLAG_HAS_DECIMAL_POINT) {
@@ -125,38 +178,81 @@ impl<'a> Lexer<'a> {
                     }
                     flags::add(&mut num_flags, Self::FLAG_HAS_DECIMAL_POINT);
                     t_str.push(t);
+                    self.bump();
                 }
-                p => {
-                    self.prev = Some(p);
-                    break;
-                }
+                _ => break,
             }
         }
 
         t_str.shrink_to_fit();
+        let digits = Self::strip_digit_separators(&t_str)?;
         if flags::has(num_flags, Self::FLAG_HAS_DECIMAL_POINT) {
             if flags::has(num_flags, Self::FLAG_HAS_DECIMALS) {
-                Ok(Some(Token::Double(t_str.parse().unwrap())))
+                match digits.parse() {
+                    Ok(d) => Ok(Token::Double(d)),
+                    Err(_) => Err(AeonDeserializeError::lexing(format!(
+                        "Invalid number: {}",
+                        t_str
+                    ))),
+                }
             } else {
                 Err(AeonDeserializeError::lexing(format!(
                     "Trailing decimal point in number: {}",
                     t_str
                 )))
             }
+        } else if digits == "-" {
+            Err(AeonDeserializeError::lexing(
+                "Expected digits after '-'".to_string(),
+            ))
         } else {
-            Ok(Some(Token::Integer(t_str.parse().unwrap())))
+            match digits.parse() {
+                Ok(i) => Ok(Token::Integer(i)),
+                Err(_) => Err(AeonDeserializeError::lexing(format!(
+                    "Integer out of range: {}",
+                    t_str
+                ))
+                .with_help(format!(
+                    "integers must be between {} and {}",
+                    i64::MIN,
+                    i64::MAX
+                ))),
+            }
+        }
+    }
+
+    /// Removes '_' digit separators, which are only allowed between two digits
+    fn strip_digit_separators(t_str: &str) -> DeserializeResult<String> {
+        let chars: Vec<char> = t_str.chars().collect();
+        let mut digits = String::with_capacity(chars.len());
+        for (idx, &c) in chars.iter().enumerate() {
+            if c != '_' {
+                digits.push(c);
+                continue;
+            }
+            let between_digits = idx > 0
+                && chars[idx - 1].is_ascii_digit()
+                && chars.get(idx + 1).is_some_and(|n| n.is_ascii_digit());
+            if !between_digits {
+                return Err(AeonDeserializeError::lexing(format!(
+                    "Misplaced '_' in number: {}",
+                    t_str
+                ))
+                .with_help("'_' can only be used between two digits, e.g. 1_000"));
+            }
         }
+        Ok(digits)
     }
 
-    fn get_string(&mut self) -> DeserializeResult<Option<Token>> {
+    fn get_string(&mut self) -> DeserializeResult<Token> {
         // TODO: allow 'text' as alternative string syntax as well?
         // TODO: use 0xFFFD for replacement char
         // TODO: write string byte + unicode syntax
         let mut t_str = String::with_capacity(10);
-        while let Some(t) = self.code.next() {
+        while let Some(t) = self.bump() {
             match t {
                 '\\' => {
-                    if let Some(next) = self.code.next() {
+                    if let Some(next) = self.bump() {
                         match next {
                             't' => t_str.push('\t'),
                             'r' => t_str.push('\r'),
@@ -165,7 +261,7 @@ impl<'a> Lexer<'a> {
                             '"' => t_str.push('"'),
                             'x' => {}
                             'u' => {
-                                let next = self.code.next();
+                                let next = self.bump();
                                 if !matches!(next, Some('{')) {
                                     return Err(AeonDeserializeError::lexing(format!(
                                         "Expected '{{' after '\\u' in string: {}{:?}",
@@ -176,7 +272,7 @@ impl<'a> Lexer<'a> {
                                 let mut uc: [char; 4] = ['0'; 4];
                                 let mut dig: u32 = 0;
 
-                                for next in self.code.by_ref() {
+                                while let Some(next) = self.bump() {
                                     if next == '}' {
                                         break;
                                     }
@@ -195,6 +291,12 @@ impl<'a> Lexer<'a> {
                                     uc[dig as usize] = next;
                                     dig += 1;
                                 }
+                                if dig != 4 {
+                                    return Err(AeonDeserializeError::lexing(format!(
+                                        "Expected exactly 4 hex digits in '\\u' in string: {}",
+                                        t_str
+                                    )));
+                                }
                                 let uc: String = uc.iter().collect();
                                 dig = match u32::from_str_radix(&uc, 16) {
                                     Ok(dig) => dig,
@@ -218,21 +320,22 @@ impl<'a> Lexer<'a> {
                             }
                             _ => {
                                 return Err(AeonDeserializeError::lexing(format!(
-                                    "Unescaped string: {}{:?}",
-                                    t_str, next
-                                )))
+                                    "Unknown escape sequence '\\{}' in string",
+                                    next
+                                ))
+                                .with_help("valid escape sequences are \\t, \\r, \\n, \\\\, \\\" and \\u{XXXX}"))
                             }
                         }
                     } else {
-                        return Err(AeonDeserializeError::lexing(format!(
-                            "Unescaped string: {}",
-                            t_str
-                        )));
+                        return Err(AeonDeserializeError::lexing(
+                            "Unterminated string".to_string(),
+                        )
+                        .with_help("add a '\"' to close the string"));
                     }
                 }
                 '"' => {
                     t_str.shrink_to_fit();
-                    return Ok(Some(Token::String(t_str)));
+                    return Ok(Token::String(t_str));
                 }
                 _ => {
                     t_str.push(t);
@@ -240,23 +343,24 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        Err(AeonDeserializeError::lexing(format!(
-            "Unescaped string: {}",
-            t_str
-        )))
+        Err(
+            AeonDeserializeError::lexing("Unterminated string".to_string())
+                .with_help("add a '\"' to close the string"),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::lexer::Lexer;
+    use crate::span::Position;
     use crate::token::Token;
 
     #[test]
     pub fn multiple_tokens() {
         let s = r#"hello:"world"world:1236"#.to_string();
         let mut lex = Lexer::new(&s);
-        macro_rules! get_current( () => { lex.next().unwrap().unwrap() } );
+        macro_rules! get_current( () => { lex.next().unwrap().unwrap().token } );
         let mut current = get_current!();
 
         // assert
@@ -299,7 +403,7 @@ mod tests {
 
     #[test]
     pub fn string_token_with_escaped_unicode_or_hex() {
-        let result = Lexer::new(r#""\u{2714}""#).next().unwrap().unwrap();
+        let result = Lexer::new(r#""\u{2714}""#).next().unwrap().unwrap().token;
         //eprintln!("{:#?}", result);
         assert!(
             matches!(
@@ -314,7 +418,7 @@ mod tests {
     pub fn integer_token() {
         assert!(
             matches!(
-                Lexer::new("150").next().unwrap().unwrap(),
+                Lexer::new("150").next().unwrap().unwrap().token,
                 Token::Integer(150)
             ),
             "expected integer token"
@@ -324,14 +428,14 @@ mod tests {
     #[test]
     pub fn nil_token() {
         assert!(
-            matches!(Lexer::new("nil").next().unwrap().unwrap(), Token::Nil),
+            matches!(Lexer::new("nil").next().unwrap().unwrap().token, Token::Nil),
             "expected nil token"
         );
     }
 
     #[test]
     pub fn string_token() {
-        let t_str = Lexer::new("\"hello world\"").next().unwrap().unwrap();
+        let t_str = Lexer::new("\"hello world\"").next().unwrap().unwrap().token;
         match t_str {
             Token::String(s) => {
                 assert_eq!(s, "hello world", "Expected string token");
@@ -342,7 +446,7 @@ mod tests {
 
     #[test]
     pub fn identifier_token() {
-        let t_str = Lexer::new("WORLD01_HELLo").next().unwrap().unwrap();
+        let t_str = Lexer::new("WORLD01_HELLo").next().unwrap().unwrap().token;
         match t_str {
             Token::Identifier(s) => {
                 assert_eq!(s, "WORLD01_HELLo", "Expected identifier token");
@@ -351,9 +455,27 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn identifier_token_with_dashes() {
+        let mut lexer = Lexer::new("max-conns: -1");
+        match lexer.next().unwrap().unwrap().token {
+            Token::Identifier(s) => assert_eq!(s, "max-conns"),
+            t => panic!("Expected identifier token, found {:?}", t),
+        }
+        lexer.next().unwrap().unwrap();
+        match lexer.next().unwrap().unwrap().token {
+            Token::Integer(i) => assert_eq!(i, -1),
+            t => panic!("Expected integer token, found {:?}", t),
+        }
+        for bad in ["a-: 1", "a--b: 1", "a-_b-", "a- b"] {
+            let err = Lexer::new(bad).next().unwrap_err();
+            assert!(err.message.contains("after '-' in identifier"), "{}", bad);
+        }
+    }
+
     #[test]
     pub fn double_token() {
-        let double = Lexer::new("19.13").next().unwrap().unwrap();
+        let double = Lexer::new("19.13").next().unwrap().unwrap().token;
         match double {
             Token::Double(d) => {
                 assert_eq!(d, 19.13, "Expected double token");
@@ -361,4 +483,81 @@ mod tests {
             _ => panic!("Expected double token"),
         }
     }
+
+    #[test]
+    pub fn token_spans_track_offset_line_and_column() {
+        let mut lex = Lexer::new("# comment\nname: \"å\"\n  num: 12");
+        let name = lex.next().unwrap().unwrap();
+        assert_eq!(
+            Position {
+                offset: 10,
+                line: 2,
+                column: 1
+            },
+            name.span.start
+        );
+        assert_eq!(14, name.span.end.offset);
+        let _colon = lex.next().unwrap().unwrap();
+        let string = lex.next().unwrap().unwrap();
+        assert_eq!((2, 7), (string.span.start.line, string.span.start.column));
+        // 'å' is two bytes but a single column
+        assert_eq!(4, string.span.len());
+        assert_eq!(10, string.span.end.column);
+        let num = lex.next().unwrap().unwrap();
+        assert_eq!((3, 3), (num.span.start.line, num.span.start.column));
+        let _colon = lex.next().unwrap().unwrap();
+        let int = lex.next().unwrap().unwrap();
+        assert_eq!((3, 8), (int.span.start.line, int.span.start.column));
+        assert!(lex.next().unwrap().is_none());
+    }
+
+    #[test]
+    pub fn lexing_error_has_span() {
+        let mut lex = Lexer::new("ok: \n  $");
+        lex.next().unwrap();
+        lex.next().unwrap();
+        let err = lex.next().unwrap_err();
+        let span = err.span.expect("expected span on lexing error");
+        assert_eq!((2, 3), (span.start.line, span.start.column));
+        assert_eq!(7, span.start.offset);
+    }
+
+    #[test]
+    pub fn integer_token_with_digit_separators() {
+        assert!(
+            matches!(
+                Lexer::new("-1_000_000").next().unwrap().unwrap().token,
+                Token::Integer(-1_000_000)
+            ),
+            "expected integer token"
+        );
+    }
+
+    #[test]
+    pub fn malformed_numbers_are_errors() {
+        for input in [
+            "-",
+            "- 1",
+            "1_",
+            "1__0",
+            "1_.5",
+            "1.",
+            "1.2.3",
+            "9223372036854775808",
+            "-9223372036854775809",
+        ] {
+            assert!(
+                Lexer::new(input).next().is_err(),
+                "expected error for {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    pub fn unicode_escape_requires_four_hex_digits() {
+        assert!(Lexer::new(r#""\u{41}""#).next().is_err());
+        assert!(Lexer::new(r#""\u{0041""#).next().is_err());
+        assert!(Lexer::new(r#""\u{00g1}""#).next().is_err());
+    }
 }
@@ -1,19 +1,33 @@
 use crate::document::AeonDocument;
-use crate::error::{AeonDeserializeError, AeonSerializeError};
+use crate::error::{AeonDeserializeError, AeonPathError, AeonSerializeError};
 use crate::serializer::AeonFormatter;
+pub use crate::serializer::SerializeOptions;
 pub mod convert;
+pub mod cst;
 mod deserializer;
+pub mod diagnostic;
+pub mod diff;
 pub mod document;
 pub mod error;
 mod flags;
+pub mod include;
+pub mod interpolate;
+pub mod layers;
 mod lexer;
 mod macros;
+pub mod ordered_map;
+pub mod overrides;
+mod path;
+pub mod query;
+pub mod schema;
 mod serializer;
+pub mod span;
 mod token;
 pub mod value;
 
 pub type DeserializeResult<T> = Result<T, AeonDeserializeError>;
 pub type SerializeResult<T> = Result<T, AeonSerializeError>;
+pub type PathResult<T> = Result<T, AeonPathError>;
 
 pub fn serialize(aeon: &AeonDocument) -> SerializeResult<String> {
     if aeon.is_empty {
@@ -23,11 +37,32 @@ pub fn serialize(aeon: &AeonDocument) -> SerializeResult<String> {
     Ok(serializer::PrettySerializer::serialize_aeon(aeon))
 }
 
+pub fn serialize_with_options(
+    aeon: &AeonDocument,
+    options: SerializeOptions,
+) -> SerializeResult<String> {
+    if aeon.is_empty {
+        return Ok(String::new());
+    }
+    Ok(serializer::PrettySerializer::serialize_aeon_with_options(
+        aeon, options,
+    ))
+}
+
 pub fn deserialize(s: String) -> DeserializeResult<AeonDocument> {
     let mut deserializer = deserializer::Deserializer::new(&s);
     deserializer.deserialize()
 }
 
+/// Deserializes `s`, using `resolver` to find the documents pulled in by `@include("path")`
+pub fn deserialize_with_resolver(
+    s: String,
+    resolver: &dyn include::IncludeResolver,
+) -> DeserializeResult<AeonDocument> {
+    let mut deserializer = deserializer::Deserializer::with_resolver(&s, resolver, Vec::new());
+    deserializer.deserialize()
+}
+
 pub trait AeonDeserialize
 where
     Self: Sized,
@@ -39,7 +74,7 @@ pub trait AeonSerialize {
     fn to_aeon(&self) -> SerializeResult<String>;
     // TODO: rebuild this to not perform a bunch of unnecessary steps
     fn to_aeon_value(&self) -> SerializeResult<value::AeonValue>;
-    fn create_macros(insert_self: bool) -> std::collections::HashMap<String, document::AeonMacro>;
+    fn create_macros(insert_self: bool) -> ordered_map::OrderedMap<String, document::AeonMacro>;
 }
 
 pub trait AeonDeserializeProperty
@@ -55,5 +90,18 @@ pub trait AeonSerializeProperty {
     //fn serialize_property_or_nil(&self) -> value::AeonValue;
     fn create_property_macros(
         insert_self: bool,
-    ) -> std::collections::HashMap<String, document::AeonMacro>;
+    ) -> ordered_map::OrderedMap<String, document::AeonMacro>;
+    /// Like `create_property_macros`, but fails if two types create macros with the same name
+    /// and different arguments, see [`document::merge_macros`]
+    fn try_create_property_macros(
+        insert_self: bool,
+    ) -> SerializeResult<ordered_map::OrderedMap<String, document::AeonMacro>> {
+        Ok(Self::create_property_macros(insert_self))
+    }
+}
+
+/// Types that can describe the values they are deserialized from,
+/// as a schema node in the format read by [`schema::Schema`]
+pub trait AeonSchema {
+    fn property_schema() -> value::AeonValue;
 }
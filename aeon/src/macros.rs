@@ -1,12 +1,17 @@
+/// Creates a map from `key => value` pairs, in the given order when the map is
+/// an [`OrderedMap`](crate::ordered_map::OrderedMap).
+///
+/// Works for any map implementing `FromIterator<(K, V)>`, the type is inferred from the context.
 #[macro_export]
 macro_rules! map(
     ( $( $k:expr => $v:expr ),+ $(,)? ) => ( // $(,)? is to always allow trailing commas
         {
-            let mut map = std::collections::HashMap::new();
-            $(
-                map.insert($k, $v);
-            )+
-            map
+            std::iter::IntoIterator::into_iter([
+                $(
+                    ($k, $v),
+                )+
+            ])
+            .collect()
         }
     );
 );
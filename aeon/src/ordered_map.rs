@@ -0,0 +1,377 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::ops::Index;
+
+/// A map that remembers the order its keys were first inserted in.
+///
+/// Replacing the value of an existing key keeps its position, removing a key
+/// shifts the entries after it.
+#[derive(Clone)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+    indices: HashMap<K, usize>,
+}
+
+impl<K, V> OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new() -> OrderedMap<K, V> {
+        OrderedMap {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> OrderedMap<K, V> {
+        OrderedMap {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Inserts `value` at the end of the map, or replaces the value in place if
+    /// `key` already exists, returning the old value.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(&idx) = self.indices.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[idx].1, value));
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.get(key).map(|&idx| &self.entries[idx].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.indices.get(key) {
+            Some(&idx) => Some(&mut self.entries[idx].1),
+            None => None,
+        }
+    }
+
+    /// Gets the value of `key`, inserting the result of `default` at the end of the map first if it doesn't exist.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        let idx = match self.indices.get(&key) {
+            Some(&idx) => idx,
+            None => {
+                self.insert(key, default());
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[idx].1
+    }
+
+    pub fn get_index(&self, idx: usize) -> Option<(&K, &V)> {
+        self.entries.get(idx).map(|(k, v)| (k, v))
+    }
+
+    pub fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.get(key).copied()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.contains_key(key)
+    }
+
+    /// Removes `key`, keeping the order of the remaining entries.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(idx);
+        for (k, _) in &self.entries[idx..] {
+            if let Some(i) = self.indices.get_mut::<K>(k) {
+                *i -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut keep: F) {
+        self.entries.retain_mut(|(k, v)| keep(k, v));
+        self.reindex();
+    }
+
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+    {
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.reindex();
+    }
+
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&K, &V, &K, &V) -> std::cmp::Ordering,
+    {
+        self.entries.sort_by(|a, b| compare(&a.0, &a.1, &b.0, &b.1));
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.indices.clear();
+        for (idx, (k, _)) in self.entries.iter().enumerate() {
+            self.indices.insert(k.clone(), idx);
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+
+    pub fn into_values(self) -> impl DoubleEndedIterator<Item = V> + ExactSizeIterator {
+        self.entries.into_iter().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> Default for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+/// Maps are equal if they contain the same entries, regardless of order
+impl<K, V> PartialEq for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(k, v)| other.get(k).is_some_and(|o| o == v))
+    }
+}
+
+impl<K, Q, V> Index<&Q> for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in OrderedMap")
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = OrderedMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V> Extend<(K, V)> for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V> From<HashMap<K, V>> for OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone + Ord,
+{
+    /// `HashMap` has no order of its own, so the keys are sorted to get a stable one
+    fn from(map: HashMap<K, V>) -> Self {
+        let mut ordered: OrderedMap<K, V> = map.into_iter().collect();
+        ordered.sort_keys();
+        ordered
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    inner: std::slice::Iter<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K, V> {
+    inner: std::slice::IterMut<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut OrderedMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ordered_map::OrderedMap;
+
+    fn keys(map: &OrderedMap<String, i32>) -> Vec<&str> {
+        map.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    pub fn keeps_insertion_order() {
+        let mut map = OrderedMap::new();
+        for (idx, k) in ["zebra", "apple", "mango", "kiwi"].iter().enumerate() {
+            map.insert(k.to_string(), idx as i32);
+        }
+        assert_eq!(vec!["zebra", "apple", "mango", "kiwi"], keys(&map));
+        assert_eq!(Some(1), map.insert("apple".into(), 10));
+        assert_eq!(vec!["zebra", "apple", "mango", "kiwi"], keys(&map));
+        assert_eq!(10, map["apple"]);
+    }
+
+    #[test]
+    pub fn remove_shifts_remaining_entries() {
+        let mut map: OrderedMap<String, i32> =
+            vec![("a".to_string(), 1), ("b".into(), 2), ("c".into(), 3)]
+                .into_iter()
+                .collect();
+        assert_eq!(Some(1), map.remove("a"));
+        assert_eq!(None, map.remove("a"));
+        assert_eq!(vec!["b", "c"], keys(&map));
+        assert_eq!(Some(&3), map.get("c"));
+        assert_eq!(Some(1), map.index_of("c"));
+        map.insert("a".into(), 4);
+        assert_eq!(vec!["b", "c", "a"], keys(&map));
+    }
+
+    #[test]
+    pub fn sort_keys_and_order_insensitive_equality() {
+        let mut map: OrderedMap<String, i32> = vec![("b".to_string(), 1), ("a".into(), 2)]
+            .into_iter()
+            .collect();
+        let unsorted = map.clone();
+        map.sort_keys();
+        assert_eq!(vec!["a", "b"], keys(&map));
+        assert_eq!(Some(&2), map.get("a"));
+        assert_eq!(unsorted, map);
+    }
+}
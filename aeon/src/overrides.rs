@@ -0,0 +1,225 @@
+use crate::convert::AeonObjectConvert;
+use crate::deserializer::Deserializer;
+use crate::document::AeonDocument;
+use crate::error::{AeonDeserializeError, AeonPathError};
+use crate::value::AeonValue;
+use crate::DeserializeResult;
+
+/// Separates the path segments of environment variable overrides, as in `APP__SERVER__PORT`
+const ENV_SEPARATOR: &str = "__";
+
+/// Applies an override written as `path=value`, e.g. `servers/0/port=9090`.
+///
+/// See [`set`] for how the path and value are read.
+pub fn apply_set(doc: &mut AeonDocument, assignment: &str) -> DeserializeResult<()> {
+    match assignment.split_once('=') {
+        Some((path, value)) => set(doc, path, value),
+        None => Err(AeonDeserializeError::deserialization(format!(
+            "Override '{}' has no '='",
+            assignment
+        ))
+        .with_help("write overrides as 'path=value', e.g. 'server/port=9090'")),
+    }
+}
+
+/// Sets the value at `path`, with segments separated by '/', to `value` parsed as Aeon.
+///
+/// Values may be lists, maps and calls to the macros of `doc`. A single bare word that isn't valid
+/// Aeon is used as a string, so `host=example.com` doesn't need quotes. Anything else that isn't
+/// valid Aeon is an error, including words starting with a digit, '-' or '.' such as `80a`.
+///
+/// Missing properties and map keys are created, as are objects for the segments leading to them,
+/// as with [`AeonObjectConvert::set_path`].
+pub fn set(doc: &mut AeonDocument, path: &str, value: &str) -> DeserializeResult<()> {
+    let value = parse_value(doc, path, value)?;
+    doc.set_path(path, value).map(|_| ()).map_err(path_error)
+}
+
+/// Applies every variable of `vars` named `{prefix}__{SEGMENT}__{SEGMENT}...`,
+/// e.g. `APP__SERVERS__0__PORT=9090` with the prefix `APP`.
+///
+/// Segments match existing keys regardless of case, and missing keys are created in lowercase.
+/// Pass [`std::env::vars`] to use the environment of the current process.
+/// Variables are applied in order of their names, so `APP__A` is set before `APP__A__B`.
+pub fn apply_env<I, K, V>(doc: &mut AeonDocument, prefix: &str, vars: I) -> DeserializeResult<()>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let prefix = format!("{}{}", prefix, ENV_SEPARATOR);
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let path = name.as_ref().strip_prefix(&prefix)?;
+            Some((path.to_string(), value.as_ref().to_string()))
+        })
+        .collect();
+    overrides.sort();
+    for (name, value) in overrides {
+        let path = env_path(doc, &name);
+        let value = parse_value(doc, &path, &value)?;
+        doc.set_path(&path, value).map_err(|e| {
+            path_error(e).with_help(format!(
+                "set by the environment variable {}{}",
+                prefix, name
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+fn parse_value(doc: &mut AeonDocument, path: &str, value: &str) -> DeserializeResult<AeonValue> {
+    match Deserializer::new(value).deserialize_value(doc) {
+        Ok(value) => Ok(value),
+        Err(_) if is_bare_word(value) => Ok(AeonValue::String(value.to_string())),
+        Err(mut e) => {
+            e.message = format!("Invalid value for '{}': {}", path, e.message);
+            Err(e)
+        }
+    }
+}
+
+/// Whether `value` is a single word that is meant as a string, rather than a mistyped number,
+/// list, map, string or macro call
+fn is_bare_word(value: &str) -> bool {
+    let value = value.trim();
+    !value.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+        && !value.contains(|c: char| c.is_whitespace() || "[]{}()\",".contains(c))
+}
+
+fn path_error(e: AeonPathError) -> AeonDeserializeError {
+    AeonDeserializeError::deserialization(format!("Cannot set '{}': {}", e.path, e.message))
+}
+
+/// The path that the segments of the environment variable `name` refer to,
+/// using the existing keys that match them regardless of case
+fn env_path(doc: &AeonDocument, name: &str) -> String {
+    let mut path: Vec<String> = Vec::new();
+    let mut current: Option<&AeonValue> = None;
+    for segment in name.split(ENV_SEPARATOR) {
+        let found = match current {
+            _ if path.is_empty() => doc
+                .properties
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(segment))
+                .map(|(k, p)| (k.as_str(), &p.value)),
+            Some(AeonValue::Object(map) | AeonValue::Record(_, map)) => map
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(segment))
+                .map(|(k, v)| (k.as_str(), v)),
+            Some(AeonValue::List(items)) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get(i))
+                .map(|v| (segment, v)),
+            _ => None,
+        };
+        current = found.map(|(_, v)| v);
+        path.push(match found {
+            Some((key, _)) => key.to_string(),
+            None => segment.to_lowercase(),
+        });
+    }
+    path.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::{AeonConvert, AeonObjectConvert};
+    use crate::overrides::{apply_env, apply_set};
+    use crate::value::AeonValue;
+
+    const CONFIG: &str = r#"
+        @server(host, port)
+        servers: [server("a", 80), server("b", 81)]
+        logLevel: "info"
+    "#;
+
+    fn config() -> crate::document::AeonDocument {
+        crate::deserialize(CONFIG.to_string()).unwrap()
+    }
+
+    fn port(doc: &crate::document::AeonDocument, idx: usize) -> Option<i64> {
+        doc.get("servers").list().unwrap()[idx]
+            .clone()
+            .get("port")
+            .int()
+    }
+
+    #[test]
+    pub fn set_parses_values_and_creates_objects() {
+        let mut doc = config();
+        apply_set(&mut doc, "servers/0/port=9090").unwrap();
+        apply_set(&mut doc, "servers/-=server(\"c\", 82)").unwrap();
+        apply_set(&mut doc, "tls/cert/path=/etc/cert.pem").unwrap();
+        apply_set(&mut doc, "tls/ciphers=[\"a\", \"b\"]").unwrap();
+        apply_set(&mut doc, "name=").unwrap();
+        assert_eq!(Some(9090), port(&doc, 0));
+        assert_eq!(Some(82), port(&doc, 2));
+        assert_eq!(
+            doc.get_path("tls/cert/path").str(),
+            Some("/etc/cert.pem".into())
+        );
+        assert_eq!(2, doc.get_path("tls/ciphers").list().unwrap().len());
+        assert_eq!(doc.get_path("name").str(), Some(String::new()));
+    }
+
+    #[test]
+    pub fn env_overrides_match_keys_regardless_of_case() {
+        let mut doc = config();
+        let vars = [
+            ("APP__SERVERS__1__PORT", "9091"),
+            ("APP__LOGLEVEL", "debug"),
+            ("APP__CACHE__ENABLED", "true"),
+            ("OTHER__LOGLEVEL", "error"),
+        ];
+        apply_env(&mut doc, "APP", vars).unwrap();
+        assert_eq!(Some(9091), port(&doc, 1));
+        assert_eq!(doc.get_path("logLevel").str(), Some("debug".into()));
+        assert_eq!(doc.get_path("cache/enabled"), Some(AeonValue::Bool(true)));
+    }
+
+    #[test]
+    pub fn bad_overrides_are_errors() {
+        let bad = [
+            (
+                "servers/5/port=1",
+                "Cannot set 'servers/5/port': Index 5 is out of range",
+            ),
+            (
+                "logLevel/x=1",
+                "Cannot set 'logLevel/x': Cannot index into a value of type 'string'",
+            ),
+            (
+                "servers/a=1",
+                "Cannot set 'servers/a': 'a' is not a list index",
+            ),
+            ("servers=[1, 2", "Invalid value for 'servers':"),
+            ("servers=nope(1)", "Invalid value for 'servers':"),
+            ("servers/0/port=80a", "Invalid value for 'servers/0/port':"),
+            ("servers/0/port=9O90", "Invalid value for 'servers/0/port':"),
+            ("timeout=-5s", "Invalid value for 'timeout':"),
+            ("ratio=.5", "Invalid value for 'ratio':"),
+            ("logLevel=very verbose", "Invalid value for 'logLevel':"),
+            ("servers", "Override 'servers' has no '='"),
+            ("=1", "Cannot set '': Path is empty"),
+        ];
+        for (assignment, message) in bad {
+            let err = apply_set(&mut config(), assignment).expect_err(assignment);
+            assert!(err.message.starts_with(message), "{}: {}", assignment, err);
+        }
+        let err = apply_env(&mut config(), "APP", [("APP__SERVERS__0__PORT", "80a")]).unwrap_err();
+        assert!(
+            err.message
+                .starts_with("Invalid value for 'servers/0/port':"),
+            "{}",
+            err
+        );
+        let err = apply_env(&mut config(), "APP", [("APP__LOGLEVEL__X", "1")]).unwrap_err();
+        assert_eq!(
+            err.help.as_deref(),
+            Some("set by the environment variable APP__LOGLEVEL__X")
+        );
+    }
+}
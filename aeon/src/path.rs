@@ -0,0 +1,97 @@
+use crate::error::{AeonPathError, AeonPathErrorCode};
+use crate::value::AeonValue;
+use crate::PathResult;
+use std::fmt::Display;
+
+/// The segments of a path separated by '/', ignoring empty segments
+pub(crate) fn segments(path: &str) -> Vec<&str> {
+    path.split('/').filter(|f| !f.is_empty()).collect()
+}
+
+/// The path of `key` inside the value at `path`, where an empty path is the document itself
+pub(crate) fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}/{}", path, key)
+    }
+}
+
+pub(crate) fn parse_index(segment: &str, path: &str, idx: usize) -> PathResult<usize> {
+    segment.parse().map_err(|_| {
+        AeonPathError::new(
+            AeonPathErrorCode::NotFound,
+            format!("'{}' is not a list index", segment),
+            path,
+            idx,
+        )
+    })
+}
+
+/// The item of a list of `len` items that `segment` refers to,
+/// where negative indexes count from the end of the list
+pub(crate) fn list_index(segment: &str, len: usize, path: &str, idx: usize) -> PathResult<usize> {
+    let index = match segment.strip_prefix('-') {
+        Some(from_end) => match parse_index(from_end, path, idx)? {
+            0 => return Err(out_of_range(segment, len, path, idx)),
+            n => len.checked_sub(n),
+        },
+        None => Some(parse_index(segment, path, idx)?),
+    };
+    index
+        .filter(|&i| i < len)
+        .ok_or_else(|| out_of_range(segment, len, path, idx))
+}
+
+/// Whether `segment` refers to a new item after the end of a list of `len` items
+pub(crate) fn appends(segment: &str, len: usize) -> bool {
+    segment == "-" || segment.parse::<usize>() == Ok(len)
+}
+
+pub(crate) fn empty_path(path: &str) -> AeonPathError {
+    AeonPathError::new(
+        AeonPathErrorCode::NotFound,
+        "Path is empty".to_string(),
+        path,
+        0,
+    )
+}
+
+pub(crate) fn not_found(segment: &str, path: &str, idx: usize) -> AeonPathError {
+    AeonPathError::new(
+        AeonPathErrorCode::NotFound,
+        format!("'{}' does not exist", segment),
+        path,
+        idx,
+    )
+}
+
+pub(crate) fn out_of_range(
+    index: impl Display,
+    len: usize,
+    path: &str,
+    idx: usize,
+) -> AeonPathError {
+    AeonPathError::new(
+        AeonPathErrorCode::IndexOutOfRange,
+        format!(
+            "Index {} is out of range for a list of {} items",
+            index, len
+        ),
+        path,
+        idx,
+    )
+}
+
+/// Error for indexing into `value`, which is at segment `idx` of `path`
+pub(crate) fn not_a_container(value: &AeonValue, path: &str, idx: usize) -> AeonPathError {
+    AeonPathError::new(
+        AeonPathErrorCode::NotAContainer,
+        format!(
+            "Cannot index into a value of type '{}'",
+            AeonValue::tag_to_str(value.tag())
+        ),
+        path,
+        idx,
+    )
+}
@@ -0,0 +1,512 @@
+use crate::deserializer::Deserializer;
+use crate::document::AeonDocument;
+use crate::error::{AeonPathError, AeonPathErrorCode};
+use crate::path::{empty_path, join};
+use crate::value::AeonValue;
+use crate::PathResult;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// A path that can match many values, e.g. `servers/*/port`.
+///
+/// Segments are separated by '/' like in [`crate::convert::AeonObjectConvert::get_path`],
+/// and may also be:
+/// - `*`, every value of a map or list
+/// - `**`, the current value and every value nested in it, at any depth
+/// - `[start:end]`, the values of a list or map from `start` up to `end`, where either can be
+///   left out and negative indexes count from the end, or `[index]` for a single value
+/// - `[field op value]`, the values of a list or map with a `field` for which the comparison holds,
+///   where `op` is one of `==`, `!=`, `<`, `<=`, `>` and `>=` and `value` is written in Aeon,
+///   or `[field]` for the values that have the field
+///
+/// Brackets may follow a name, as in `servers[port > 8000]/name`, and further brackets narrow
+/// down the values selected by the first, so `servers[port > 8000][0]` is the first such server.
+#[derive(Clone, Debug)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+/// A value matched by a [`Query`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryMatch<'a> {
+    /// The concrete path of the value, with list indexes instead of wildcards
+    pub path: String,
+    pub value: &'a AeonValue,
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+    Key(String),
+    Wildcard,
+    Descendants,
+    /// The values selected by the brackets of a segment
+    Select(Vec<Selector>),
+}
+
+#[derive(Clone, Debug)]
+enum Selector {
+    Slice(Option<i64>, Option<i64>),
+    Filter(Predicate),
+}
+
+#[derive(Clone, Debug)]
+struct Predicate {
+    field: Vec<String>,
+    comparison: Option<(Comparison, AeonValue)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Operators in the order they're looked for, so that `<=` isn't taken for `<`
+const OPERATORS: [(&str, Comparison); 6] = [
+    ("==", Comparison::Eq),
+    ("!=", Comparison::Ne),
+    ("<=", Comparison::Le),
+    (">=", Comparison::Ge),
+    ("<", Comparison::Lt),
+    (">", Comparison::Gt),
+];
+
+impl Query {
+    pub fn parse(query: &str) -> PathResult<Query> {
+        let mut steps = Vec::new();
+        for (idx, segment) in split_segments(query)
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .enumerate()
+        {
+            let invalid = |message: String| {
+                AeonPathError::new(AeonPathErrorCode::InvalidQuery, message, query, idx)
+            };
+            let (name, mut brackets) = match segment.find('[') {
+                Some(start) => segment.split_at(start),
+                None => (segment, ""),
+            };
+            match name {
+                "" => (),
+                "*" => steps.push(Step::Wildcard),
+                "**" => steps.push(Step::Descendants),
+                _ => steps.push(Step::Key(name.to_string())),
+            }
+            let mut selectors = Vec::new();
+            while !brackets.is_empty() {
+                let end = match closing_bracket(brackets) {
+                    Some(end) => end,
+                    None => return Err(invalid(format!("Unclosed '[' in '{}'", segment))),
+                };
+                selectors.push(parse_brackets(brackets[1..end].trim()).map_err(invalid)?);
+                brackets = &brackets[end + 1..];
+                if !brackets.is_empty() && !brackets.starts_with('[') {
+                    return Err(invalid(format!(
+                        "Expected '[' or '/' after ']', found '{}'",
+                        brackets
+                    )));
+                }
+            }
+            if !selectors.is_empty() {
+                steps.push(Step::Select(selectors));
+            }
+        }
+        if steps.is_empty() {
+            return Err(empty_path(query));
+        }
+        Ok(Query { steps })
+    }
+
+    /// The values of `doc` matched by this query
+    pub fn find_in<'a>(&self, doc: &'a AeonDocument) -> Vec<QueryMatch<'a>> {
+        self.run(Node::Document(doc))
+    }
+
+    /// The values inside `value` matched by this query, with paths relative to `value`
+    pub fn find<'a>(&self, value: &'a AeonValue) -> Vec<QueryMatch<'a>> {
+        self.run(Node::Value(value))
+    }
+
+    fn run<'a>(&self, root: Node<'a>) -> Vec<QueryMatch<'a>> {
+        let mut current = vec![(String::new(), root)];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for (path, node) in current {
+                step.apply(&path, node, &mut next);
+            }
+            if let Step::Descendants = step {
+                // `**` reached through another `**` would otherwise be matched twice
+                let mut seen = HashSet::new();
+                next.retain(|(path, _)| seen.insert(path.clone()));
+            }
+            current = next;
+        }
+        current
+            .into_iter()
+            .filter_map(|(path, node)| match node {
+                Node::Value(value) => Some(QueryMatch { path, value }),
+                Node::Document(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Node<'a> {
+    Document(&'a AeonDocument),
+    Value(&'a AeonValue),
+}
+
+impl<'a> Node<'a> {
+    fn children(self) -> Vec<(String, &'a AeonValue)> {
+        match self {
+            Node::Document(doc) => doc
+                .properties
+                .iter()
+                .map(|(k, p)| (k.clone(), &p.value))
+                .collect(),
+            Node::Value(AeonValue::Object(map) | AeonValue::Record(_, map)) => {
+                map.iter().map(|(k, v)| (k.clone(), v)).collect()
+            }
+            Node::Value(AeonValue::List(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            Node::Value(_) => Vec::new(),
+        }
+    }
+
+    fn child(self, key: &str) -> Option<(String, &'a AeonValue)> {
+        match self {
+            Node::Document(doc) => doc.properties.get(key).map(|p| (key.to_string(), &p.value)),
+            Node::Value(AeonValue::Object(map) | AeonValue::Record(_, map)) => {
+                map.get(key).map(|v| (key.to_string(), v))
+            }
+            Node::Value(AeonValue::List(items)) => {
+                let idx = key.parse::<i64>().ok()?;
+                let idx = if idx < 0 {
+                    items.len().checked_sub(idx.unsigned_abs() as usize)?
+                } else {
+                    idx as usize
+                };
+                items.get(idx).map(|v| (idx.to_string(), v))
+            }
+            Node::Value(_) => None,
+        }
+    }
+}
+
+impl Step {
+    fn apply<'a>(&self, path: &str, node: Node<'a>, out: &mut Vec<(String, Node<'a>)>) {
+        let mut push = |key: String, value| out.push((join(path, &key), Node::Value(value)));
+        match self {
+            Step::Key(key) => {
+                if let Some((key, value)) = node.child(key) {
+                    push(key, value);
+                }
+            }
+            Step::Wildcard => {
+                for (key, value) in node.children() {
+                    push(key, value);
+                }
+            }
+            Step::Select(selectors) => {
+                let mut selected = node.children();
+                for selector in selectors {
+                    selected = match selector {
+                        Selector::Filter(predicate) => selected
+                            .into_iter()
+                            .filter(|(_, value)| predicate.matches(value))
+                            .collect(),
+                        Selector::Slice(start, end) => {
+                            let len = selected.len() as i64;
+                            let bound = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+                            let start = start.map_or(0, bound) as usize;
+                            let end = end.map_or(len, bound) as usize;
+                            selected.into_iter().take(end).skip(start).collect()
+                        }
+                    };
+                }
+                for (key, value) in selected {
+                    push(key, value);
+                }
+            }
+            Step::Descendants => {
+                out.push((path.to_string(), node));
+                descendants(path, node, out);
+            }
+        }
+    }
+}
+
+fn descendants<'a>(path: &str, node: Node<'a>, out: &mut Vec<(String, Node<'a>)>) {
+    for (key, value) in node.children() {
+        let path = join(path, &key);
+        out.push((path.clone(), Node::Value(value)));
+        descendants(&path, Node::Value(value), out);
+    }
+}
+
+impl Predicate {
+    fn matches(&self, value: &AeonValue) -> bool {
+        let mut field = value;
+        for key in &self.field {
+            field = match Node::Value(field).child(key) {
+                Some((_, value)) => value,
+                None => return false,
+            };
+        }
+        let (comparison, expected) = match &self.comparison {
+            Some(comparison) => comparison,
+            None => return true,
+        };
+        match (comparison, compare(field, expected)) {
+            (Comparison::Eq, ordering) => ordering == Some(Ordering::Equal),
+            (Comparison::Ne, ordering) => ordering != Some(Ordering::Equal),
+            (Comparison::Lt, Some(ordering)) => ordering.is_lt(),
+            (Comparison::Le, Some(ordering)) => ordering.is_le(),
+            (Comparison::Gt, Some(ordering)) => ordering.is_gt(),
+            (Comparison::Ge, Some(ordering)) => ordering.is_ge(),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Orders numbers by value and strings alphabetically, other values are only equal or not
+fn compare(a: &AeonValue, b: &AeonValue) -> Option<Ordering> {
+    match (a, b) {
+        (AeonValue::Integer(a), AeonValue::Integer(b)) => Some(a.cmp(b)),
+        (AeonValue::Integer(a), AeonValue::Double(b)) => (*a as f64).partial_cmp(b),
+        (AeonValue::Double(a), AeonValue::Integer(b)) => a.partial_cmp(&(*b as f64)),
+        (AeonValue::Double(a), AeonValue::Double(b)) => a.partial_cmp(b),
+        (AeonValue::String(a), AeonValue::String(b)) => Some(a.cmp(b)),
+        (a, b) if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn parse_brackets(inner: &str) -> Result<Selector, String> {
+    let operator = find_operator(inner);
+    // a ':' in the value of a filter, e.g. [url == "http://a"], doesn't make it a slice
+    if let (None, Some((start, end))) = (operator, inner.split_once(':')) {
+        let bound = |s: &str| -> Result<Option<i64>, String> {
+            let s = s.trim();
+            if s.is_empty() {
+                return Ok(None);
+            }
+            s.parse()
+                .map(Some)
+                .map_err(|_| format!("'{}' is not a list index", s))
+        };
+        return Ok(Selector::Slice(bound(start)?, bound(end)?));
+    }
+    if let Ok(index) = inner.parse::<i64>() {
+        let end = index
+            .checked_add(1)
+            .ok_or_else(|| format!("'{}' is too large for a list index", index))?;
+        return Ok(Selector::Slice(
+            Some(index),
+            Some(end).filter(|&end| end != 0),
+        ));
+    }
+    let (field, comparison) = match operator {
+        Some((idx, op, comparison)) => {
+            let literal = inner[idx + op.len()..].trim();
+            let value = Deserializer::new(literal)
+                .deserialize_value(&mut AeonDocument::new())
+                .map_err(|e| {
+                    format!(
+                        "Invalid value '{}' in filter: {}, strings must be quoted",
+                        literal, e.message
+                    )
+                })?;
+            (&inner[..idx], Some((comparison, value)))
+        }
+        None => (inner, None),
+    };
+    let field: Vec<String> = field
+        .split(['/', '.'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if field.is_empty() {
+        return Err(format!("Expected a field to filter on in '[{}]'", inner));
+    }
+    Ok(Selector::Filter(Predicate { field, comparison }))
+}
+
+/// The first comparison operator in `inner` that isn't inside a string, and its index
+fn find_operator(inner: &str) -> Option<(usize, &'static str, Comparison)> {
+    let mut in_string = false;
+    let mut chars = inner.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            _ if !in_string => {
+                let found = OPERATORS
+                    .iter()
+                    .find(|(op, _)| inner[idx..].starts_with(op));
+                if let Some((op, comparison)) = found {
+                    return Some((idx, op, *comparison));
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Splits `query` on the '/' that aren't inside brackets or strings
+fn split_segments(query: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut start = 0;
+    let mut chars = query.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string && depth > 0 => depth -= 1,
+            '/' if !in_string && depth == 0 => {
+                segments.push(&query[start..idx]);
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    segments.push(&query[start..]);
+    segments
+}
+
+/// Index of the ']' closing the '[' that `s` starts with
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut chars = s.char_indices().skip(1);
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            ']' if !in_string => return Some(idx),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::AeonObjectConvert;
+    use crate::query::Query;
+    use crate::value::AeonValue;
+
+    const SERVERS: &str = r#"
+        @server(name, port)
+        servers: [server("dev", 8080), server("production", 80), server("backup", 9000)]
+        db: { timeout: 5, replica: { timeout: 10 } }
+        timeout: 30
+    "#;
+
+    fn paths(query: &str) -> Vec<String> {
+        let doc = crate::deserialize(SERVERS.to_string()).unwrap();
+        doc.query(query)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect()
+    }
+
+    #[test]
+    pub fn wildcards_and_recursive_descent() {
+        assert_eq!(
+            vec!["servers/0/port", "servers/1/port", "servers/2/port"],
+            paths("servers/*/port")
+        );
+        assert_eq!(
+            vec!["timeout", "db/timeout", "db/replica/timeout"],
+            paths("**/timeout")
+        );
+        assert_eq!(
+            vec!["db/replica/timeout"],
+            paths("db/**/replica/**/timeout")
+        );
+        assert_eq!(vec!["servers/2/name"], paths("servers/-1/name"));
+        assert!(paths("servers/*/nothing").is_empty());
+    }
+
+    #[test]
+    pub fn slices_and_filters() {
+        assert_eq!(vec!["servers/0", "servers/1"], paths("servers[:2]"));
+        assert_eq!(
+            vec!["servers/1/name", "servers/2/name"],
+            paths("servers[-2:]/name")
+        );
+        assert_eq!(
+            vec!["servers/0/name", "servers/2/name"],
+            paths("servers[port > 8000]/name")
+        );
+        assert_eq!(vec!["servers/1"], paths("servers[name == \"production\"]"));
+        assert_eq!(
+            vec!["servers/1"],
+            paths("servers[port <= 80.5][name != \"dev\"]")
+        );
+        assert_eq!(vec!["db"], paths("[replica/timeout >= 10]"));
+        assert_eq!(vec!["db/replica"], paths("db[timeout]"));
+        assert_eq!(vec!["servers/2"], paths("servers[port > 8000][-1]"));
+
+        let doc = crate::deserialize(
+            r#"links: [{ url: "http://a" }, { url: "https://b:8443" }]"#.to_string(),
+        )
+        .unwrap();
+        let found = doc.query("links[url == \"https://b:8443\"]").unwrap();
+        assert_eq!(
+            vec!["links/1"],
+            found.into_iter().map(|m| m.path).collect::<Vec<_>>()
+        );
+        let found = doc.query("links[url != \"http://a\"]/url").unwrap();
+        assert_eq!("links/1/url", found[0].path);
+
+        let doc = crate::deserialize(SERVERS.to_string()).unwrap();
+        let found = Query::parse("[port == 9000]/name")
+            .unwrap()
+            .find(doc.get_path_ref("servers").unwrap());
+        assert_eq!("2/name", found[0].path);
+        assert_eq!(&AeonValue::String("backup".into()), found[0].value);
+    }
+
+    #[test]
+    pub fn invalid_query_is_error() {
+        let bad = [
+            ("servers[port > 80", "Unclosed '['"),
+            (
+                "servers[port > production]",
+                "Invalid value 'production' in filter",
+            ),
+            ("servers[a:b]", "'a' is not a list index"),
+            (
+                "servers[9223372036854775807]",
+                "'9223372036854775807' is too large for a list index",
+            ),
+            ("servers[ == 1]", "Expected a field to filter on"),
+            ("servers[0]x", "Expected '[' or '/' after ']'"),
+            ("/", "Path is empty"),
+        ];
+        for (query, message) in bad {
+            let err = Query::parse(query).unwrap_err();
+            assert!(err.message.starts_with(message), "{}: {}", query, err);
+        }
+    }
+}
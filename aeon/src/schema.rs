@@ -0,0 +1,648 @@
+use crate::cst::CstDocument;
+use crate::document::{AeonArgType, AeonDocument};
+use crate::error::AeonDeserializeError;
+use crate::ordered_map::OrderedMap;
+use crate::path::join;
+use crate::span::Span;
+use crate::value::{inline, properties, AeonValue};
+use crate::{AeonSchema, DeserializeResult};
+use std::fmt::{Display, Formatter};
+
+/// Keys a schema may have, for the help of errors about unknown keys
+const KEYS: &str = "type, required, enum, min, max, min_length, max_length, pattern, \
+                    macros, properties, additional, values, items and description";
+
+/// The expected shape of a document, written in Aeon, e.g.
+///
+/// ```aeon
+/// name: { type: "string", pattern: "[a-z]*" }
+/// port: { type: "int", min: 1, max: 65535 }
+/// mode: { enum: ["dev", "production"], required: false }
+/// servers: {
+///     type: "list",
+///     min_length: 1,
+///     items: { macros: ["server"], properties: { host: "string", port: "int?" } },
+/// }
+/// ```
+///
+/// Each property of the schema describes the property of the same name in the documents it
+/// validates, and a type like `"int"` or `["int", "double"]` is short for `{ type: ... }`.
+/// The keys are:
+/// - `type`, one type or a list of types, from `nil`, `bool`, `string`, `int`, `double`, `object`
+///   and `list`, where `int?` also accepts `nil`, or `any`
+/// - `required`, false if the value may be left out, true by default
+/// - `enum`, the values that are allowed, or with a `type`, the values allowed of the same type as
+///   them, e.g. `{ type: ["string", "object"], enum: ["Empty"] }` allows any object
+/// - `min` and `max`, the range of numbers
+/// - `min_length` and `max_length`, the number of characters in a string or items in a list
+/// - `pattern`, which strings must match, where `*` matches any characters, `?` any one character
+///   and `[a-z]` or `[!a-z]` characters in or not in a set
+/// - `macros`, the macros objects may be written with, any if left out
+/// - `properties`, the schemas of the keys of an object
+/// - `additional`, true if an object may have keys not in `properties`, false by default
+/// - `values`, the schema of every value of an object
+/// - `items`, the schema of every item of a list
+/// - `description`, which is ignored
+#[derive(Clone, Debug)]
+pub struct Schema {
+    root: Node,
+}
+
+/// A value that doesn't match a [`Schema`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaViolation {
+    /// Path of the value, with segments separated by '/'
+    pub path: String,
+    pub message: String,
+    /// Where the value is in the source, or its parent if it's missing,
+    /// when validated with [`Schema::validate_source`]
+    pub span: Option<Span>,
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at '{}'", self.message, self.path)
+    }
+}
+
+impl SchemaViolation {
+    /// The violation as an error, e.g. to show with [`crate::diagnostic::Diagnostic`]
+    pub fn to_error(&self) -> AeonDeserializeError {
+        let err = AeonDeserializeError::deserialization(self.to_string());
+        match self.span {
+            Some(span) => err.with_span(span),
+            None => err,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    types: Vec<AeonArgType>,
+    required: bool,
+    choices: Option<Vec<AeonValue>>,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<(String, Vec<PatternToken>)>,
+    macros: Option<Vec<String>>,
+    properties: Option<OrderedMap<String, Node>>,
+    additional: bool,
+    values: Option<Box<Node>>,
+    items: Option<Box<Node>>,
+}
+
+impl Schema {
+    pub fn parse(code: &str) -> DeserializeResult<Schema> {
+        Schema::from_document(&crate::deserialize(code.to_string())?)
+    }
+
+    pub fn from_document(doc: &AeonDocument) -> DeserializeResult<Schema> {
+        let mut properties = OrderedMap::new();
+        for (name, prop) in doc.properties.iter() {
+            properties.insert(name.clone(), Node::parse(&prop.value, name)?);
+        }
+        let mut root = Node::any();
+        root.properties = Some(properties);
+        Ok(Schema { root })
+    }
+
+    /// The schema of documents deserialized as `T`, e.g. a struct deriving `AeonSchema`
+    pub fn of<T: AeonSchema>() -> DeserializeResult<Schema> {
+        Schema::from_document(&Schema::document_of::<T>())
+    }
+
+    /// The schema of documents deserialized as `T` as a document, one property per property of
+    /// `T`, e.g. to serialize next to them or to generate reference documentation from
+    pub fn document_of<T: AeonSchema>() -> AeonDocument {
+        match T::property_schema() {
+            AeonValue::Object(node) | AeonValue::Record(_, node) => match node.get("properties") {
+                Some(AeonValue::Object(properties) | AeonValue::Record(_, properties)) => {
+                    properties
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect()
+                }
+                _ => AeonDocument::new(),
+            },
+            _ => AeonDocument::new(),
+        }
+    }
+
+    /// Whether documents may have properties not in the schema, false by default
+    pub fn allow_additional(mut self, allow: bool) -> Self {
+        self.root.additional = allow;
+        self
+    }
+
+    /// Every way in which `doc` doesn't match the schema, in the order of the schema
+    pub fn validate(&self, doc: &AeonDocument) -> Vec<SchemaViolation> {
+        self.validate_root(&properties(doc))
+    }
+
+    fn validate_root(&self, root: &AeonValue) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+        self.root.validate(root, "", &mut violations);
+        violations
+    }
+
+    /// Deserializes `source` and validates it, giving each violation the span of its value
+    pub fn validate_source(&self, source: &str) -> DeserializeResult<Vec<SchemaViolation>> {
+        let cst = CstDocument::parse(source.to_string())?;
+        let mut violations = self.validate_root(&cst.to_document()?.into());
+        for violation in &mut violations {
+            violation.span = cst.span_of(&violation.path).or_else(|| {
+                let parent = violation.path.rsplit_once('/')?.0;
+                cst.span_of(parent)
+            });
+        }
+        Ok(violations)
+    }
+}
+
+impl Node {
+    fn any() -> Node {
+        Node {
+            types: Vec::new(),
+            required: true,
+            choices: None,
+            min: None,
+            max: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+            macros: None,
+            properties: None,
+            additional: false,
+            values: None,
+            items: None,
+        }
+    }
+
+    /// Reads the schema at `path` of the schema document
+    fn parse(value: &AeonValue, path: &str) -> DeserializeResult<Node> {
+        let invalid = |message: String| {
+            AeonDeserializeError::conversion(format!("{} in schema at '{}'", message, path))
+        };
+        let mut node = Node::any();
+        let map = match value {
+            AeonValue::String(_) | AeonValue::List(_) => {
+                node.types = parse_types(value).map_err(invalid)?;
+                return Ok(node);
+            }
+            AeonValue::Object(map) | AeonValue::Record(_, map) => map,
+            v => {
+                return Err(invalid(format!(
+                    "Expected a type, a list of types or an object, found {}",
+                    AeonValue::tag_to_str(v.tag())
+                )))
+            }
+        };
+        for (key, value) in map.iter() {
+            match key.as_str() {
+                "type" => node.types = parse_types(value).map_err(invalid)?,
+                "required" => node.required = boolean(key, value).map_err(invalid)?,
+                "additional" => node.additional = boolean(key, value).map_err(invalid)?,
+                "enum" => match value {
+                    AeonValue::List(choices) => node.choices = Some(choices.clone()),
+                    _ => return Err(invalid("'enum' must be a list".to_string())),
+                },
+                "min" => node.min = Some(number(key, value).map_err(invalid)?),
+                "max" => node.max = Some(number(key, value).map_err(invalid)?),
+                "min_length" => node.min_length = Some(length(key, value).map_err(invalid)?),
+                "max_length" => node.max_length = Some(length(key, value).map_err(invalid)?),
+                "pattern" => match value {
+                    AeonValue::String(pattern) => {
+                        let tokens = parse_pattern(pattern).map_err(invalid)?;
+                        node.pattern = Some((pattern.clone(), tokens));
+                    }
+                    _ => return Err(invalid("'pattern' must be a string".to_string())),
+                },
+                "macros" => node.macros = Some(strings(key, value).map_err(invalid)?),
+                "properties" => match value {
+                    AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                        let mut properties = OrderedMap::new();
+                        for (name, value) in map.iter() {
+                            let child = format!("{}/properties/{}", path, name);
+                            properties.insert(name.clone(), Node::parse(value, &child)?);
+                        }
+                        node.properties = Some(properties);
+                    }
+                    _ => return Err(invalid("'properties' must be an object".to_string())),
+                },
+                "values" => {
+                    node.values = Some(Box::new(Node::parse(value, &format!("{}/values", path))?))
+                }
+                "items" => {
+                    node.items = Some(Box::new(Node::parse(value, &format!("{}/items", path))?))
+                }
+                "description" => (),
+                _ => {
+                    return Err(invalid(format!("Unknown key '{}'", key))
+                        .with_help(format!("the keys of a schema are {}", KEYS)))
+                }
+            }
+        }
+        Ok(node)
+    }
+
+    fn validate(&self, value: &AeonValue, path: &str, violations: &mut Vec<SchemaViolation>) {
+        let mut violation = |message: String| {
+            violations.push(SchemaViolation {
+                path: path.to_string(),
+                message,
+                span: None,
+            })
+        };
+        if !self.types.is_empty() && !self.types.iter().any(|t| t.accepts(value)) {
+            let expected: Vec<String> = self.types.iter().map(|t| t.to_string()).collect();
+            violation(format!(
+                "Expected {}, found {}",
+                expected.join(" or "),
+                AeonValue::tag_to_str(value.tag())
+            ));
+            return;
+        }
+        if matches!(value, AeonValue::Nil) && self.types.iter().any(|t| t.nullable) {
+            return;
+        }
+        if let Some(choices) = &self.choices {
+            let constrained =
+                self.types.is_empty() || choices.iter().any(|c| c.tag() == value.tag());
+            if constrained && !choices.contains(value) {
+                let choices: Vec<String> = choices.iter().map(inline).collect();
+                violation(format!(
+                    "Expected one of {}, found {}",
+                    choices.join(", "),
+                    inline(value)
+                ));
+            }
+        }
+        let number = match value {
+            AeonValue::Integer(i) => Some(*i as f64),
+            AeonValue::Double(d) => Some(*d),
+            _ => None,
+        };
+        if let Some(number) = number {
+            if let Some(min) = self.min.filter(|&min| number < min) {
+                violation(format!("Must be at least {}, found {}", min, inline(value)));
+            }
+            if let Some(max) = self.max.filter(|&max| number > max) {
+                violation(format!("Must be at most {}, found {}", max, inline(value)));
+            }
+        }
+        let length = match value {
+            AeonValue::String(s) => Some((s.chars().count(), "characters")),
+            AeonValue::List(items) => Some((items.len(), "items")),
+            _ => None,
+        };
+        if let Some((length, unit)) = length {
+            if let Some(min) = self.min_length.filter(|&min| length < min) {
+                violation(format!(
+                    "Must have at least {} {}, found {}",
+                    min, unit, length
+                ));
+            }
+            if let Some(max) = self.max_length.filter(|&max| length > max) {
+                violation(format!(
+                    "Must have at most {} {}, found {}",
+                    max, unit, length
+                ));
+            }
+        }
+        if let (Some((pattern, tokens)), AeonValue::String(s)) = (&self.pattern, value) {
+            if !matches_pattern(tokens, &s.chars().collect::<Vec<char>>()) {
+                violation(format!(
+                    "{} does not match the pattern '{}'",
+                    inline(value),
+                    pattern
+                ));
+            }
+        }
+        if let (Some(macros), Some(name)) = (&self.macros, value.macro_name()) {
+            if !macros.iter().any(|m| m == name) {
+                violation(if macros.is_empty() {
+                    format!("Macro '{}' is not allowed here", name)
+                } else {
+                    format!(
+                        "Macro '{}' is not allowed here, expected one of {}",
+                        name,
+                        macros.join(", ")
+                    )
+                });
+            }
+        }
+
+        match value {
+            AeonValue::Object(map) | AeonValue::Record(_, map) => {
+                self.validate_map(map, path, violations)
+            }
+            AeonValue::List(items) => {
+                if let Some(schema) = &self.items {
+                    for (idx, item) in items.iter().enumerate() {
+                        schema.validate(item, &join(path, &idx.to_string()), violations);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn validate_map(
+        &self,
+        map: &OrderedMap<String, AeonValue>,
+        path: &str,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        if let Some(properties) = &self.properties {
+            for (name, schema) in properties.iter() {
+                match map.get(name) {
+                    Some(value) => schema.validate(value, &join(path, name), violations),
+                    None if schema.required => violations.push(SchemaViolation {
+                        path: join(path, name),
+                        message: format!("Missing required property '{}'", name),
+                        span: None,
+                    }),
+                    None => (),
+                }
+            }
+            if !self.additional {
+                for key in map.keys().filter(|k| !properties.contains_key(*k)) {
+                    violations.push(SchemaViolation {
+                        path: join(path, key),
+                        message: format!("Unknown property '{}'", key),
+                        span: None,
+                    });
+                }
+            }
+        }
+        if let Some(schema) = &self.values {
+            for (key, value) in map.iter() {
+                schema.validate(value, &join(path, key), violations);
+            }
+        }
+    }
+}
+
+fn parse_types(value: &AeonValue) -> Result<Vec<AeonArgType>, String> {
+    let names = match value {
+        AeonValue::String(name) => vec![name.clone()],
+        AeonValue::List(_) => strings("type", value)?,
+        _ => return Err("'type' must be a type name or a list of them".to_string()),
+    };
+    names
+        .iter()
+        .filter(|name| name.as_str() != "any")
+        .map(|name| {
+            let (name, nullable) = match name.strip_suffix('?') {
+                Some(name) => (name, true),
+                None => (name.as_str(), false),
+            };
+            AeonArgType::from_name(name, nullable).ok_or_else(|| format!("Unknown type '{}'", name))
+        })
+        .collect()
+}
+
+fn boolean(key: &str, value: &AeonValue) -> Result<bool, String> {
+    match value {
+        AeonValue::Bool(b) => Ok(*b),
+        _ => Err(format!("'{}' must be true or false", key)),
+    }
+}
+
+fn number(key: &str, value: &AeonValue) -> Result<f64, String> {
+    match value {
+        AeonValue::Integer(i) => Ok(*i as f64),
+        AeonValue::Double(d) => Ok(*d),
+        _ => Err(format!("'{}' must be a number", key)),
+    }
+}
+
+fn length(key: &str, value: &AeonValue) -> Result<usize, String> {
+    match value {
+        AeonValue::Integer(i) if *i >= 0 => Ok(*i as usize),
+        _ => Err(format!("'{}' must be a number that isn't negative", key)),
+    }
+}
+
+fn strings(key: &str, value: &AeonValue) -> Result<Vec<String>, String> {
+    let items = match value {
+        AeonValue::List(items) => items,
+        _ => return Err(format!("'{}' must be a list of strings", key)),
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            AeonValue::String(s) => Ok(s.clone()),
+            _ => Err(format!("'{}' must be a list of strings", key)),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+enum PatternToken {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyChars,
+    /// `[a-z]`, or `[!a-z]` if negated
+    Set {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<PatternToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '?' => PatternToken::AnyChar,
+            '*' => PatternToken::AnyChars,
+            '[' => {
+                let mut negated = false;
+                let mut ranges = Vec::new();
+                let mut set: Vec<char> = Vec::new();
+                loop {
+                    match chars.next() {
+                        Some(']') if !set.is_empty() || negated => break,
+                        Some('!') if set.is_empty() && !negated => negated = true,
+                        Some(c) => set.push(c),
+                        None => return Err(format!("Unclosed '[' in pattern '{}'", pattern)),
+                    }
+                }
+                let mut i = 0;
+                while i < set.len() {
+                    if i + 2 < set.len() && set[i + 1] == '-' {
+                        ranges.push((set[i], set[i + 2]));
+                        i += 3;
+                    } else {
+                        ranges.push((set[i], set[i]));
+                        i += 1;
+                    }
+                }
+                PatternToken::Set { negated, ranges }
+            }
+            c => PatternToken::Char(c),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Matches `s` against the whole pattern, going back to the last `*` when a match fails
+fn matches_pattern(tokens: &[PatternToken], s: &[char]) -> bool {
+    let (mut t, mut i) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while i < s.len() {
+        let matched = match tokens.get(t) {
+            Some(PatternToken::AnyChars) => {
+                backtrack = Some((t, i));
+                t += 1;
+                continue;
+            }
+            Some(PatternToken::Char(c)) => *c == s[i],
+            Some(PatternToken::AnyChar) => true,
+            Some(PatternToken::Set { negated, ranges }) => {
+                ranges.iter().any(|&(lo, hi)| lo <= s[i] && s[i] <= hi) != *negated
+            }
+            None => false,
+        };
+        if matched {
+            t += 1;
+            i += 1;
+        } else if let Some((star, at)) = backtrack {
+            // let the last `*` match one more character
+            t = star + 1;
+            i = at + 1;
+            backtrack = Some((star, at + 1));
+        } else {
+            return false;
+        }
+    }
+    tokens[t..]
+        .iter()
+        .all(|t| matches!(t, PatternToken::AnyChars))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::Schema;
+
+    const SCHEMA: &str = r#"
+        name: { type: "string", pattern: "[a-z]*-[0-9][0-9]", description: "name of the app" }
+        port: { type: "int", min: 1, max: 65535 }
+        mode: { enum: ["dev", "production"], required: false }
+        tags: { type: "list", items: "string", max_length: 2, required: false }
+        servers: {
+            type: "list",
+            min_length: 1,
+            items: { macros: ["server"], properties: { host: "string", port: "int?" } },
+        }
+        limits: { type: "object", values: ["int", "double"], required: false }
+    "#;
+
+    fn violations(code: &str) -> Vec<String> {
+        Schema::parse(SCHEMA)
+            .unwrap()
+            .validate_source(code)
+            .unwrap()
+            .iter()
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn valid_document_has_no_violations() {
+        let code = r#"
+            @server(host, port)
+            name: "app-01"
+            port: 8080
+            mode: "dev"
+            servers: [server("a", 1), server("b", nil), { host: "c", port: 3 }]
+            limits: { cpu: 1.5, memory: 512 }
+        "#;
+        assert!(violations(code).is_empty(), "{:?}", violations(code));
+    }
+
+    #[test]
+    pub fn all_violations_are_reported() {
+        let code = r#"
+            @node(host, port)
+            name: "App-1"
+            port: 0
+            mode: "staging"
+            tags: ["a", 2, "c"]
+            servers: [node("a", 1), { host: "b", port: "80", extra: true }]
+            limits: { cpu: "all" }
+            debug: true
+        "#;
+        assert_eq!(
+            vec![
+                "\"App-1\" does not match the pattern '[a-z]*-[0-9][0-9]' at 'name'",
+                "Must be at least 1, found 0 at 'port'",
+                "Expected one of \"dev\", \"production\", found \"staging\" at 'mode'",
+                "Must have at most 2 items, found 3 at 'tags'",
+                "Expected string, found int at 'tags/1'",
+                "Macro 'node' is not allowed here, expected one of server at 'servers/0'",
+                "Expected int?, found string at 'servers/1/port'",
+                "Unknown property 'extra' at 'servers/1/extra'",
+                "Expected int or double, found string at 'limits/cpu'",
+                "Unknown property 'debug' at 'debug'",
+            ],
+            violations(code)
+        );
+    }
+
+    #[test]
+    pub fn violations_have_spans() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let code = "name: \"x-10\"\nport: 99999\nservers: [{ port: 1 }]";
+        let found = schema.validate_source(code).unwrap();
+        assert_eq!(2, found.len(), "{:?}", found);
+        let span = found[0].span.unwrap();
+        assert_eq!((2, 7), (span.start.line, span.start.column));
+        // missing values point at their parent
+        assert_eq!("Missing required property 'host'", found[1].message);
+        let span = found[1].span.unwrap();
+        assert_eq!((3, 11), (span.start.line, span.start.column));
+
+        let found = schema
+            .allow_additional(true)
+            .validate_source("debug: 1")
+            .unwrap();
+        assert_eq!(
+            vec!["name", "port", "servers"],
+            found.iter().map(|v| v.path.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(None, found[0].span);
+    }
+
+    #[test]
+    pub fn invalid_schema_is_error() {
+        let bad = [
+            ("a: \"integer\"", "Unknown type 'integer' in schema at 'a'"),
+            (
+                "a: { type: \"int\", maximum: 1 }",
+                "Unknown key 'maximum' in schema at 'a'",
+            ),
+            (
+                "a: { items: { pattern: \"[a-\" } }",
+                "Unclosed '[' in pattern '[a-' in schema at 'a/items'",
+            ),
+            (
+                "a: { min_length: -1 }",
+                "'min_length' must be a number that isn't negative",
+            ),
+            (
+                "a: 1",
+                "Expected a type, a list of types or an object, found int",
+            ),
+        ];
+        for (code, message) in bad {
+            let err = Schema::parse(code).unwrap_err();
+            assert!(err.message.starts_with(message), "{}: {}", code, err);
+        }
+    }
+}
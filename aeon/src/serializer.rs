@@ -1,5 +1,7 @@
 use crate::document::{AeonDocument, AeonMacro, AeonProperty};
+use crate::ordered_map::OrderedMap;
 use crate::value::AeonValue;
+use std::collections::{HashMap, HashSet};
 
 macro_rules! serialize_arg(
     ($s:ident, $idx:ident, $val:expr) => {
@@ -15,36 +17,76 @@ macro_rules! serialize_arg(
 
 pub trait AeonFormatter {
     fn serialize_aeon(obj: &AeonDocument) -> String;
-    fn serialize_macro(&mut self, mac: &AeonMacro, s: &mut String);
+    fn serialize_macro(&mut self, obj: &AeonDocument, mac: &AeonMacro, s: &mut String);
     fn serialize_property(&mut self, obj: &AeonDocument, property: &AeonProperty, s: &mut String);
     fn serialize_value(&mut self, obj: &AeonDocument, value: &AeonValue, s: &mut String);
 }
 
+/// Options for [`crate::serialize_with_options`]
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    /// Write macros, properties and map keys sorted by name instead of in insertion order
+    pub sort_keys: bool,
+    /// Write calls to macros with at least this many arguments using named arguments,
+    /// e.g. `server(id: 1, name: "a")`
+    pub named_args_threshold: Option<usize>,
+    /// Write lists and objects that appear more than once as an anchor, `&name value`,
+    /// the first time and as a reference, `*name`, after that
+    pub anchors: bool,
+}
+
 pub struct PrettySerializer {
     indent: i8,
     indent_skip: bool,
+    options: SerializeOptions,
+    /// How many times each list and object is written, keyed by its inline serialization
+    repeats: HashMap<String, usize>,
+    /// Names of the anchors written so far, keyed like `repeats`
+    anchor_names: HashMap<String, String>,
+    used_anchor_names: HashSet<String>,
+    /// Name to base the anchor of the next value on, e.g. the key it's written under
+    anchor_hint: Option<String>,
+}
+
+enum Anchor {
+    Define(String),
+    Reference(String),
 }
 
 impl PrettySerializer {
-    pub fn new() -> Self {
+    pub fn with_options(options: SerializeOptions) -> Self {
         Self {
             indent: 0,
             indent_skip: false,
+            options,
+            repeats: HashMap::new(),
+            anchor_names: HashMap::new(),
+            used_anchor_names: HashSet::new(),
+            anchor_hint: None,
         }
     }
-}
 
-impl AeonFormatter for PrettySerializer {
-    fn serialize_aeon(obj: &AeonDocument) -> String {
-        let mut ser = PrettySerializer::new();
+    pub fn serialize_aeon_with_options(obj: &AeonDocument, options: SerializeOptions) -> String {
+        let mut ser = PrettySerializer::with_options(options);
         let mut s = String::with_capacity(50);
-        for mac in obj.macros.values() {
-            ser.serialize_macro(mac, &mut s);
+        let mut macros: Vec<&AeonMacro> = obj.macros.values().collect();
+        let mut properties: Vec<&AeonProperty> = obj.properties.values().collect();
+        if ser.options.sort_keys {
+            macros.sort_by(|a, b| a.name.cmp(&b.name));
+            properties.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        if ser.options.anchors {
+            for prop in &properties {
+                ser.count_repeats(obj, &prop.value);
+            }
+        }
+        for mac in macros {
+            ser.serialize_macro(obj, mac, &mut s);
         }
         if !obj.macros.is_empty() {
             s.push('\n');
         }
-        for prop in obj.properties.values() {
+        for prop in properties {
             ser.serialize_property(obj, prop, &mut s);
             s.push('\n');
             s.push('\n');
@@ -52,12 +94,107 @@ impl AeonFormatter for PrettySerializer {
         s
     }
 
-    fn serialize_macro(&mut self, mac: &AeonMacro, s: &mut String) {
+    /// The values inside `value`, in the order they are written
+    fn children<'a>(&self, obj: &AeonDocument, value: &'a AeonValue) -> Vec<&'a AeonValue> {
+        match value {
+            AeonValue::List(v) => v.iter().collect(),
+            AeonValue::Object(v) | AeonValue::Record(_, v) => match macro_for(obj, value, v) {
+                Some(m) => {
+                    let named = self
+                        .options
+                        .named_args_threshold
+                        .is_some_and(|threshold| m.len() >= threshold);
+                    args_to_write(m, v, named)
+                        .into_iter()
+                        .map(|i| &v[&m.args[i]])
+                        .collect()
+                }
+                None => {
+                    let mut entries: Vec<(&String, &AeonValue)> = v.iter().collect();
+                    if self.options.sort_keys {
+                        entries.sort_by(|a, b| a.0.cmp(b.0));
+                    }
+                    entries.into_iter().map(|(_, v)| v).collect()
+                }
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Counts how many times each list and object will be written, not counting
+    /// those inside values that will be written as references
+    fn count_repeats(&mut self, obj: &AeonDocument, value: &AeonValue) {
+        if !can_anchor(value) {
+            return;
+        }
+        let mut key = String::new();
+        serialize_value_inline(obj, value, &mut key);
+        let count = self.repeats.entry(key).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            for child in self.children(obj, value) {
+                self.count_repeats(obj, child);
+            }
+        }
+    }
+
+    /// Whether `value` should be written with an anchor or as a reference
+    fn anchor_for(&mut self, obj: &AeonDocument, value: &AeonValue) -> Option<Anchor> {
+        let hint = self.anchor_hint.take();
+        if !self.options.anchors || !can_anchor(value) {
+            return None;
+        }
+        let mut key = String::new();
+        serialize_value_inline(obj, value, &mut key);
+        if self.repeats.get(&key).is_none_or(|&count| count < 2) {
+            return None;
+        }
+        if let Some(name) = self.anchor_names.get(&key) {
+            return Some(Anchor::Reference(name.clone()));
+        }
+        let base = hint
+            .filter(|h| is_valid_identifier(h))
+            .unwrap_or_else(|| "value".to_string());
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.used_anchor_names.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        self.used_anchor_names.insert(name.clone());
+        self.anchor_names.insert(key, name.clone());
+        Some(Anchor::Define(name))
+    }
+}
+
+/// Only non-empty lists and objects are worth writing as references
+fn can_anchor(value: &AeonValue) -> bool {
+    match value {
+        AeonValue::List(v) => !v.is_empty(),
+        AeonValue::Object(v) | AeonValue::Record(_, v) => !v.is_empty(),
+        _ => false,
+    }
+}
+
+impl AeonFormatter for PrettySerializer {
+    fn serialize_aeon(obj: &AeonDocument) -> String {
+        Self::serialize_aeon_with_options(obj, SerializeOptions::default())
+    }
+
+    fn serialize_macro(&mut self, obj: &AeonDocument, mac: &AeonMacro, s: &mut String) {
         s.push('@');
         s.push_str(mac.name.as_str());
         s.push('(');
         for arg in 0..mac.args.len() {
             serialize_arg!(s, arg, &mac.args[arg]);
+            if let Some(typ) = mac.type_of(arg) {
+                s.push_str(": ");
+                s.push_str(&typ.to_string());
+            }
+            if let Some(default) = mac.default_of(arg) {
+                s.push_str(" = ");
+                serialize_value_inline(obj, default, s);
+            }
         }
         s.push(')');
         s.push('\n');
@@ -67,6 +204,7 @@ impl AeonFormatter for PrettySerializer {
         s.push_str(property.name.as_str());
         s.push(':');
         s.push(' ');
+        self.anchor_hint = Some(property.name.clone());
         self.serialize_value(obj, &property.value, s);
     }
 
@@ -82,6 +220,22 @@ impl AeonFormatter for PrettySerializer {
                 }
             };
         }
+        match self.anchor_for(obj, value) {
+            Some(Anchor::Reference(name)) => {
+                indent_me!(self, s);
+                s.push('*');
+                s.push_str(&name);
+                return;
+            }
+            Some(Anchor::Define(name)) => {
+                indent_me!(self, s);
+                s.push('&');
+                s.push_str(&name);
+                s.push(' ');
+                self.indent_skip = true;
+            }
+            None => (),
+        }
         match value {
             AeonValue::Nil => {
                 indent_me!(self, s);
@@ -93,35 +247,7 @@ impl AeonFormatter for PrettySerializer {
             }
             AeonValue::String(v) => {
                 indent_me!(self, s);
-                s.push('"');
-                for x in v.chars() {
-                    match x {
-                        '\\' => {
-                            s.push('\\');
-                            s.push('\\');
-                        }
-                        '\r' => {
-                            s.push('\\');
-                            s.push('r');
-                        }
-                        '\n' => {
-                            s.push('\\');
-                            s.push('n');
-                        }
-                        '\t' => {
-                            s.push('\\');
-                            s.push('t');
-                        }
-                        '"' => {
-                            s.push('\\');
-                            s.push('"');
-                        }
-                        _ => {
-                            s.push(x);
-                        }
-                    }
-                }
-                s.push('"');
+                serialize_string(v, s);
             }
             AeonValue::Integer(v) => {
                 indent_me!(self, s);
@@ -150,21 +276,33 @@ impl AeonFormatter for PrettySerializer {
                 }
                 s.push(']');
             }
-            AeonValue::Object(v) => {
+            AeonValue::Object(v) | AeonValue::Record(_, v) => {
                 indent_me!(self, s);
-                if let Some(m) = obj.try_get_macro(v) {
+                if let Some(m) = macro_for(obj, value, v) {
                     // first check if a macro exists for this map
                     s.push_str(&m.name);
                     s.push('(');
+                    let named = self
+                        .options
+                        .named_args_threshold
+                        .is_some_and(|threshold| m.len() >= threshold);
+                    let args = args_to_write(m, v, named);
                     self.indent += 4;
                     if v.iter().any(|(_, v)| matches!(v, AeonValue::List(_))) {
                         // map contains a list
                         self.indent_skip = true;
-                        for i in 0..m.args.len() {
-                            if i != 0 {
+                        for (n, &i) in args.iter().enumerate() {
+                            if n != 0 {
                                 s.push(',');
                                 s.push('\n');
                             }
+                            if named {
+                                indent_me!(self, s);
+                                s.push_str(&m.args[i]);
+                                s.push_str(": ");
+                                self.indent_skip = true;
+                            }
+                            self.anchor_hint = Some(m.args[i].clone());
                             self.serialize_value(obj, &v[&m.args[i]], s);
                         }
                         self.indent -= 4;
@@ -174,12 +312,17 @@ impl AeonFormatter for PrettySerializer {
                         }
                     } else {
                         // map contains no list
-                        for i in 0..m.args.len() {
+                        for (n, &i) in args.iter().enumerate() {
                             self.indent_skip = true;
-                            if i != 0 {
+                            if n != 0 {
                                 s.push(',');
                                 s.push(' ');
                             }
+                            if named {
+                                s.push_str(&m.args[i]);
+                                s.push_str(": ");
+                            }
+                            self.anchor_hint = Some(m.args[i].clone());
                             self.serialize_value(obj, &v[&m.args[i]], s);
                         }
                         self.indent -= 4;
@@ -189,7 +332,11 @@ impl AeonFormatter for PrettySerializer {
                     // if not, serialize as a regular map
                     s.push('{');
                     let mut f = true;
-                    for (k, v) in v.iter() {
+                    let mut entries: Vec<(&String, &AeonValue)> = v.iter().collect();
+                    if self.options.sort_keys {
+                        entries.sort_by(|a, b| a.0.cmp(b.0));
+                    }
+                    for (k, v) in entries {
                         if f {
                             f = false;
                         } else {
@@ -207,6 +354,7 @@ impl AeonFormatter for PrettySerializer {
                         s.push(':');
                         s.push(' ');
                         self.indent_skip = true;
+                        self.anchor_hint = Some(k.clone());
                         self.serialize_value(obj, v, s);
                     }
                     if !v.is_empty() {
@@ -220,7 +368,122 @@ impl AeonFormatter for PrettySerializer {
     }
 }
 
-fn is_valid_identifier(s: &str) -> bool {
+/// The macro to serialize `map` with, preferring the one that created it
+fn macro_for<'a>(
+    obj: &'a AeonDocument,
+    value: &AeonValue,
+    map: &OrderedMap<String, AeonValue>,
+) -> Option<&'a AeonMacro> {
+    value
+        .macro_name()
+        .and_then(|name| obj.try_get_named_macro(name, map))
+        .or_else(|| obj.try_get_macro(map))
+}
+
+/// Indexes of the arguments of `m` to write when serializing `map` with it, leaving out
+/// those equal to their default value. Positional calls can only leave out trailing arguments.
+fn args_to_write(m: &AeonMacro, map: &OrderedMap<String, AeonValue>, named: bool) -> Vec<usize> {
+    let is_default = |idx: usize| {
+        m.default_of(idx)
+            .is_some_and(|default| map.get(&m.args[idx]) == Some(default))
+    };
+    if named {
+        return (0..m.len()).filter(|&idx| !is_default(idx)).collect();
+    }
+    let mut len = m.len();
+    while len > 0 && is_default(len - 1) {
+        len -= 1;
+    }
+    (0..len).collect()
+}
+
+fn serialize_string(v: &str, s: &mut String) {
+    s.push('"');
+    for x in v.chars() {
+        match x {
+            '\\' => {
+                s.push('\\');
+                s.push('\\');
+            }
+            '\r' => {
+                s.push('\\');
+                s.push('r');
+            }
+            '\n' => {
+                s.push('\\');
+                s.push('n');
+            }
+            '\t' => {
+                s.push('\\');
+                s.push('t');
+            }
+            '"' => {
+                s.push('\\');
+                s.push('"');
+            }
+            _ => {
+                s.push(x);
+            }
+        }
+    }
+    s.push('"');
+}
+
+/// Serializes `value` on a single line, using the macros of `obj` for objects they apply to.
+/// Used where a value is written into existing text, such as edits through [`crate::cst`].
+pub(crate) fn serialize_value_inline(obj: &AeonDocument, value: &AeonValue, s: &mut String) {
+    match value {
+        AeonValue::Nil => s.push_str("nil"),
+        AeonValue::Bool(v) => s.push_str(if *v { "true" } else { "false" }),
+        AeonValue::String(v) => serialize_string(v, s),
+        AeonValue::Integer(v) => s.push_str(&v.to_string()),
+        AeonValue::Double(v) => s.push_str(&format!("{:?}", v)),
+        AeonValue::List(v) => {
+            s.push('[');
+            for (i, item) in v.iter().enumerate() {
+                if i != 0 {
+                    s.push_str(", ");
+                }
+                serialize_value_inline(obj, item, s);
+            }
+            s.push(']');
+        }
+        AeonValue::Object(v) | AeonValue::Record(_, v) => {
+            if let Some(m) = macro_for(obj, value, v) {
+                s.push_str(&m.name);
+                s.push('(');
+                for (n, i) in args_to_write(m, v, false).into_iter().enumerate() {
+                    if n != 0 {
+                        s.push_str(", ");
+                    }
+                    serialize_value_inline(obj, &v[&m.args[i]], s);
+                }
+                s.push(')');
+            } else {
+                s.push('{');
+                for (i, (k, v)) in v.iter().enumerate() {
+                    if i != 0 {
+                        s.push_str(", ");
+                    }
+                    serialize_key(k, s);
+                    s.push_str(": ");
+                    serialize_value_inline(obj, v, s);
+                }
+                s.push('}');
+            }
+        }
+    }
+}
+
+pub(crate) fn serialize_key(k: &str, s: &mut String) {
+    if is_valid_identifier(k) {
+        s.push_str(k);
+    } else {
+        serialize_string(k, s);
+    }
+}
+
+pub(crate) fn is_valid_identifier(s: &str) -> bool {
     let start_valid = match s.get(0..=0) {
         None => false,
         Some(first) => first
@@ -237,11 +500,12 @@ fn is_valid_identifier(s: &str) -> bool {
     }
     for c in s.chars() {
         match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => (),
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => (),
             _ => return false,
         }
     }
-    true
+    // a '-' joins two words
+    !s.ends_with('-') && !s.contains("--")
 }
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+
+/// A location in the source text.
+///
+/// `offset` is a byte offset, `line` and `column` are 1-based, with `column`
+/// counted in chars rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const fn start() -> Position {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A range in the source text, `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub const fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    /// Zero-width span at `pos`, used for things like unexpected end of input.
+    pub const fn at(pos: Position) -> Span {
+        Span {
+            start: pos,
+            end: pos,
+        }
+    }
+
+    /// Span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let start = if other.start.offset < self.start.offset {
+            other.start
+        } else {
+            self.start
+        };
+        let end = if other.end.offset > self.end.offset {
+            other.end
+        } else {
+            self.end
+        };
+        Span { start, end }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
@@ -1,3 +1,6 @@
+use crate::span::Span;
+use std::fmt::{Display, Formatter};
+
 #[derive(Debug)]
 pub enum Token {
     LeftBracket,
@@ -8,12 +11,51 @@ pub enum Token {
     RightParenthesis,
     Colon,
     Comma,
+    Equals,
+    Question,
     Identifier(String),
     String(String),
     Integer(i64),
     Double(f64),
     At,
+    /// `&name`, names the value after it
+    Anchor(String),
+    /// `*name`, refers to the value named with `&name`
+    Alias(String),
     True,
     False,
     Nil,
 }
+
+#[derive(Debug)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LeftBracket => write!(f, "'['"),
+            Token::RightBracket => write!(f, "']'"),
+            Token::LeftBrace => write!(f, "'{{'"),
+            Token::RightBrace => write!(f, "'}}'"),
+            Token::LeftParenthesis => write!(f, "'('"),
+            Token::RightParenthesis => write!(f, "')'"),
+            Token::Colon => write!(f, "':'"),
+            Token::Comma => write!(f, "','"),
+            Token::Equals => write!(f, "'='"),
+            Token::Question => write!(f, "'?'"),
+            Token::Identifier(id) => write!(f, "identifier '{}'", id),
+            Token::String(s) => write!(f, "string {:?}", s),
+            Token::Integer(i) => write!(f, "integer {}", i),
+            Token::Double(d) => write!(f, "number {:?}", d),
+            Token::At => write!(f, "'@'"),
+            Token::Anchor(name) => write!(f, "anchor '&{}'", name),
+            Token::Alias(name) => write!(f, "reference '*{}'", name),
+            Token::True => write!(f, "'true'"),
+            Token::False => write!(f, "'false'"),
+            Token::Nil => write!(f, "'nil'"),
+        }
+    }
+}
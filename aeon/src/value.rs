@@ -1,26 +1,47 @@
-use std::collections::HashMap;
+use crate::ordered_map::OrderedMap;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AeonValue {
     Nil,
     Bool(bool),
     String(String),
     Integer(i64),
     Double(f64),
-    Object(HashMap<String, AeonValue>),
+    Object(OrderedMap<String, AeonValue>),
+    /// An object created by calling a macro, which remembers the name of the macro
+    /// so that it can be serialized with that same macro
+    Record(String, OrderedMap<String, AeonValue>),
     List(Vec<AeonValue>),
 }
 
 impl From<crate::document::AeonDocument> for AeonValue {
     fn from(doc: crate::document::AeonDocument) -> Self {
-        let mut obj = HashMap::<String, AeonValue>::new();
-        doc.properties
-            .into_values()
-            .for_each(|a| obj.insert(a.name, a.value).map_or((), |_| ()));
-        AeonValue::Object(obj)
+        AeonValue::Object(
+            doc.properties
+                .into_values()
+                .map(|a| (a.name, a.value))
+                .collect(),
+        )
     }
 }
 
+/// The properties of `doc` as an object, for when the document can't be taken by value
+pub(crate) fn properties(doc: &crate::document::AeonDocument) -> AeonValue {
+    AeonValue::Object(
+        doc.properties
+            .iter()
+            .map(|(k, p)| (k.clone(), p.value.clone()))
+            .collect(),
+    )
+}
+
+/// `value` written on one line without macros, for messages
+pub(crate) fn inline(value: &AeonValue) -> String {
+    let mut s = String::new();
+    crate::serializer::serialize_value_inline(&crate::document::AeonDocument::new(), value, &mut s);
+    s
+}
+
 impl AeonValue {
     pub const fn tag(&self) -> u8 {
         match self {
@@ -29,11 +50,24 @@ impl AeonValue {
             AeonValue::String(_) => 2,
             AeonValue::Integer(_) => 3,
             AeonValue::Double(_) => 4,
-            AeonValue::Object(_) => 5,
+            AeonValue::Object(_) | AeonValue::Record(..) => 5,
             AeonValue::List(_) => 6,
         }
     }
 
+    /// Every value inside this one matched by `query`, see [`crate::query::Query`]
+    pub fn query(&self, query: &str) -> crate::PathResult<Vec<crate::query::QueryMatch<'_>>> {
+        Ok(crate::query::Query::parse(query)?.find(self))
+    }
+
+    /// The name of the macro that created this value, if any
+    pub fn macro_name(&self) -> Option<&str> {
+        match self {
+            AeonValue::Record(name, _) => Some(name),
+            _ => None,
+        }
+    }
+
     pub const fn tag_to_str(tag: u8) -> &'static str {
         match tag {
             0 => "nil",
@@ -46,4 +80,9 @@ impl AeonValue {
             _ => "Missing implementation for tag, bug in AeonValue::tag_to_str",
         }
     }
+
+    /// The tag with the name `name`, the opposite of `tag_to_str`
+    pub fn tag_from_str(name: &str) -> Option<u8> {
+        (0..=6).find(|&tag| Self::tag_to_str(tag) == name)
+    }
 }
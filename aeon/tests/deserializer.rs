@@ -32,7 +32,7 @@ mod tests {
 
                 fn create_macros(
                     insert_self: bool,
-                ) -> std::collections::HashMap<String, aeon::document::AeonMacro> {
+                ) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {
                     use aeon::AeonSerializeProperty;
                     Self::create_property_macros(insert_self)
                 }
@@ -49,12 +49,13 @@ mod tests {
     /// }
     ///
     /// fn ser_props() -> AeonValue {
-    ///     let mut obj = std::collections::HashMap::<String, AeonValue>::new();
+    ///     let mut obj = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
     ///     aeon_prop_ser!(obj => self.cool);
     ///     aeon_prop_ser!(maybe obj => self.maybe_cool);
     ///     AeonValue::Object(obj)
     /// }
     /// ```
+    #[allow(unused_macros)]
     macro_rules! aeon_prop_ser {
         (maybe $obj:expr => $prop:expr) => {
             $obj.insert(
@@ -74,21 +75,267 @@ mod tests {
     pub fn deserialize_map_with_both_quoted_and_not_quoted_keys() {
         use aeon::convert::{AeonConvert, AeonObjectConvert};
         let aeon = r#"map: {test: 1, "two": 2}"#.into();
-        let ser = aeon::deserialize(aeon).ok().expect("failed to deserialize");
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
 
         assert_eq!(ser.get_path("map/test").int(), Some(1));
         assert_eq!(ser.get_path("map/two").int(), Some(2));
     }
 
+    #[test]
+    pub fn deserialize_identifiers_with_dashes() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = "@m(a-b, c_d-2)\nx-y: m(1, { e-f: 2 })".into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(ser.get_path("x-y/a-b").int(), Some(1));
+        assert_eq!(ser.get_path("x-y/c_d-2/e-f").int(), Some(2));
+
+        let bad = [
+            "a-: 1",
+            "a--b: 1",
+            "@m(a-) x: m(1)",
+            "x: { a-: 1 }",
+            "x-: -1",
+        ];
+        for aeon in bad {
+            let err = aeon::deserialize(aeon.into()).expect_err(aeon);
+            assert!(
+                err.message.contains("after '-' in identifier"),
+                "{}: {}",
+                aeon,
+                err
+            );
+        }
+    }
+
     #[test]
     pub fn deserialize_double() {
         use aeon::convert::{AeonConvert, AeonObjectConvert};
         let aeon = r#"doub: 2.10"#.into();
-        let ser = aeon::deserialize(aeon).ok().expect("failed to deserialize");
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
 
         assert_eq!(ser.get("doub").double(), Some(2.10));
     }
 
+    #[test]
+    pub fn deserialize_error_points_at_offending_token() {
+        let aeon = "first: 1\nsecond: [1, 2,\n    3 4]\n".into();
+        let err = aeon::deserialize(aeon).expect_err("expected error");
+        let span = err.span.expect("expected span on error");
+        assert_eq!((3, 7), (span.start.line, span.start.column));
+        assert_eq!(30, span.start.offset);
+        assert_eq!(31, span.end.offset);
+        assert!(err.to_string().ends_with(" at 3:7"), "{}", err);
+    }
+
+    #[test]
+    pub fn deserialize_error_at_end_of_input() {
+        let aeon = "value: ".into();
+        let err = aeon::deserialize(aeon).expect_err("expected error");
+        let span = err.span.expect("expected span on error");
+        assert_eq!((1, 8), (span.start.line, span.start.column));
+        assert!(span.is_empty());
+        assert_eq!("Expected property value, found end of input", err.message);
+    }
+
+    #[test]
+    pub fn deserialize_error_for_unclosed_list_points_at_opening_bracket() {
+        let aeon = "list: [1, 2".into();
+        let err = aeon::deserialize(aeon).expect_err("expected error");
+        let span = err.span.expect("expected span on error");
+        assert_eq!((1, 7), (span.start.line, span.start.column));
+        assert_eq!("Unclosed list", err.message);
+        assert!(err.help.is_some());
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_remembers_macro() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = "@point(x, y)\n@size(x, y)\nat: point(1, 2)\nbig: size(3, 4)".into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(Some("point"), ser.get("at").unwrap().macro_name());
+        assert_eq!(Some("size"), ser.get("big").unwrap().macro_name());
+        assert_eq!(ser.get_path("big/y").int(), Some(4));
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_with_named_args() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = r#"@server(id, name, ip, port)
+            a: server(id: 1, name: "test", ip: "127.0.0.1", port: 7171)
+            b: server(2, port: 8080, ip: "0.0.0.0", name: "other")"#
+            .into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(ser.get_path("a/port").int(), Some(7171));
+        assert_eq!(ser.get_path("b/id").int(), Some(2));
+        assert_eq!(ser.get_path("b/name").str(), Some("other".into()));
+        assert_eq!(ser.get_path("b/port").int(), Some(8080));
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_with_bad_named_args_is_error() {
+        let bad = [
+            ("m(a: 1, a: 2)", "given more than once", 9),
+            ("m(1, a: 2)", "given more than once", 6),
+            ("m(a: 1, c: 2)", "no argument named 'c'", 9),
+            ("m(b: 1)", "Missing argument 'a'", 1),
+            ("m(a: 1, 2)", "Positional argument after named", 9),
+            ("m(1, 2, 3, a: 4)", "Too many args", 9),
+        ];
+        for (call, message, column) in bad {
+            let aeon = format!("@m(a, b)\nv: {}", call);
+            let err = aeon::deserialize(aeon).expect_err(call);
+            assert!(err.message.contains(message), "{}: {}", call, err);
+            assert_eq!(column + 3, err.span.unwrap().start.column, "{}", call);
+        }
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_with_default_args() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = r#"@server(id, name, ip = "0.0.0.0", port = 8080)
+            a: server(1, "a")
+            b: server(2, "b", "1.1.1.1")
+            c: server(3, "c", port: 1)"#
+            .into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(ser.get_path("a/ip").str(), Some("0.0.0.0".into()));
+        assert_eq!(ser.get_path("a/port").int(), Some(8080));
+        assert_eq!(ser.get_path("b/ip").str(), Some("1.1.1.1".into()));
+        assert_eq!(ser.get_path("b/port").int(), Some(8080));
+        assert_eq!(ser.get_path("c/ip").str(), Some("0.0.0.0".into()));
+        assert_eq!(ser.get_path("c/port").int(), Some(1));
+    }
+
+    #[test]
+    pub fn deserialize_macro_with_bad_default_args_is_error() {
+        let err = aeon::deserialize("@m(a = 1, b)".into()).expect_err("expected error");
+        assert!(err.message.contains("'b'"), "{}", err);
+        assert_eq!(11, err.span.unwrap().start.column);
+
+        let err = aeon::deserialize("@m(a, b = 1)\nv: m()".into()).expect_err("expected error");
+        assert!(err.message.ends_with("was 0, expected 1 to 2"), "{}", err);
+        assert_eq!(
+            Some("macro 'm' is defined as @m(a, b = 1)"),
+            err.help.as_deref()
+        );
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_with_typed_args() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = r#"@server(id: int, name: string, tags: list, port: int? = 8080)
+            a: server(1, "a", ["x"])
+            b: server(2, "b", [], nil)
+            c: server(tags: [], name: "c", id: 3)"#
+            .into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(ser.get_path("a/port").int(), Some(8080));
+        assert_eq!(ser.get_path("b/port"), Some(aeon::value::AeonValue::Nil));
+        assert_eq!(ser.get_path("c/name").str(), Some("c".into()));
+    }
+
+    #[test]
+    pub fn deserialize_macro_call_with_wrong_arg_type_is_error() {
+        let bad = [
+            (
+                "m(\"1\", [])",
+                "'a' of macro m must be int, found string",
+                3,
+            ),
+            ("m(1, 2)", "'b' of macro m must be list?, found int", 6),
+            (
+                "m(1, b: { x: 1 })",
+                "'b' of macro m must be list?, found object",
+                9,
+            ),
+            (
+                "m(1, [m(nil, nil)])",
+                "'a' of macro m must be int, found nil",
+                9,
+            ),
+        ];
+        for (call, message, column) in bad {
+            let aeon = format!("@m(a: int, b: list?)\nv: {}", call);
+            let err = aeon::deserialize(aeon).expect_err(call);
+            assert!(err.message.contains(message), "{}: {}", call, err);
+            assert_eq!(column + 3, err.span.unwrap().start.column, "{}", call);
+            assert_eq!(
+                Some("macro 'm' is defined as @m(a: int, b: list?)"),
+                err.help.as_deref()
+            );
+        }
+
+        let err = aeon::deserialize("@m(a: integer)".into()).expect_err("expected error");
+        assert!(err.message.contains("Unknown type 'integer'"), "{}", err);
+        assert_eq!(7, err.span.unwrap().start.column);
+
+        let err = aeon::deserialize("@m(a: int = 1.5)".into()).expect_err("expected error");
+        assert!(err.message.contains("must be int, found double"), "{}", err);
+        assert_eq!(13, err.span.unwrap().start.column);
+    }
+
+    #[test]
+    pub fn deserialize_anchors_and_references() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let aeon = r#"@service(name, retry)
+            retry: &default_retry { tries: 3, backoff: [1, 2, 4] }
+            a: service("a", *default_retry)
+            b: service(retry: *default_retry, name: "b")
+            tls: [&cert "cert.pem", *cert]"#
+            .into();
+        let ser = aeon::deserialize(aeon).expect("failed to deserialize");
+
+        assert_eq!(ser.get_path("a/retry"), ser.get_path("retry"));
+        assert_eq!(ser.get_path("b/retry/tries").int(), Some(3));
+        let cert = aeon::value::AeonValue::String("cert.pem".into());
+        assert_eq!(
+            ser.get_path("tls"),
+            Some(aeon::value::AeonValue::List(vec![cert.clone(), cert]))
+        );
+    }
+
+    #[test]
+    pub fn deserialize_bad_references_is_error() {
+        let bad = [
+            ("a: *nope", "Unknown reference '*nope'", 4),
+            ("a: [*later, &later 1]", "Unknown reference '*later'", 5),
+            (
+                "a: &me { b: [*me] }",
+                "'*me' is inside the value it refers to",
+                14,
+            ),
+        ];
+        for (code, message, column) in bad {
+            let err = aeon::deserialize(code.into()).expect_err(code);
+            assert!(err.message.contains(message), "{}: {}", code, err);
+            assert_eq!(column, err.span.unwrap().start.column, "{}", code);
+        }
+    }
+
+    #[test]
+    pub fn deserialize_references_that_grow_exponentially_is_error() {
+        let mut code = "a0: &a0 [1, 1, 1, 1, 1, 1, 1, 1]\n".to_string();
+        for i in 1..10 {
+            code.push_str(&format!(
+                "a{}: &a{} [{}]\n",
+                i,
+                i,
+                vec![format!("*a{}", i - 1); 8].join(", ")
+            ));
+        }
+        let err = aeon::deserialize(code).expect_err("expected error");
+        assert!(
+            err.message.starts_with("References copy more than"),
+            "{}",
+            err
+        );
+    }
+
     // TODO: make it easier to implement these functions with macros?
     // TODO: i.e. in addition to the proc macros
 
@@ -110,7 +357,7 @@ mod tests {
     impl aeon::AeonSerializeProperty for NestedDerive {
         fn serialize_property(&self) -> aeon::SerializeResult<aeon::value::AeonValue> {
             use aeon::value::AeonValue;
-            let mut obj = std::collections::HashMap::<String, AeonValue>::new();
+            let mut obj = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
             obj.insert("cool".into(), self.cool.serialize_property()?);
             obj.insert("maybe_cool".into(), self.maybe_cool.serialize_property()?);
             Ok(AeonValue::Object(obj))
@@ -118,9 +365,9 @@ mod tests {
 
         fn create_property_macros(
             insert_self: bool,
-        ) -> std::collections::HashMap<String, aeon::document::AeonMacro> {
+        ) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {
             use aeon::document::AeonMacro;
-            let mut macros = std::collections::HashMap::<String, AeonMacro>::new();
+            let mut macros = aeon::ordered_map::OrderedMap::<String, AeonMacro>::new();
             if insert_self {
                 macros.insert(
                     "nestedstruct".into(),
@@ -172,7 +419,10 @@ mod tests {
                     .map(aeon::AeonDeserializeProperty::from_property)
                     .transpose()?
                     .unwrap(), //.object().unwrap(),
-                opt_hash_map: field.get("opt_hash_map").object(),
+                opt_hash_map: field
+                    .get("opt_hash_map")
+                    .object()
+                    .map(|o| o.into_iter().collect()),
                 some_hash_map_with_other_values: field
                     .get("some_hash_map_with_other_values")
                     .map(aeon::AeonDeserializeProperty::from_property)
@@ -190,7 +440,7 @@ mod tests {
     impl aeon::AeonSerializeProperty for TestDerive {
         fn serialize_property(&self) -> aeon::SerializeResult<aeon::value::AeonValue> {
             use aeon::value::AeonValue;
-            let mut obj = std::collections::HashMap::<String, AeonValue>::new();
+            let mut obj = aeon::ordered_map::OrderedMap::<String, AeonValue>::new();
             obj.insert("bytes".into(), self.bytes.serialize_property()?);
             obj.insert("some_ip".into(), self.some_ip.serialize_property()?);
             obj.insert("some_thing".into(), self.some_thing.serialize_property()?);
@@ -219,9 +469,9 @@ mod tests {
 
         fn create_property_macros(
             insert_self: bool,
-        ) -> std::collections::HashMap<String, aeon::document::AeonMacro> {
+        ) -> aeon::ordered_map::OrderedMap<String, aeon::document::AeonMacro> {
             use aeon::document::AeonMacro;
-            let mut macros = std::collections::HashMap::<String, AeonMacro>::new();
+            let mut macros = aeon::ordered_map::OrderedMap::<String, AeonMacro>::new();
             if insert_self {
                 macros.insert(
                     "testderive".into(),
@@ -265,7 +515,7 @@ mod tests {
             some_hash_map: map!("summits".into() => AeonValue::Integer(987)),
             opt_hash_map: None,
             some_hash_map_with_other_values: map!("thingy".into() => NestedDerive {
-                cool: 001,
+                cool: 1,
                 maybe_cool: None
             }),
             nested_struct: NestedDerive {
@@ -327,6 +577,6 @@ mod tests {
                 .int()
                 .unwrap()
         );
-        assert!(matches!(test.opt_hash_map, None));
+        assert!(test.opt_hash_map.is_none());
     }
 }
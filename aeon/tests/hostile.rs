@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod tests {
+    use aeon::convert::AeonObjectConvert;
+    use aeon::cst::CstDocument;
+    use aeon::value::AeonValue;
+    use aeon::AeonDeserializeProperty;
+    use std::collections::HashMap;
+    use std::panic::catch_unwind;
+
+    /// Malformed input that must be rejected with an error instead of a panic
+    const HOSTILE: &[&str] = &[
+        // numbers
+        "n: -",
+        "n: - 1",
+        "n: --1",
+        "n: 1_",
+        "n: 1__000",
+        "n: 1_.5",
+        "n: 1.",
+        "n: 1.2.3",
+        "n: -.",
+        "n: 9223372036854775808",
+        "n: -9223372036854775809",
+        "n: 99999999999999999999999999999999999999",
+        // strings
+        "s: \"",
+        "s: \"unterminated",
+        "s: \"\\",
+        "s: \"\\q\"",
+        "s: \"\\u\"",
+        "s: \"\\u{\"",
+        "s: \"\\u{12\"",
+        "s: \"\\u{123456}\"",
+        "s: \"\\u{zzzz}\"",
+        "s: \"\\u{d800}\"",
+        // macros
+        "@",
+        "@(",
+        "@m",
+        "@m(",
+        "@m(a",
+        "@m(a,",
+        "@m(1)",
+        "@m(a b)",
+        "v: m(1)",
+        "@m(a) v: m()",
+        "@m(a) v: m(1, 2)",
+        "@m(a) v: m(1",
+        "@m(a) v: m(1 2)",
+        "@m(a) v: m",
+        "v: m",
+        // anchors and references
+        "a: &",
+        "a: *",
+        "a: &1 2",
+        "a: &x",
+        "a: &nil 1",
+        "a: *x",
+        "a: &x [*x]",
+        "a: &x &y { b: *y }",
+        "a: &x [1] &y 2",
+        // lists and maps
+        "l: [",
+        "l: [1",
+        "l: [1,",
+        "l: [1 2]",
+        "l: [,]",
+        "l: ]",
+        "m: {",
+        "m: {a",
+        "m: {a:",
+        "m: {a: 1",
+        "m: {a 1}",
+        "m: {1: 1}",
+        "m: {a: 1 b: 2}",
+        "m: }",
+        // top level
+        ":",
+        "1",
+        "\"key\": 1",
+        "a",
+        "a:",
+        "a 1",
+        "a: :",
+        "a: )",
+        "$",
+        "a: 1 ;",
+        "\u{0}",
+        "a: \u{feff}1",
+    ];
+
+    /// Lists, maps and macro calls closed by the wrong delimiter
+    const MISMATCHED: &[&str] = &[
+        "l: [1, 2}",
+        "l: [1, 2)",
+        "l: [}",
+        "m: {a: 1]",
+        "m: {a: 1)",
+        "m: {]",
+        "@m(a) v: m(1]",
+        "@m(a) v: m(1}",
+        "l: [{a: 1]]",
+    ];
+
+    fn assert_rejected(input: &str) {
+        let owned = input.to_string();
+        match catch_unwind(move || aeon::deserialize(owned).is_err()) {
+            Ok(true) => (),
+            Ok(false) => panic!("expected {:?} to be rejected", input),
+            Err(_) => panic!("deserializing {:?} panicked", input),
+        }
+    }
+
+    #[test]
+    pub fn hostile_inputs_are_rejected_without_panicking() {
+        for input in HOSTILE {
+            assert_rejected(input);
+        }
+    }
+
+    #[test]
+    pub fn mismatched_delimiters_are_rejected_by_both_parsers() {
+        for input in MISMATCHED {
+            assert_rejected(input);
+            let err = CstDocument::parse(input.to_string()).unwrap_err();
+            assert!(err.message.contains("to close the"), "{}", err);
+        }
+    }
+
+    #[test]
+    pub fn every_prefix_of_valid_input_is_handled_without_panicking() {
+        let valid = "@server(id, name, ip, port)\n\
+                     # comment\n\
+                     servers: [server(1, \"t\\u{2714}\", \"127.0.0.1\", 7_171), server(2, \"p\", \"0.0.0.0\", -8080)]\n\
+                     map: {a: 1.5, \"b c\": [nil, true, false], d: {}}\n";
+        for (idx, _) in valid.char_indices() {
+            let prefix = valid[..idx].to_string();
+            if catch_unwind(move || aeon::deserialize(prefix)).is_err() {
+                panic!("deserializing {:?} panicked", &valid[..idx]);
+            }
+        }
+        assert!(aeon::deserialize(valid.to_string()).is_ok());
+    }
+
+    #[test]
+    pub fn deeply_nested_input_is_rejected() {
+        for open in ["[", "{a: ", "@m(a) v: m("] {
+            let input = format!("v: {}", open.repeat(100_000));
+            assert_rejected(&input);
+        }
+        assert!(aeon::deserialize(format!("v: {}{}", "[".repeat(100), "]".repeat(100))).is_ok());
+    }
+
+    #[test]
+    pub fn macro_errors_point_at_the_call() {
+        let err = aeon::deserialize("@m(a, b)\nv: m(1)".into()).unwrap_err();
+        let span = err.span.unwrap();
+        assert_eq!((2, 4), (span.start.line, span.start.column));
+        assert_eq!(4, span.len());
+        assert!(err.message.contains("Wrong number of args"), "{}", err);
+
+        let err = aeon::deserialize("v: [unknown(1)]".into()).unwrap_err();
+        assert_eq!(5, err.span.unwrap().start.column);
+        assert!(err.message.contains("does not exist"), "{}", err);
+    }
+
+    #[test]
+    pub fn conversion_failures_are_errors() {
+        let doc = aeon::deserialize("big: 70000\nneg: -1\ntext: \"t\"".into()).unwrap();
+        assert!(u16::from_property(doc.get("big").unwrap()).is_err());
+        assert!(u64::from_property(doc.get("neg").unwrap()).is_err());
+        assert!(i64::from_property(doc.get("text").unwrap()).is_err());
+        assert!(Vec::<i32>::from_property(doc.get("text").unwrap()).is_err());
+        assert!(HashMap::<String, i32>::from_property(doc.get("big").unwrap()).is_err());
+        assert!(aeon::convert::expected::<i32>(doc.get("missing")).is_err());
+        assert_eq!(
+            70000,
+            aeon::convert::expected::<i32>(doc.get("big")).unwrap()
+        );
+        assert!(matches!(
+            aeon::convert::maybe::<i32>(doc.get("missing")),
+            Ok(None)
+        ));
+        assert!(Option::<u8>::from_property(AeonValue::Integer(256)).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use aeon::convert::{AeonConvert, AeonObjectConvert};
+    use aeon::include::{FileResolver, IncludeResolver, IncludeSource};
+    use std::collections::HashMap;
+
+    /// Resolves includes from documents kept in memory
+    struct MapResolver(HashMap<&'static str, &'static str>);
+
+    impl IncludeResolver for MapResolver {
+        fn resolve(&self, path: &str, _from: Option<&str>) -> Result<IncludeSource, String> {
+            self.0
+                .get(path)
+                .map(|source| IncludeSource::new(path, *source))
+                .ok_or_else(|| "no such document".to_string())
+        }
+    }
+
+    fn documents(documents: &[(&'static str, &'static str)]) -> MapResolver {
+        MapResolver(documents.iter().copied().collect())
+    }
+
+    #[test]
+    pub fn include_adds_macros_and_properties() {
+        let resolver = documents(&[
+            ("macros.aeon", "@server(id, port = 8080)"),
+            (
+                "base.aeon",
+                "@include(\"macros.aeon\")\nname: \"base\"\nport: 1",
+            ),
+        ]);
+        let code = "@include(\"base.aeon\")\nport: 2\nmain: server(1)";
+        let aeon = aeon::deserialize_with_resolver(code.into(), &resolver).unwrap();
+
+        assert_eq!(aeon.get_path("name").str(), Some("base".into()));
+        assert_eq!(aeon.get_path("port").int(), Some(2));
+        assert_eq!(aeon.get_path("main/port").int(), Some(8080));
+        assert!(aeon.macros.contains_key("server"));
+    }
+
+    #[test]
+    pub fn include_cycle_is_error() {
+        let resolver = documents(&[
+            ("a.aeon", "@include(\"b.aeon\")"),
+            ("b.aeon", "x: 1\n@include(\"a.aeon\")"),
+        ]);
+        let err = aeon::include::load("a.aeon", &resolver).unwrap_err();
+        assert_eq!("Include cycle: a.aeon -> b.aeon -> a.aeon", err.message);
+        assert_eq!(["a.aeon", "b.aeon"], err.include_chain());
+        assert_eq!(2, err.span.unwrap().start.line);
+    }
+
+    #[test]
+    pub fn include_errors_report_the_include_chain() {
+        let resolver = documents(&[
+            ("a.aeon", "@include(\"b.aeon\")"),
+            ("b.aeon", "\n\nx: [1 2]"),
+        ]);
+        let err = aeon::include::load("a.aeon", &resolver).unwrap_err();
+        assert_eq!(["a.aeon", "b.aeon"], err.include_chain());
+        assert_eq!(3, err.span.unwrap().start.line);
+        assert!(
+            err.to_string()
+                .ends_with(" in b.aeon, included from a.aeon"),
+            "{}",
+            err
+        );
+        let rendered = aeon::diagnostic::Diagnostic::new(&err, "\n\nx: [1 2]").render();
+        assert!(rendered.contains("--> b.aeon:3:7"), "{}", rendered);
+        assert!(
+            rendered.contains("= note: included from a.aeon"),
+            "{}",
+            rendered
+        );
+
+        let resolver = documents(&[("a.aeon", "@include(\"c\")")]);
+        let err = aeon::include::load("a.aeon", &resolver).unwrap_err();
+        assert_eq!("Could not include 'c': no such document", err.message);
+        assert_eq!(["a.aeon"], err.include_chain());
+    }
+
+    #[test]
+    pub fn include_without_resolver_is_error() {
+        let err = aeon::deserialize("@include(\"a.aeon\")".into()).unwrap_err();
+        assert!(
+            err.message.contains("without an include resolver"),
+            "{}",
+            err
+        );
+        assert_eq!(10, err.span.unwrap().start.column);
+    }
+
+    #[test]
+    pub fn file_resolver_stays_inside_base_directory() {
+        let dir = std::env::temp_dir().join(format!("aeon-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("base/shared")).unwrap();
+        std::fs::write(dir.join("base/main.aeon"), "@include(\"shared/a.aeon\")").unwrap();
+        std::fs::write(dir.join("base/shared/a.aeon"), "@include(\"b.aeon\")\na: 1").unwrap();
+        std::fs::write(dir.join("base/shared/b.aeon"), "b: 2").unwrap();
+        std::fs::write(dir.join("base/escape.aeon"), "@include(\"../secret.aeon\")").unwrap();
+        std::fs::write(dir.join("secret.aeon"), "secret: 3").unwrap();
+
+        let resolver = FileResolver::new(dir.join("base"));
+        let aeon = aeon::include::load("main.aeon", &resolver).unwrap();
+        assert_eq!(aeon.get_path("a").int(), Some(1));
+        assert_eq!(aeon.get_path("b").int(), Some(2));
+
+        let err = aeon::include::load("escape.aeon", &resolver).unwrap_err();
+        assert!(
+            err.message.contains("outside of the base directory"),
+            "{}",
+            err
+        );
+
+        let resolver = FileResolver::new(dir.join("base")).restrict_to_base(false);
+        let aeon = aeon::include::load("escape.aeon", &resolver).unwrap();
+        assert_eq!(aeon.get_path("secret").int(), Some(3));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -130,6 +130,34 @@ mod tests {
         assert!(serialized.contains(','));
     }
 
+    #[test]
+    pub fn serialize_identifiers_with_dashes() {
+        let mut aeon = AeonDocument::new();
+        aeon.add_macro(AeonMacro::new("m".into(), vec!["a-b".into()]));
+        aeon.add_property(
+            "x",
+            AeonValue::Object(map!["a-b".into() => AeonValue::Integer(1)]),
+        );
+        aeon.add_property(
+            "y-z",
+            AeonValue::Object(map![
+               "a-".into() => AeonValue::Integer(2),
+               "a--b".into() => AeonValue::Integer(3),
+            ]),
+        );
+        let serialized = aeon::serialize(&aeon).ok().unwrap();
+        assert!(
+            like(
+                r#"@m(a-b) x: m(1) y-z: { "a-": 2, "a--b": 3 }"#,
+                serialized.as_str()
+            ),
+            "{}",
+            serialized
+        );
+        let again = aeon::deserialize(serialized.clone()).unwrap();
+        assert_eq!(serialized, aeon::serialize(&again).unwrap());
+    }
+
     #[test]
     pub fn serialize_list_of_strings_property() {
         let mut aeon = AeonDocument::new();
@@ -183,4 +211,176 @@ mod tests {
         let ser = aeon::serialize(&aeon).ok().unwrap();
         assert!(like("@character(name, world)\n", ser.as_str()));
     }
+
+    #[test]
+    pub fn serialize_keeps_source_order() {
+        let code =
+            "@zebra(b, a)\n@apple(x)\nzulu: {y: 1, b: 2, m: 3}\nalpha: 1\nmike: [apple(1)]\n";
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let ser = aeon::serialize(&aeon).unwrap();
+        assert!(
+            like(
+                "@zebra(b, a) @apple(x) zulu: {y: 1, b: 2, m: 3} alpha: 1 mike: [apple(1)]",
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+        assert_eq!(
+            ser,
+            aeon::serialize(&aeon::deserialize(ser.clone()).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn serialize_with_sorted_keys() {
+        let code = "@zebra(b, a)\n@apple(x)\nzulu: {y: 1, b: 2, m: 3}\nalpha: 1\n";
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let options = aeon::SerializeOptions {
+            sort_keys: true,
+            ..Default::default()
+        };
+        let ser = aeon::serialize_with_options(&aeon, options).unwrap();
+        assert!(
+            like(
+                "@apple(x) @zebra(b, a) alpha: 1 zulu: {b: 2, m: 3, y: 1}",
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+    }
+
+    #[test]
+    pub fn serialize_uses_macro_that_created_the_value() {
+        let code =
+            "@point(x, y)\n@size(x, y)\nat: point(1, 2)\nbig: size(3, 4)\nplain: {x: 5, y: 6}\n";
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let ser = aeon::serialize(&aeon).unwrap();
+        assert!(
+            like(
+                "@point(x, y) @size(x, y) at: point(1, 2) big: size(3, 4) plain: point(5, 6)",
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+    }
+    #[test]
+    pub fn serialize_wide_macros_with_named_args() {
+        use aeon::convert::{AeonConvert, AeonObjectConvert};
+        let code =
+            "@point(x, y)\n@server(id, name, port)\nat: point(1, 2)\ns: server(1, \"a\", 80)\n";
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let options = aeon::SerializeOptions {
+            named_args_threshold: Some(3),
+            ..Default::default()
+        };
+        let ser = aeon::serialize_with_options(&aeon, options).unwrap();
+        assert!(
+            like(
+                "@point(x, y) @server(id, name, port) at: point(1, 2) s: server(id: 1, name: \"a\", port: 80)",
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+        let again = aeon::deserialize(ser).unwrap();
+        assert_eq!(Some(80), again.get_path("s/port").int());
+    }
+    #[test]
+    pub fn serialize_leaves_out_trailing_default_args() {
+        let code = r#"@server(id, name, ip = "0.0.0.0", port = 8080)
+            a: server(1, "a")
+            b: server(2, "b", "1.1.1.1", 8080)
+            c: server(3, "c", port: 1)"#;
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let ser = aeon::serialize(&aeon).unwrap();
+        assert!(
+            like(
+                r#"@server(id, name, ip = "0.0.0.0", port = 8080)
+                a: server(1, "a")
+                b: server(2, "b", "1.1.1.1")
+                c: server(3, "c", "0.0.0.0", 1)"#,
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+        let options = aeon::SerializeOptions {
+            named_args_threshold: Some(1),
+            ..Default::default()
+        };
+        let ser = aeon::serialize_with_options(&aeon, options).unwrap();
+        assert!(
+            ser.contains(r#"c: server(id: 3, name: "c", port: 1)"#),
+            "{}",
+            ser
+        );
+        let again = aeon::deserialize(ser).unwrap();
+        assert_eq!(aeon.properties["c"].value, again.properties["c"].value);
+    }
+
+    #[test]
+    pub fn serialize_typed_macro() {
+        let code = r#"@server(id: int, port: int? = 8080)
+            a: server(1)
+            b: { id: "not an int", port: 1 }"#;
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let ser = aeon::serialize(&aeon).unwrap();
+        assert!(
+            like(
+                r#"@server(id: int, port: int? = 8080)
+                a: server(1)
+                b: {
+                    id: "not an int",
+                    port: 1
+                }"#,
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+        assert!(aeon::deserialize(ser).is_ok());
+    }
+
+    #[test]
+    pub fn serialize_repeated_values_as_references() {
+        let code = r#"@server(name, tls)
+            retry: { tries: 3, backoff: [1, 2] }
+            a: server("a", { cert: "a.pem", retry: { tries: 3, backoff: [1, 2] } })
+            b: server("b", { cert: "a.pem", retry: { tries: 3, backoff: [1, 2] } })
+            c: [1, 2]"#;
+        let aeon = aeon::deserialize(code.into()).unwrap();
+        let options = aeon::SerializeOptions {
+            anchors: true,
+            ..Default::default()
+        };
+        let ser = aeon::serialize_with_options(&aeon, options).unwrap();
+        assert!(
+            like(
+                r#"@server(name, tls)
+                retry: &retry {
+                    tries: 3,
+                    backoff: &backoff [
+                        1,
+                        2
+                    ]
+                }
+                a: server("a", &tls {
+                    cert: "a.pem",
+                    retry: *retry
+                })
+                b: server("b", *tls)
+                c: *backoff"#,
+                ser.as_str()
+            ),
+            "{}",
+            ser
+        );
+        let again = aeon::deserialize(ser).unwrap();
+        for prop in ["retry", "a", "b", "c"] {
+            assert_eq!(aeon.properties[prop].value, again.properties[prop].value);
+        }
+    }
 }